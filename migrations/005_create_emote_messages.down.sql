@@ -0,0 +1 @@
+DROP TABLE emote_messages;
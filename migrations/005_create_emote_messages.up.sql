@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS emote_messages (
+	xiv_id int NOT NULL,
+	language int NOT NULL,
+	command varchar(30),
+	alias varchar(30),
+	targeted text NOT NULL,
+	untargeted text NOT NULL,
+	insert_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	update_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (xiv_id, language),
+	FOREIGN KEY (xiv_id) REFERENCES emotes (xiv_id)
+);
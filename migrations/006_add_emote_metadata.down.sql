@@ -0,0 +1,5 @@
+DROP TABLE emote_commands;
+ALTER TABLE emote_messages DROP COLUMN name;
+ALTER TABLE emotes DROP COLUMN sort_order;
+ALTER TABLE emotes DROP COLUMN category;
+ALTER TABLE emotes RENAME COLUMN name TO command;
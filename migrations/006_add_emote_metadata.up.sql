@@ -0,0 +1,12 @@
+ALTER TABLE emotes RENAME COLUMN command TO name;
+ALTER TABLE emotes ADD category int;
+ALTER TABLE emotes ADD sort_order int NOT NULL DEFAULT 0;
+ALTER TABLE emote_messages ADD name text NOT NULL DEFAULT '';
+
+CREATE TABLE IF NOT EXISTS emote_commands (
+	command varchar(30) PRIMARY KEY,
+	xiv_id int NOT NULL,
+	insert_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	update_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	FOREIGN KEY (xiv_id) REFERENCES emotes (xiv_id)
+);
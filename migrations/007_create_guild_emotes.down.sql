@@ -0,0 +1,7 @@
+DELETE FROM emote_log_tags WHERE emote_log_id IN (
+	SELECT emote_log_id FROM emote_logs WHERE emote_xiv_id IS NULL
+);
+DELETE FROM emote_logs WHERE emote_xiv_id IS NULL;
+ALTER TABLE emote_logs DROP COLUMN guild_emote_id;
+ALTER TABLE emote_logs ALTER COLUMN emote_xiv_id SET NOT NULL;
+DROP TABLE guild_emotes;
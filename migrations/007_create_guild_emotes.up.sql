@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS guild_emotes (
+	guild_emote_id bigserial PRIMARY KEY,
+	guild_id bigint NOT NULL,
+	command varchar(30) NOT NULL,
+	targeted text NOT NULL,
+	untargeted text NOT NULL,
+	insert_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	update_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	UNIQUE (guild_id, command),
+	FOREIGN KEY (guild_id) REFERENCES guilds (guild_id)
+);
+
+-- logs of guild emotes have no xiv id, and keep counting towards totals after the emote is deleted
+ALTER TABLE emote_logs ALTER COLUMN emote_xiv_id DROP NOT NULL;
+ALTER TABLE emote_logs ADD guild_emote_id bigint;
+ALTER TABLE emote_logs ADD FOREIGN KEY (guild_emote_id) REFERENCES guild_emotes (guild_emote_id) ON DELETE SET NULL;
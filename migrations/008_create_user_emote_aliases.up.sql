@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS user_emote_aliases (
+	user_emote_alias_id bigserial PRIMARY KEY,
+	user_id bigint NOT NULL,
+	alias varchar(30) NOT NULL,
+	command text NOT NULL,
+	insert_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	update_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	UNIQUE (user_id, alias),
+	FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE guilds DROP multi_target_mode;
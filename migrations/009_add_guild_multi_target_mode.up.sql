@@ -0,0 +1 @@
+ALTER TABLE guilds ADD multi_target_mode int NOT NULL DEFAULT 0;
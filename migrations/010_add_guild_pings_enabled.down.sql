@@ -0,0 +1 @@
+ALTER TABLE guilds DROP pings_enabled;
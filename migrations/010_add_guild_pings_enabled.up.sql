@@ -0,0 +1 @@
+ALTER TABLE guilds ADD pings_enabled boolean NOT NULL DEFAULT true;
@@ -0,0 +1,2 @@
+DROP TABLE channels;
+ALTER TABLE guilds DROP send_mode;
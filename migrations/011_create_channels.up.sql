@@ -0,0 +1,12 @@
+ALTER TABLE guilds ADD send_mode int NOT NULL DEFAULT 0;
+
+-- settings left NULL use the guild's
+CREATE TABLE IF NOT EXISTS channels (
+	channel_id bigserial PRIMARY KEY,
+	discord_id char(20) NOT NULL UNIQUE,
+	guild_id bigint NOT NULL,
+	send_mode int,
+	insert_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	update_tm timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	FOREIGN KEY (guild_id) REFERENCES guilds (guild_id)
+);
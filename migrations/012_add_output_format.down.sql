@@ -0,0 +1,2 @@
+ALTER TABLE guilds DROP output_format;
+ALTER TABLE users DROP output_format;
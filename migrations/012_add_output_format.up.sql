@@ -0,0 +1,3 @@
+-- users leave it NULL to use their guild's
+ALTER TABLE users ADD output_format int;
+ALTER TABLE guilds ADD output_format int NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- in minutes, for timestamps in the game log output format
+ALTER TABLE users ADD utc_offset int NOT NULL DEFAULT 0;
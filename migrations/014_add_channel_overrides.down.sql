@@ -0,0 +1,5 @@
+ALTER TABLE channels DROP prefix_emotes_enabled;
+ALTER TABLE channels DROP output_format;
+ALTER TABLE channels DROP prefix;
+ALTER TABLE channels DROP gender;
+ALTER TABLE channels DROP language;
@@ -0,0 +1,6 @@
+-- like send_mode, settings left NULL use the guild's
+ALTER TABLE channels ADD language int;
+ALTER TABLE channels ADD gender int;
+ALTER TABLE channels ADD prefix varchar(5);
+ALTER TABLE channels ADD output_format int;
+ALTER TABLE channels ADD prefix_emotes_enabled boolean NOT NULL DEFAULT true;
@@ -0,0 +1,2 @@
+ALTER TABLE guilds DROP channel_filter;
+ALTER TABLE guilds DROP channel_filter_mode;
@@ -0,0 +1,3 @@
+-- channels and categories emotes are limited to or kept out of, depending on the mode
+ALTER TABLE guilds ADD channel_filter_mode int NOT NULL DEFAULT 0;
+ALTER TABLE guilds ADD channel_filter text[] NOT NULL DEFAULT '{}';
@@ -1,6 +1,6 @@
 use std::env;
 
-use discord_xiv_emotes::setup_client;
+use discord_xiv_emotes::{catalog::CatalogConfig, setup_client};
 use dotenvy::dotenv;
 use sqlx::PgPool;
 use tracing::*;
@@ -20,7 +20,9 @@ async fn main() {
         .await
         .expect("could not connect to database");
     info!("connected to db at {}", db_url);
-    let mut client = setup_client(token, pool).await;
+    let catalog_config = CatalogConfig::from_env();
+    debug!(?catalog_config, "loaded catalog config");
+    let mut client = setup_client(token, pool, catalog_config).await;
 
     client.start().await.expect("couldn't start client");
 }
@@ -0,0 +1,672 @@
+//! The emote catalog, along with the raw emote data it's parsed from as loaded from an
+//! [EmoteSource]
+
+pub mod emotes_table;
+pub mod fallback;
+#[cfg(test)]
+pub mod fixture;
+pub mod markup;
+pub mod sheets;
+pub mod snapshot;
+pub mod xivapi;
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    fmt::Debug,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use tracing::*;
+use xiv_emote_parser::log_message::parser::{extract_condition_texts, ConditionTexts};
+
+use crate::{
+    db::{
+        models::{DbEmoteCategory, DbLanguage},
+        Db,
+    },
+    handler::{ConditionTextPair, EmoteData},
+    HandlerError,
+};
+
+use self::{
+    emotes_table::EmotesTableSource, fallback::FallbackTemplates, sheets::SheetsSource,
+    snapshot::SnapshotSource, xivapi::XivapiSource,
+};
+
+pub const DEFAULT_SNAPSHOT_PATH: &str = "emote_snapshot.json";
+pub const DEFAULT_SHEETS_PATH: &str = "sheets";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawEmoteData {
+    pub id: u32,
+    pub name: String,
+    /// Row id in the game's EmoteCategory sheet, if the emote has one
+    pub category: Option<u32>,
+    /// Where the emote is placed within its category in the game's emote list
+    pub order: u32,
+    pub en: RawEmoteTexts,
+    pub ja: RawEmoteTexts,
+    pub de: RawEmoteTexts,
+    pub fr: RawEmoteTexts,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawEmoteTexts {
+    /// Localized display name of the emote
+    pub name: String,
+    pub command: Option<String>,
+    pub alias: Option<String>,
+    pub targeted: String,
+    pub untargeted: String,
+}
+
+/// How an emote defines a command or alias, in order of precedence for when several emotes share
+/// the same one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CommandKind {
+    /// An English or Japanese command
+    Command,
+    /// An English or Japanese alias
+    Alias,
+    /// A German or French command
+    SecondaryCommand,
+    /// A German or French alias
+    SecondaryAlias,
+}
+
+impl RawEmoteData {
+    /// All non-empty commands and aliases that should resolve to this emote, along with how they
+    /// are defined
+    pub fn commands(&self) -> impl Iterator<Item = (CommandKind, &String)> {
+        [
+            (CommandKind::Command, &self.en.command),
+            (CommandKind::Command, &self.ja.command),
+            (CommandKind::Alias, &self.en.alias),
+            (CommandKind::Alias, &self.ja.alias),
+            (CommandKind::SecondaryCommand, &self.de.command),
+            (CommandKind::SecondaryCommand, &self.fr.command),
+            (CommandKind::SecondaryAlias, &self.de.alias),
+            (CommandKind::SecondaryAlias, &self.fr.alias),
+        ]
+        .into_iter()
+        .filter_map(|(kind, cmd)| cmd.as_ref().map(|cmd| (kind, cmd)))
+        .filter(|(_, cmd)| !cmd.is_empty())
+    }
+}
+
+/// An emote that defines a command or alias
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOwner {
+    pub id: u32,
+    pub name: String,
+    pub kind: CommandKind,
+}
+
+/// A command or alias that more than one emote defines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandCollision {
+    pub command: String,
+    /// The emote that the command resolves to
+    pub winner: CommandOwner,
+    /// The other emotes that define the command, in order of precedence
+    pub losers: Vec<CommandOwner>,
+}
+
+impl CommandCollision {
+    /// Whether the winner was only decided by emote id, rather than by a command beating an alias
+    /// or a German or French command giving way to an English or Japanese one
+    pub fn is_ambiguous(&self) -> bool {
+        self.losers
+            .iter()
+            .any(|loser| loser.kind == self.winner.kind)
+    }
+}
+
+/// Decides which emote each command resolves to, returning the id of the emote for every command
+/// along with any collisions. A command beats an alias and English or Japanese beat German or
+/// French, with the lowest emote id winning between emotes that define a command the same way.
+pub fn resolve_commands(
+    raw_emotes: &[RawEmoteData],
+) -> (HashMap<String, u32>, Vec<CommandCollision>) {
+    let mut owners: BTreeMap<&String, Vec<CommandOwner>> = BTreeMap::new();
+    for raw in raw_emotes {
+        for (kind, cmd) in raw.commands() {
+            let cmd_owners = owners.entry(cmd).or_default();
+            // an emote can define the same command in several languages
+            match cmd_owners.iter_mut().find(|owner| owner.id == raw.id) {
+                Some(owner) => owner.kind = owner.kind.min(kind),
+                None => cmd_owners.push(CommandOwner {
+                    id: raw.id,
+                    name: raw.name.clone(),
+                    kind,
+                }),
+            }
+        }
+    }
+
+    let mut resolved = HashMap::new();
+    let mut collisions = Vec::new();
+    for (cmd, mut cmd_owners) in owners {
+        cmd_owners.sort_by_key(|owner| (owner.kind, owner.id));
+        let winner = cmd_owners.remove(0);
+        resolved.insert(cmd.clone(), winner.id);
+        if !cmd_owners.is_empty() {
+            collisions.push(CommandCollision {
+                command: cmd.clone(),
+                winner,
+                losers: cmd_owners,
+            });
+        }
+    }
+    (resolved, collisions)
+}
+
+/// Parsed emotes keyed by every command and alias that resolves to them
+#[derive(Debug, Default)]
+pub struct EmoteCatalog {
+    emotes: HashMap<String, Arc<EmoteData>>,
+    raw: Vec<RawEmoteData>,
+    collisions: Vec<CommandCollision>,
+}
+
+impl EmoteCatalog {
+    /// Emotes without English and Japanese messages get messages built from `fallback` instead
+    #[instrument(level = "trace", skip(raw_emotes))]
+    pub fn new(
+        raw_emotes: Vec<RawEmoteData>,
+        fallback: &FallbackTemplates,
+    ) -> Result<EmoteCatalog, HandlerError> {
+        let parsed = raw_emotes
+            .iter()
+            .map(|raw| {
+                trace!("processing raw emote data: {:?}", raw);
+                let category = raw
+                    .category
+                    .and_then(|category| DbEmoteCategory::from_repr(category as i32));
+                let has_messages = [
+                    &raw.en.targeted,
+                    &raw.en.untargeted,
+                    &raw.ja.targeted,
+                    &raw.ja.untargeted,
+                ]
+                .into_iter()
+                .all(|text| !text.is_empty());
+                if !has_messages {
+                    trace!(raw.name, raw.id, "using fallback messages");
+                    let data = Arc::new(EmoteData {
+                        id: raw.id,
+                        name: raw.name.clone(),
+                        category,
+                        order: raw.order,
+                        synthetic: true,
+                        guild_emote_id: None,
+                        en: fallback.messages(raw, DbLanguage::En),
+                        ja: fallback.messages(raw, DbLanguage::Ja),
+                        de: Some(fallback.messages(raw, DbLanguage::De)),
+                        fr: Some(fallback.messages(raw, DbLanguage::Fr)),
+                    });
+                    return Ok((raw.id, data));
+                }
+                let data = Arc::new(EmoteData {
+                    id: raw.id,
+                    name: raw.name.clone(),
+                    category,
+                    order: raw.order,
+                    synthetic: false,
+                    guild_emote_id: None,
+                    en: ConditionTextPair {
+                        targeted: extract(raw, &raw.en.targeted, "en->targeted")?,
+                        untargeted: extract(raw, &raw.en.untargeted, "en->untargeted")?,
+                    },
+                    ja: ConditionTextPair {
+                        targeted: extract(raw, &raw.ja.targeted, "ja->targeted")?,
+                        untargeted: extract(raw, &raw.ja.untargeted, "ja->untargeted")?,
+                    },
+                    de: extract_lowered(raw, &raw.de, "de"),
+                    fr: extract_lowered(raw, &raw.fr, "fr"),
+                });
+                Ok((raw.id, data))
+            })
+            .collect::<Result<HashMap<_, _>, HandlerError>>()?;
+
+        let (resolved, collisions) = resolve_commands(&raw_emotes);
+        for collision in &collisions {
+            let losers: Vec<_> = collision.losers.iter().map(|loser| &loser.name).collect();
+            if collision.is_ambiguous() {
+                warn!(
+                    collision.command,
+                    collision.winner.name,
+                    ?losers,
+                    "command is defined by several emotes"
+                );
+            } else {
+                debug!(
+                    collision.command,
+                    collision.winner.name,
+                    ?losers,
+                    "command is defined by several emotes, resolved by precedence"
+                );
+            }
+        }
+        let emotes = resolved
+            .into_iter()
+            .filter_map(|(cmd, id)| {
+                let data = parsed.get(&id)?;
+                trace!("{} => {}", cmd, data.name);
+                Some((cmd, data.clone()))
+            })
+            .collect();
+
+        Ok(EmoteCatalog {
+            emotes,
+            raw: raw_emotes,
+            collisions,
+        })
+    }
+
+    pub fn get(&self, command: &str) -> Option<&Arc<EmoteData>> {
+        self.emotes.get(command)
+    }
+
+    pub fn contains(&self, command: &str) -> bool {
+        self.emotes.contains_key(command)
+    }
+
+    /// Every command and alias along with the emote they resolve to, ordered by its id
+    pub fn commands_by_id(&self) -> Vec<(&String, &Arc<EmoteData>)> {
+        let mut values: Vec<_> = self.emotes.iter().collect();
+        values.sort_unstable_by_key(|(_, v)| v.id);
+        values
+    }
+
+    /// Every command and alias of the emotes in a category, in the order the game lists them
+    pub fn commands_in_category(
+        &self,
+        category: DbEmoteCategory,
+    ) -> Vec<(&String, &Arc<EmoteData>)> {
+        let mut values: Vec<_> = self
+            .emotes
+            .iter()
+            .filter(|(_, v)| v.category == Some(category))
+            .collect();
+        values.sort_unstable_by_key(|(_, v)| (v.order, v.id));
+        values
+    }
+
+    /// Every command and alias along with the emote it resolves to, in no particular order
+    pub fn commands(&self) -> impl Iterator<Item = (&String, &Arc<EmoteData>)> {
+        self.emotes.iter()
+    }
+
+    /// How many emotes can be used, which leaves out any whose every command resolves to another
+    /// emote
+    pub fn emote_count(&self) -> usize {
+        self.emotes
+            .values()
+            .map(|data| data.id)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    pub fn raw(&self) -> &[RawEmoteData] {
+        &self.raw
+    }
+
+    pub fn raw_emote(&self, id: u32) -> Option<&RawEmoteData> {
+        self.raw.iter().find(|raw| raw.id == id)
+    }
+
+    /// Every command that more than one emote defines, ordered by command
+    pub fn collisions(&self) -> &[CommandCollision] {
+        &self.collisions
+    }
+
+    /// Compares the raw data of this catalog against a newer one by emote id
+    pub fn diff(&self, newer: &EmoteCatalog) -> CatalogDiff {
+        let old: BTreeMap<_, _> = self.raw.iter().map(|raw| (raw.id, raw)).collect();
+        let new: BTreeMap<_, _> = newer.raw.iter().map(|raw| (raw.id, raw)).collect();
+        let mut diff = CatalogDiff::default();
+        for (id, raw) in &new {
+            match old.get(id) {
+                None => diff.added.push(raw.name.clone()),
+                Some(old_raw) if old_raw != raw => diff.changed.push(raw.name.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|(id, _)| !new.contains_key(id))
+            .map(|(_, raw)| raw.name.clone())
+            .collect();
+        diff
+    }
+}
+
+fn extract(raw: &RawEmoteData, text: &str, kind: &str) -> Result<ConditionTexts, HandlerError> {
+    extract_condition_texts(text).map_err(|e| {
+        error!(
+            raw.name,
+            raw.id, "could not extract condition texts for {}", kind
+        );
+        e.into()
+    })
+}
+
+/// Parses German or French texts after [lowering](markup::lower) them, or returns `None` if the
+/// language is missing from the source. Markup the lowering doesn't cover is only logged, so that
+/// the emote still works in English and Japanese.
+fn extract_lowered(
+    raw: &RawEmoteData,
+    texts: &RawEmoteTexts,
+    lang: &str,
+) -> Option<ConditionTextPair> {
+    if texts.targeted.is_empty() || texts.untargeted.is_empty() {
+        trace!(raw.name, raw.id, lang, "no messages for language");
+        return None;
+    }
+    let extract_lowered = |text: &str| {
+        extract_condition_texts(&markup::lower(text))
+            .map_err(|e| {
+                warn!(
+                    raw.name,
+                    raw.id, lang, "could not extract condition texts: {}", e
+                )
+            })
+            .ok()
+    };
+    Some(ConditionTextPair {
+        targeted: extract_lowered(&texts.targeted)?,
+        untargeted: extract_lowered(&texts.untargeted)?,
+    })
+}
+
+/// Names of the emotes that differ between two catalogs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CatalogDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl CatalogDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Somewhere the emote catalog can be loaded from
+#[async_trait]
+pub trait EmoteSource: Debug + Send + Sync {
+    async fn load(&self) -> Result<Vec<RawEmoteData>, HandlerError>;
+}
+
+/// A fixed catalog, mostly useful for building a [Handler](crate::handler::Handler) in tests
+#[async_trait]
+impl EmoteSource for Vec<RawEmoteData> {
+    async fn load(&self) -> Result<Vec<RawEmoteData>, HandlerError> {
+        Ok(self.clone())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SourceKind {
+    #[default]
+    Xivapi,
+    Sheets,
+    Db,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SnapshotMode {
+    /// Always load from the source and never read or write the snapshot
+    Disabled,
+    /// Load from the source and save the result to the snapshot, falling back to the
+    /// snapshot if the source can't be loaded
+    #[default]
+    Refresh,
+    /// Only load from the snapshot, never touching the source
+    Offline,
+}
+
+#[derive(Debug, Clone)]
+pub struct CatalogConfig {
+    pub source: SourceKind,
+    pub xivapi_key: Option<String>,
+    pub sheets_path: PathBuf,
+    pub snapshot_path: PathBuf,
+    pub snapshot_mode: SnapshotMode,
+    pub fallback: FallbackTemplates,
+}
+
+impl Default for CatalogConfig {
+    fn default() -> Self {
+        CatalogConfig {
+            source: SourceKind::default(),
+            xivapi_key: None,
+            sheets_path: PathBuf::from(DEFAULT_SHEETS_PATH),
+            snapshot_path: PathBuf::from(DEFAULT_SNAPSHOT_PATH),
+            snapshot_mode: SnapshotMode::default(),
+            fallback: FallbackTemplates::default(),
+        }
+    }
+}
+
+impl CatalogConfig {
+    /// Reads `EMOTE_SOURCE`, `XIVAPI_KEY`, `EMOTE_SHEETS_PATH`, `EMOTE_SNAPSHOT_PATH`,
+    /// `EMOTE_SNAPSHOT_MODE`, `EMOTE_FALLBACK_TEMPLATE` and `EMOTE_FALLBACK_TARGETED_TEMPLATE`,
+    /// using defaults for any that are not set
+    pub fn from_env() -> CatalogConfig {
+        let default = CatalogConfig::default();
+        CatalogConfig {
+            source: env::var("EMOTE_SOURCE")
+                .map(|source| {
+                    source
+                        .parse()
+                        .expect("EMOTE_SOURCE must be one of xivapi, sheets or db")
+                })
+                .unwrap_or(default.source),
+            xivapi_key: env::var("XIVAPI_KEY").ok(),
+            sheets_path: env::var("EMOTE_SHEETS_PATH")
+                .map(PathBuf::from)
+                .unwrap_or(default.sheets_path),
+            snapshot_path: env::var("EMOTE_SNAPSHOT_PATH")
+                .map(PathBuf::from)
+                .unwrap_or(default.snapshot_path),
+            snapshot_mode: env::var("EMOTE_SNAPSHOT_MODE")
+                .map(|mode| {
+                    mode.parse()
+                        .expect("EMOTE_SNAPSHOT_MODE must be one of disabled, refresh or offline")
+                })
+                .unwrap_or(default.snapshot_mode),
+            fallback: FallbackTemplates {
+                untargeted: env::var("EMOTE_FALLBACK_TEMPLATE").ok(),
+                targeted: env::var("EMOTE_FALLBACK_TARGETED_TEMPLATE").ok(),
+            },
+        }
+    }
+
+    /// Builds the configured source, wrapped according to the configured [SnapshotMode]
+    pub(crate) fn build_source(&self, db: &Db) -> Box<dyn EmoteSource> {
+        let source: Box<dyn EmoteSource> = match self.source {
+            SourceKind::Xivapi => Box::new(XivapiSource::new(self.xivapi_key.clone())),
+            SourceKind::Sheets => Box::new(SheetsSource::new(self.sheets_path.clone())),
+            SourceKind::Db => Box::new(EmotesTableSource::new(db.clone())),
+        };
+        match self.snapshot_mode {
+            SnapshotMode::Disabled => source,
+            mode => Box::new(SnapshotSource::new(
+                source,
+                self.snapshot_path.clone(),
+                mode,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emote(id: u32, en: (&str, &str), ja: (&str, &str), de: &str, fr: &str) -> RawEmoteData {
+        let texts = |command: &str, alias: &str| RawEmoteTexts {
+            command: Some(command.to_string()).filter(|c| !c.is_empty()),
+            alias: Some(alias.to_string()).filter(|a| !a.is_empty()),
+            ..Default::default()
+        };
+        RawEmoteData {
+            id,
+            name: format!("emote {}", id),
+            category: None,
+            order: 0,
+            en: texts(en.0, en.1),
+            ja: texts(ja.0, ja.1),
+            de: texts(de, ""),
+            fr: texts(fr, ""),
+        }
+    }
+
+    fn owner(id: u32, kind: CommandKind) -> CommandOwner {
+        CommandOwner {
+            id,
+            name: format!("emote {}", id),
+            kind,
+        }
+    }
+
+    #[test]
+    fn resolves_commands_without_collisions() {
+        let (resolved, collisions) = resolve_commands(&[
+            emote(1, ("/hug", ""), ("/ハグ", "/はぐ"), "/umarmen", "/câlin"),
+            emote(2, ("/wave", "/bye"), ("/手をふる", ""), "", ""),
+        ]);
+
+        assert_eq!(resolved.len(), 8);
+        assert_eq!(resolved["/はぐ"], 1);
+        assert_eq!(resolved["/bye"], 2);
+        assert!(collisions.is_empty());
+    }
+
+    #[test]
+    fn english_and_japanese_commands_collide_by_id() {
+        let (resolved, collisions) = resolve_commands(&[
+            emote(3, ("/sit", ""), ("/すわる", ""), "", ""),
+            emote(2, ("/sit", ""), ("/すわる", ""), "", ""),
+        ]);
+
+        assert_eq!(resolved["/sit"], 2);
+        assert_eq!(resolved["/すわる"], 2);
+        assert_eq!(collisions.len(), 2);
+        for collision in &collisions {
+            assert_eq!(collision.winner, owner(2, CommandKind::Command));
+            assert_eq!(collision.losers, [owner(3, CommandKind::Command)]);
+            assert!(collision.is_ambiguous());
+        }
+    }
+
+    #[test]
+    fn secondary_commands_lose_to_english_and_japanese() {
+        let (resolved, collisions) = resolve_commands(&[
+            emote(1, ("/beg", ""), ("", ""), "/pose", "/sit"),
+            emote(2, ("/pose", ""), ("", ""), "", ""),
+            emote(3, ("", ""), ("/sit", ""), "", ""),
+        ]);
+
+        assert_eq!(resolved["/pose"], 2);
+        assert_eq!(resolved["/sit"], 3);
+        assert_eq!(
+            collisions[0].losers,
+            [owner(1, CommandKind::SecondaryCommand)]
+        );
+        assert!(collisions.iter().all(|c| !c.is_ambiguous()));
+    }
+
+    #[test]
+    fn aliases_lose_to_commands() {
+        let (resolved, collisions) = resolve_commands(&[
+            emote(1, ("/greet", "/wave"), ("", ""), "", ""),
+            emote(2, ("/wave", ""), ("", ""), "", ""),
+        ]);
+
+        assert_eq!(resolved["/wave"], 2);
+        assert_eq!(
+            collisions,
+            [CommandCollision {
+                command: "/wave".to_string(),
+                winner: owner(2, CommandKind::Command),
+                losers: vec![owner(1, CommandKind::Alias)],
+            }]
+        );
+        assert!(!collisions[0].is_ambiguous());
+    }
+
+    #[test]
+    fn an_emote_only_owns_a_command_once() {
+        // an alias that's also the emote's own command in another language
+        let (resolved, collisions) = resolve_commands(&[emote(
+            1,
+            ("/dance", ""),
+            ("/ダンス", "/dance"),
+            "/dance",
+            "",
+        )]);
+
+        assert_eq!(resolved["/dance"], 1);
+        assert!(collisions.is_empty());
+    }
+
+    #[test]
+    fn ties_are_broken_by_id_in_order_of_precedence() {
+        let (resolved, collisions) = resolve_commands(&[
+            emote(9, ("", "/x"), ("", ""), "", ""),
+            emote(7, ("", ""), ("", ""), "/x", ""),
+            emote(8, ("", "/x"), ("", ""), "", ""),
+            emote(6, ("", ""), ("", ""), "", "/x"),
+        ]);
+
+        assert_eq!(resolved["/x"], 8);
+        assert_eq!(collisions[0].winner, owner(8, CommandKind::Alias));
+        assert_eq!(
+            collisions[0].losers,
+            [
+                owner(9, CommandKind::Alias),
+                owner(6, CommandKind::SecondaryCommand),
+                owner(7, CommandKind::SecondaryCommand),
+            ]
+        );
+        assert!(collisions[0].is_ambiguous());
+    }
+
+    #[test]
+    fn counts_emotes_that_can_be_used() {
+        let mut raw_emotes = fixture::raw_emotes();
+        let mut shadowed = raw_emotes[2].clone();
+        shadowed.id = 200;
+        raw_emotes.push(shadowed);
+
+        let catalog = EmoteCatalog::new(raw_emotes, &Default::default()).unwrap();
+
+        assert_eq!(catalog.raw().len(), 4);
+        assert_eq!(catalog.emote_count(), 3);
+    }
+
+    #[test]
+    fn drops_languages_that_cant_be_parsed() {
+        let mut raw_emotes = fixture::raw_emotes();
+        raw_emotes[2].de.targeted = "<If(PlayerParameter(8))>unterminated".to_string();
+
+        let catalog = EmoteCatalog::new(raw_emotes, &Default::default()).unwrap();
+
+        // the emote keeps its English and Japanese messages
+        let hug = catalog.get("/umarmen").unwrap();
+        assert_eq!(hug.id, 112);
+        assert!(!hug.synthetic);
+        assert!(hug.de.is_none());
+    }
+}
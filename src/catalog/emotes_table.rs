@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use tracing::*;
+
+use crate::{db::Db, HandlerError};
+
+use super::{EmoteSource, RawEmoteData};
+
+/// Loads the emote data last saved to the `emotes` and `emote_messages` tables
+#[derive(Debug, Clone)]
+pub struct EmotesTableSource {
+    db: Db,
+}
+
+impl EmotesTableSource {
+    pub(crate) fn new(db: Db) -> EmotesTableSource {
+        EmotesTableSource { db }
+    }
+}
+
+#[async_trait]
+impl EmoteSource for EmotesTableSource {
+    #[instrument(level = "debug", skip(self))]
+    async fn load(&self) -> Result<Vec<RawEmoteData>, HandlerError> {
+        self.db.find_raw_emotes().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::catalog::{fixture, EmoteCatalog};
+
+    use super::*;
+
+    #[ignore = "needs a database at DATABASE_URL"]
+    #[sqlx::test]
+    async fn loads_saved_emotes(pool: PgPool) {
+        let db = Db(pool);
+        let catalog = EmoteCatalog::new(fixture::raw_emotes(), &Default::default()).unwrap();
+        db.upsert_emotes(&catalog).await.unwrap();
+
+        let handler = fixture::handler_from(Box::new(EmotesTableSource::new(db))).await;
+
+        assert_eq!(handler.catalog().raw(), fixture::raw_emotes());
+        assert_eq!(handler.catalog().get("/hug").unwrap().id, 112);
+    }
+}
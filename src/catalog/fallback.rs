@@ -0,0 +1,93 @@
+//! Generic messages for emotes that have no log messages of their own, such as expressions.
+//!
+//! Templates are plain text where `{user}` is replaced with the user sending the emote, `{target}`
+//! with its target and `{emote}` with the localized name of the emote. `{target}` should only be
+//! used in the targeted template.
+
+use xiv_emote_parser::log_message::{
+    condition::DynamicText,
+    parser::{ConditionText, ConditionTexts, Text},
+};
+
+use crate::{db::models::DbLanguage, handler::ConditionTextPair, util::LocalizedString};
+
+use super::RawEmoteData;
+
+pub const DEFAULT_UNTARGETED: LocalizedString = LocalizedString {
+    en: "{user} uses {emote}.",
+    ja: "{user}は「{emote}」をした。",
+    de: "{user} benutzt „{emote}“.",
+    fr: "{user} utilise « {emote} ».",
+};
+pub const DEFAULT_TARGETED: LocalizedString = LocalizedString {
+    en: "{user} uses {emote} on {target}.",
+    ja: "{user}は{target}に「{emote}」をした。",
+    de: "{user} benutzt „{emote}“ bei {target}.",
+    fr: "{user} utilise « {emote} » sur {target}.",
+};
+
+const USER_PLACEHOLDER: &str = "{user}";
+const TARGET_PLACEHOLDER: &str = "{target}";
+const EMOTE_PLACEHOLDER: &str = "{emote}";
+
+/// Overrides for the default templates, which replace them in every language
+#[derive(Debug, Clone, Default)]
+pub struct FallbackTemplates {
+    pub untargeted: Option<String>,
+    pub targeted: Option<String>,
+}
+
+impl FallbackTemplates {
+    /// Builds the messages for an emote in a language, named by its localized name if it has one
+    pub fn messages(&self, raw: &RawEmoteData, language: DbLanguage) -> ConditionTextPair {
+        let name = Some(&language.with_raw_emote_data(raw).name)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&raw.name);
+        let template = |custom: &Option<String>, default: LocalizedString| {
+            custom
+                .clone()
+                .unwrap_or_else(|| default.for_language(language).to_string())
+        };
+        ConditionTextPair {
+            targeted: render(&template(&self.targeted, DEFAULT_TARGETED), name),
+            untargeted: render(&template(&self.untargeted, DEFAULT_UNTARGETED), name),
+        }
+    }
+}
+
+/// Splits a template into static text and the character names it refers to
+fn render(template: &str, emote_name: &str) -> ConditionTexts {
+    let template = template.replace(EMOTE_PLACEHOLDER, emote_name);
+    let mut texts = Vec::new();
+    let mut rest = template.as_str();
+    while !rest.is_empty() {
+        let next = [
+            (USER_PLACEHOLDER, DynamicText::PlayerOriginNameEn),
+            (TARGET_PLACEHOLDER, DynamicText::PlayerTargetNameEn),
+        ]
+        .into_iter()
+        .filter_map(|(placeholder, dynamic)| {
+            rest.find(placeholder)
+                .map(|start| (start, placeholder, dynamic))
+        })
+        .min_by_key(|(start, _, _)| *start);
+        let Some((start, placeholder, dynamic)) = next else {
+            texts.push(Text::Static(rest.to_string()));
+            break;
+        };
+        if start > 0 {
+            texts.push(Text::Static(rest[..start].to_string()));
+        }
+        texts.push(Text::Dynamic(dynamic));
+        rest = &rest[start + placeholder.len()..];
+    }
+    ConditionTexts::new(
+        texts
+            .into_iter()
+            .map(|text| ConditionText {
+                conds: vec![],
+                text,
+            })
+            .collect(),
+    )
+}
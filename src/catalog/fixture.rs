@@ -0,0 +1,125 @@
+//! A tiny catalog for tests, with the game's own log messages where there are any
+
+use std::time::Duration;
+
+use sqlx::postgres::PgPoolOptions;
+
+use crate::{db::Db, handler::Handler};
+
+use super::{fallback::FallbackTemplates, EmoteSource, RawEmoteData, RawEmoteTexts};
+
+// the emote sheet's log messages for /surprised and /hug, as xivapi serves them
+const SURPRISED_TARGETED_EN: &str = "<Clickable(<If(Equal(ObjectParameter(1),ObjectParameter(2)))>you<Else/><If(PlayerParameter(7))><SheetEn(ObjStr,2,PlayerParameter(7),1,1)/><Else/>ObjectParameter(2)</If></If>)/> <If(Equal(ObjectParameter(1),ObjectParameter(2)))>look<Else/>looks</If> at <If(Equal(ObjectParameter(1),ObjectParameter(3)))><If(PlayerParameter(8))><SheetEn(ObjStr,2,PlayerParameter(8),1,1)/><Else/>you</If><Else/><If(PlayerParameter(8))><SheetEn(ObjStr,2,PlayerParameter(8),1,1)/><Else/>ObjectParameter(3)</If></If> in surprise.";
+const SURPRISED_UNTARGETED_EN: &str = "<Clickable(<If(Equal(ObjectParameter(1),ObjectParameter(2)))>you<Else/><If(PlayerParameter(7))><SheetEn(ObjStr,2,PlayerParameter(7),1,1)/><Else/>ObjectParameter(2)</If></If>)/> <If(Equal(ObjectParameter(1),ObjectParameter(2)))>look<Else/>looks</If> surprised!";
+const SURPRISED_TARGETED_JA: &str = "<If(PlayerParameter(7))><Sheet(ObjStr,PlayerParameter(7),0)/><Else/>ObjectParameter(2)</If>は<If(PlayerParameter(8))><Sheet(ObjStr,PlayerParameter(8),0)/><Else/>ObjectParameter(3)</If>におどろいた。";
+const SURPRISED_UNTARGETED_JA: &str = "<If(PlayerParameter(7))><Sheet(ObjStr,PlayerParameter(7),0)/><Else/>ObjectParameter(2)</If>はおどろいた。";
+const HUG_TARGETED_EN: &str = "<Clickable(<If(Equal(ObjectParameter(1),ObjectParameter(2)))>you<Else/><If(PlayerParameter(7))><SheetEn(ObjStr,2,PlayerParameter(7),1,1)/><Else/>ObjectParameter(2)</If></If>)/> <If(Equal(ObjectParameter(1),ObjectParameter(2)))>give<Else/>gives</If> <If(Equal(ObjectParameter(1),ObjectParameter(3)))><If(PlayerParameter(8))><SheetEn(ObjStr,2,PlayerParameter(8),1,1)/><Else/>you</If><Else/><If(PlayerParameter(8))><SheetEn(ObjStr,2,PlayerParameter(8),1,1)/><Else/>ObjectParameter(3)</If></If> a big hug.";
+const HUG_UNTARGETED_EN: &str = "<Clickable(<If(Equal(ObjectParameter(1),ObjectParameter(2)))>you<Else/><If(PlayerParameter(7))><SheetEn(ObjStr,2,PlayerParameter(7),1,1)/><Else/>ObjectParameter(2)</If></If>)/> <If(Equal(ObjectParameter(1),ObjectParameter(2)))>hug<Else/>hugs</If> the air.";
+const HUG_TARGETED_JA: &str = "<If(PlayerParameter(7))><Sheet(ObjStr,PlayerParameter(7),0)/><Else/>ObjectParameter(2)</If>は<If(PlayerParameter(8))><Sheet(ObjStr,PlayerParameter(8),0)/><Else/>ObjectParameter(3)</If>をハグした。";
+const HUG_UNTARGETED_JA: &str = "<If(PlayerParameter(7))><Sheet(ObjStr,PlayerParameter(7),0)/><Else/>ObjectParameter(2)</If>はハグした。";
+
+// German messages weren't copied from the game, so these are written the way its are, which is
+// enough to exercise the German columns
+const ORIGIN_DE: &str = "<Clickable(<If(Equal(ObjectParameter(1),ObjectParameter(2)))>du<Else/><If(PlayerParameter(7))><SheetDe(ObjStr,2,PlayerParameter(7),1,1)/><Else/>ObjectParameter(2)</If></If>)/>";
+const TARGET_DE: &str = "<If(Equal(ObjectParameter(1),ObjectParameter(3)))><If(PlayerParameter(8))><SheetDe(ObjStr,2,PlayerParameter(8),1,4)/><Else/>dich</If><Else/><If(PlayerParameter(8))><SheetDe(ObjStr,2,PlayerParameter(8),1,4)/><Else/>ObjectParameter(3)</If></If>";
+
+fn verb(you: &str, other: &str) -> String {
+    format!(
+        "<If(Equal(ObjectParameter(1),ObjectParameter(2)))>{}<Else/>{}</If>",
+        you, other
+    )
+}
+
+fn texts(name: &str, command: &str, alias: Option<&str>) -> RawEmoteTexts {
+    RawEmoteTexts {
+        name: name.to_string(),
+        command: Some(command.to_string()),
+        alias: alias.map(ToString::to_string),
+        ..Default::default()
+    }
+}
+
+/// /hug with messages in every language but French, /surprised with English and Japanese messages
+/// only, and /airquotes, which has no messages at all like in the game
+pub fn raw_emotes() -> Vec<RawEmoteData> {
+    vec![
+        RawEmoteData {
+            id: 1,
+            name: "Surprised".to_string(),
+            category: Some(1),
+            order: 4,
+            en: RawEmoteTexts {
+                targeted: SURPRISED_TARGETED_EN.to_string(),
+                untargeted: SURPRISED_UNTARGETED_EN.to_string(),
+                ..texts("Surprised", "/surprised", None)
+            },
+            ja: RawEmoteTexts {
+                targeted: SURPRISED_TARGETED_JA.to_string(),
+                untargeted: SURPRISED_UNTARGETED_JA.to_string(),
+                ..texts("驚く", "/おどろく", None)
+            },
+            de: texts("Überrascht", "/überrascht", None),
+            fr: texts("Surprise", "/surprise", None),
+        },
+        RawEmoteData {
+            id: 54,
+            name: "Air Quotes".to_string(),
+            category: Some(3),
+            order: 1,
+            en: texts("Air Quotes", "/airquotes", None),
+            ja: texts("エアクオート", "/エアクオート", None),
+            de: texts("Anführungszeichen", "/anführungszeichen", None),
+            fr: texts("Guillemets", "/guillemets", None),
+        },
+        RawEmoteData {
+            id: 112,
+            name: "Hug".to_string(),
+            category: Some(2),
+            order: 1,
+            en: RawEmoteTexts {
+                targeted: HUG_TARGETED_EN.to_string(),
+                untargeted: HUG_UNTARGETED_EN.to_string(),
+                ..texts("Hug", "/hug", None)
+            },
+            ja: RawEmoteTexts {
+                targeted: HUG_TARGETED_JA.to_string(),
+                untargeted: HUG_UNTARGETED_JA.to_string(),
+                ..texts("ハグ", "/ハグ", Some("/はぐ"))
+            },
+            de: RawEmoteTexts {
+                targeted: format!("{} {} {}.", ORIGIN_DE, verb("umarmst", "umarmt"), TARGET_DE),
+                untargeted: format!(
+                    "{} {} jemanden umarmen.",
+                    ORIGIN_DE,
+                    verb("möchtest", "möchte")
+                ),
+                ..texts("Umarmen", "/umarmen", None)
+            },
+            fr: texts("Câlin", "/câlin", None),
+        },
+    ]
+}
+
+/// A handler for the fixture catalog, see [handler_from]
+pub async fn handler() -> Handler {
+    handler_from(Box::new(raw_emotes())).await
+}
+
+/// A handler for whatever catalog `source` has, with a database that can't be connected to so
+/// that anything using it fails quickly
+pub async fn handler_from(source: Box<dyn EmoteSource>) -> Handler {
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(100))
+        .connect_lazy("postgres://localhost:1/unused")
+        .unwrap();
+    Handler::from_source(Db(pool), source, FallbackTemplates::default())
+        .await
+        .unwrap()
+}
+
+/// A handler for the fixture catalog that uses `db`, for tests that need a database
+pub async fn handler_with_db(db: Db) -> Handler {
+    Handler::from_source(db, Box::new(raw_emotes()), FallbackTemplates::default())
+        .await
+        .unwrap()
+}
@@ -0,0 +1,327 @@
+//! Rewrites German and French log messages into the subset of markup the parser understands.
+//!
+//! The parser was written against the English and Japanese messages, but the German and French
+//! ones additionally use grammatical case switches, language-specific name sheets with prepositions
+//! built in, highlighting and article lookups for NPC names. Those are either rewritten into their
+//! English equivalents or, where they only pick between forms of NPC names, resolved statically.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+/// `If` conditions that the parser can evaluate itself, everything else is resolved here
+const SUPPORTED_CONDITIONS: &[&str] = &[
+    "Equal(ObjectParameter(1),ObjectParameter(2))",
+    "Equal(ObjectParameter(1),ObjectParameter(3))",
+    "PlayerParameter(5)",
+    "PlayerParameter(7)",
+    "PlayerParameter(8)",
+    "<Sheet(BNpcName,PlayerParameter(7),6)/>",
+];
+
+const IF_OPEN: &str = "<If(";
+const IF_ELSE: &str = "<Else/>";
+const IF_CLOSE: &str = "</If>";
+
+static HIGHLIGHT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<Highlight>([^<]*)</Highlight>").unwrap());
+static WORD_BEFORE_HIGHLIGHT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\p{L})<Highlight>").unwrap());
+static ATTRIBUTIVE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<Sheet\(Attributive,(\d+),\d+\)/>").unwrap());
+static PLAYER_NAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"<Sheet(De|Fr)\(ObjStr,(\d+),PlayerParameter\((\d+)\)(?:,\d+)*\)/>").unwrap()
+});
+static SWITCH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"<Switch\(([^<>]*?)\)>((?:<Case\(\d+\)>[^<]*</Case>)*)</Switch>").unwrap()
+});
+static CASE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<Case\((\d+)\)>([^<]*)</Case>").unwrap());
+static EQUAL_NUMS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^Equal\((\d+),(\d+)\)$").unwrap());
+
+/// Rewrites a German or French log message so that it can be passed to the parser
+pub fn lower(text: &str) -> String {
+    let text = ATTRIBUTIVE.replace_all(text, |caps: &Captures| french_preposition(&caps[1]));
+    let text = PLAYER_NAME.replace_all(&text, |caps: &Captures| {
+        let preposition = if &caps[1] == "Fr" {
+            french_preposition(&caps[2])
+        } else {
+            ""
+        };
+        format!(
+            "{preposition}<SheetEn(ObjStr,2,PlayerParameter({}),1,1)/>",
+            &caps[3]
+        )
+    });
+    let text = SWITCH.replace_all(&text, lower_switch);
+    let text = resolve_unsupported_ifs(&text);
+    let text = WORD_BEFORE_HIGHLIGHT.replace_all(&text, "$1 <Highlight>");
+    // the parser only reads names at the start of a branch, so the highlight becomes an if with
+    // identical branches instead of being dropped
+    let text = HIGHLIGHT.replace_all(&text, "<If(PlayerParameter(7))>$1<Else/>$1</If>");
+    fill_empty_branches(&text)
+}
+
+/// French name lookups take the form of the name as a parameter, which for player names (and NPC
+/// names, which are treated as proper nouns) comes down to which preposition goes in front
+fn french_preposition(form: &str) -> &'static str {
+    match form {
+        "7" => "à ",
+        "9" => "de ",
+        _ => "",
+    }
+}
+
+/// A switch on a literal selects one of its cases, while a switch on a condition becomes an if-else
+/// between its first two cases
+fn lower_switch(caps: &Captures) -> String {
+    let cases: Vec<_> = CASE
+        .captures_iter(&caps[2])
+        .map(|case| (case[1].parse::<u32>().ok(), case[2].to_string()))
+        .collect();
+    match caps[1].parse::<u32>() {
+        Ok(selected) => cases
+            .into_iter()
+            .find(|(n, _)| *n == Some(selected))
+            .map(|(_, text)| text)
+            .unwrap_or_default(),
+        Err(_) => match &cases[..] {
+            [(_, then), (_, otherwise), ..] => {
+                format!(
+                    "{IF_OPEN}{})>{then}{IF_ELSE}{otherwise}{IF_CLOSE}",
+                    &caps[1]
+                )
+            }
+            [(_, text)] => text.clone(),
+            [] => String::new(),
+        },
+    }
+}
+
+/// Evaluates conditions the parser doesn't know about, which only ever pick between forms of NPC
+/// names, as false unless they compare two equal literals
+fn evaluate_static(condition: &str) -> bool {
+    EQUAL_NUMS
+        .captures(condition)
+        .map(|caps| caps[1] == caps[2])
+        .unwrap_or(false)
+}
+
+fn resolve_unsupported_ifs(text: &str) -> String {
+    let mut text = Cow::Borrowed(text);
+    let mut search_from = 0;
+    while let Some(offset) = text[search_from..].find(IF_OPEN) {
+        let start = search_from + offset;
+        let Some((condition, body_start)) = if_condition(&text, start) else {
+            break;
+        };
+        if SUPPORTED_CONDITIONS.contains(&condition) {
+            search_from = body_start;
+            continue;
+        }
+        let Some((else_start, close_start)) = if_branches(&text, body_start) else {
+            break;
+        };
+        let branch = if evaluate_static(condition) {
+            &text[body_start..else_start]
+        } else {
+            &text[else_start + IF_ELSE.len()..close_start]
+        };
+        // the chosen branch may itself contain ifs, so search again from the same position
+        text = Cow::Owned(
+            [
+                &text[..start],
+                branch,
+                &text[close_start + IF_CLOSE.len()..],
+            ]
+            .concat(),
+        );
+        search_from = start;
+    }
+    text.into_owned()
+}
+
+/// The parser requires both branches of an if to have content, so an empty branch gets whatever
+/// immediately follows the if copied into both of them
+fn fill_empty_branches(text: &str) -> String {
+    let mut text = text.to_string();
+    let mut search_from = 0;
+    while let Some(offset) = text[search_from..].find(IF_OPEN) {
+        let start = search_from + offset;
+        let Some((_, body_start)) = if_condition(&text, start) else {
+            break;
+        };
+        let Some((else_start, close_start)) = if_branches(&text, body_start) else {
+            break;
+        };
+        search_from = body_start;
+        let end = close_start + IF_CLOSE.len();
+        if else_start > body_start && close_start > else_start + IF_ELSE.len() {
+            continue;
+        }
+        let Some(next_end) = next_part_end(&text, end) else {
+            continue;
+        };
+        let next = text[end..next_end].to_string();
+        text = [
+            &text[..else_start],
+            &next,
+            &text[else_start..close_start],
+            &next,
+            &text[close_start..end],
+            &text[next_end..],
+        ]
+        .concat();
+    }
+    text
+}
+
+/// Where the text or if starting at `start` ends, if there is one
+fn next_part_end(text: &str, start: usize) -> Option<usize> {
+    let rest = &text[start..];
+    if rest.starts_with(IF_OPEN) {
+        let (_, body_start) = if_condition(text, start)?;
+        let (_, close_start) = if_branches(text, body_start)?;
+        Some(close_start + IF_CLOSE.len())
+    } else {
+        let len = rest.find('<').unwrap_or(rest.len());
+        (len > 0).then_some(start + len)
+    }
+}
+
+/// The condition of the if starting at `start`, along with where its then branch starts
+fn if_condition(text: &str, start: usize) -> Option<(&str, usize)> {
+    let cond_start = start + IF_OPEN.len();
+    let mut depth = 1;
+    for (i, c) in text[cond_start..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    let cond_end = cond_start + i;
+                    return text[cond_end..]
+                        .starts_with(")>")
+                        .then(|| (&text[cond_start..cond_end], cond_end + 2));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Where the else and the closing tag of the if whose then branch starts at `body_start` are
+fn if_branches(text: &str, body_start: usize) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut else_start = None;
+    let mut i = body_start;
+    while i < text.len() {
+        let rest = &text[i..];
+        if rest.starts_with(IF_OPEN) {
+            depth += 1;
+            i += IF_OPEN.len();
+        } else if rest.starts_with(IF_ELSE) {
+            if depth == 0 {
+                else_start = Some(i);
+            }
+            i += IF_ELSE.len();
+        } else if rest.starts_with(IF_CLOSE) {
+            if depth == 0 {
+                return else_start.map(|else_start| (else_start, i));
+            }
+            depth -= 1;
+            i += IF_CLOSE.len();
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use xiv_emote_parser::log_message::parser::extract_condition_texts;
+
+    use super::*;
+
+    #[test]
+    fn lowers_each_form() {
+        let cases = [
+            // French prepositions looked up by form
+            ("<Sheet(Attributive,7,0)/>x", "à x"),
+            ("<Sheet(Attributive,9,0)/>x", "de x"),
+            ("<Sheet(Attributive,1,0)/>x", "x"),
+            // player names
+            (
+                "<SheetDe(ObjStr,2,PlayerParameter(8),1,4)/>",
+                "<SheetEn(ObjStr,2,PlayerParameter(8),1,1)/>",
+            ),
+            (
+                "<SheetFr(ObjStr,7,PlayerParameter(8),1,4)/>",
+                "à <SheetEn(ObjStr,2,PlayerParameter(8),1,1)/>",
+            ),
+            (
+                "<SheetFr(ObjStr,9,PlayerParameter(7))/>",
+                "de <SheetEn(ObjStr,2,PlayerParameter(7),1,1)/>",
+            ),
+            // switches on literals and on conditions
+            (
+                "<Switch(2)><Case(1)>a</Case><Case(2)>b</Case></Switch>",
+                "b",
+            ),
+            ("<Switch(3)><Case(1)>a</Case><Case(2)>b</Case></Switch>", ""),
+            (
+                "<Switch(PlayerParameter(5))><Case(1)>sie</Case><Case(2)>er</Case></Switch>",
+                "<If(PlayerParameter(5))>sie<Else/>er</If>",
+            ),
+            (
+                "<Switch(PlayerParameter(5))><Case(1)>sie</Case></Switch>",
+                "sie",
+            ),
+            // conditions the parser can't evaluate
+            ("<If(Equal(1,1))>yes<Else/>no</If>", "yes"),
+            ("<If(Equal(1,2))>yes<Else/>no</If>", "no"),
+            ("<If(ObjectParameter(4))>yes<Else/>no</If>", "no"),
+            (
+                "<If(Equal(1,2))>a<Else/><If(Equal(3,3))>b<Else/>c</If></If>",
+                "b",
+            ),
+            (
+                "<If(PlayerParameter(5))>her<Else/>his</If>",
+                "<If(PlayerParameter(5))>her<Else/>his</If>",
+            ),
+            // highlights
+            (
+                "<Highlight>nom</Highlight>",
+                "<If(PlayerParameter(7))>nom<Else/>nom</If>",
+            ),
+            (
+                "le<Highlight>nom</Highlight>",
+                "le <If(PlayerParameter(7))>nom<Else/>nom</If>",
+            ),
+            // empty branches
+            (
+                "<If(PlayerParameter(5))><Else/>e</If>s x",
+                "<If(PlayerParameter(5))>s x<Else/>es x</If>",
+            ),
+            (
+                "<If(PlayerParameter(5))>e<Else/></If>s x",
+                "<If(PlayerParameter(5))>es x<Else/>s x</If>",
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(lower(text), expected, "lowering {}", text);
+        }
+    }
+
+    #[test]
+    fn lowered_messages_parse() {
+        let text = "<Clickable(<If(Equal(ObjectParameter(1),ObjectParameter(2)))>Du<Else/>\
+            <If(PlayerParameter(7))><SheetDe(ObjStr,2,PlayerParameter(7),1,1)/><Else/>\
+            ObjectParameter(2)</If></If>)/> <If(Equal(ObjectParameter(1),ObjectParameter(2)))>\
+            umarmst<Else/>umarmt</If> <Switch(2)><Case(1)>den</Case><Case(2)>das</Case></Switch>\
+            <Highlight>Mogry</Highlight>.";
+        assert!(extract_condition_texts(&lower(text)).is_ok());
+    }
+}
@@ -0,0 +1,455 @@
+//! Local exports of the game's Emote, LogMessage and TextCommand sheets, laid out as
+//! `<path>/<language>/<Sheet>.csv` (or `<Sheet>.json`) for each of `en` and `ja`, and optionally
+//! `de` and `fr`.
+//!
+//! CSV files can either have a single row of column names as the header or the three header rows
+//! (column indices, column names, column types) used by common datamining exports. JSON files
+//! should be an array of objects keyed by column name. Either way, rows are identified by their
+//! `#` or `ID` column.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tracing::*;
+
+use crate::HandlerError;
+
+use super::{EmoteSource, RawEmoteData, RawEmoteTexts};
+
+const ID_COLUMNS: &[&str] = &["#", "ID"];
+const TARGETED_COLUMNS: &[&str] = &["LogMessage{Targeted}", "LogMessageTargeted"];
+const UNTARGETED_COLUMNS: &[&str] = &["LogMessage{Untargeted}", "LogMessageUntargeted"];
+const TEXT_COMMAND_COLUMNS: &[&str] = &["TextCommand"];
+const CATEGORY_COLUMNS: &[&str] = &["EmoteCategory"];
+const ORDER_COLUMNS: &[&str] = &["Order"];
+const NAME_COLUMNS: &[&str] = &["Name"];
+
+type Row = HashMap<String, String>;
+
+#[derive(Debug, Clone)]
+pub struct SheetsSource {
+    path: PathBuf,
+}
+
+impl SheetsSource {
+    pub fn new(path: PathBuf) -> SheetsSource {
+        SheetsSource { path }
+    }
+}
+
+#[derive(Debug)]
+struct Sheet {
+    name: &'static str,
+    rows: HashMap<u32, Row>,
+}
+
+impl Sheet {
+    #[instrument(level = "debug")]
+    fn load(dir: &Path, name: &'static str) -> Result<Sheet, HandlerError> {
+        let csv_path = dir.join(format!("{}.csv", name));
+        let rows = if csv_path.exists() {
+            read_csv(&csv_path)?
+        } else {
+            read_json(&dir.join(format!("{}.json", name)))?
+        };
+        let rows = rows
+            .into_iter()
+            .filter_map(|row| {
+                column(&row, ID_COLUMNS)
+                    .and_then(parse_id)
+                    .map(|id| (id, row))
+            })
+            .collect::<HashMap<_, _>>();
+        debug!(rows = rows.len(), "loaded sheet");
+        Ok(Sheet { name, rows })
+    }
+
+    fn get(&self, id: u32) -> Option<&Row> {
+        self.rows.get(&id)
+    }
+
+    /// Follows a link column in `row` to this sheet and reads `col` from the linked row
+    fn linked_column(&self, row: &Row, link_cols: &[&str], col: &str) -> Option<&str> {
+        column(row, link_cols)
+            .and_then(parse_id)
+            .and_then(|id| self.get(id))
+            .and_then(|linked| column(linked, &[col]))
+            .filter(|s| !s.is_empty())
+    }
+}
+
+fn column<'a>(row: &'a Row, names: &[&str]) -> Option<&'a str> {
+    names
+        .iter()
+        .find_map(|name| row.get(*name))
+        .map(String::as_str)
+}
+
+// links to row 0 are how the sheets represent an empty link
+fn parse_id(s: &str) -> Option<u32> {
+    s.trim().parse().ok().filter(|id| *id != 0)
+}
+
+fn read_csv(path: &Path) -> Result<Vec<Row>, HandlerError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let mut records = reader.records();
+    let mut header = records
+        .next()
+        .transpose()?
+        .ok_or_else(|| HandlerError::InvalidSheet(path.display().to_string()))?;
+    if header.get(0) == Some("key") {
+        // datamining exports have a row of column indices, then names, then types
+        header = records
+            .next()
+            .transpose()?
+            .ok_or_else(|| HandlerError::InvalidSheet(path.display().to_string()))?;
+        records.next().transpose()?;
+    }
+    records
+        .map(|record| {
+            Ok(header
+                .iter()
+                .zip(record?.iter())
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+fn read_json(path: &Path) -> Result<Vec<Row>, HandlerError> {
+    let objects: Vec<serde_json::Map<String, Value>> =
+        serde_json::from_str(&fs::read_to_string(path)?)?;
+    Ok(objects
+        .into_iter()
+        .map(|obj| {
+            obj.into_iter()
+                .map(|(k, v)| {
+                    let v = match v {
+                        Value::String(s) => s,
+                        Value::Null => String::new(),
+                        v => v.to_string(),
+                    };
+                    (k, v)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+#[derive(Debug)]
+struct LanguageSheets {
+    emote: Sheet,
+    log_message: Sheet,
+    text_command: Sheet,
+}
+
+impl LanguageSheets {
+    fn load(dir: &Path) -> Result<LanguageSheets, HandlerError> {
+        Ok(LanguageSheets {
+            emote: Sheet::load(dir, "Emote")?,
+            log_message: Sheet::load(dir, "LogMessage")?,
+            text_command: Sheet::load(dir, "TextCommand")?,
+        })
+    }
+
+    /// Like [LanguageSheets::load], but a missing directory just means the language isn't exported
+    fn load_optional(dir: &Path) -> Result<Option<LanguageSheets>, HandlerError> {
+        if dir.is_dir() {
+            LanguageSheets::load(dir).map(Some)
+        } else {
+            info!(?dir, "no sheets for language");
+            Ok(None)
+        }
+    }
+
+    fn texts(&self, id: u32) -> RawEmoteTexts {
+        let row = match self.emote.get(id) {
+            Some(row) => row,
+            None => {
+                warn!(id, sheet = self.emote.name, "emote missing from sheet");
+                return RawEmoteTexts::default();
+            }
+        };
+        let log_message = |cols| {
+            self.log_message
+                .linked_column(row, cols, "Text")
+                .map(ToString::to_string)
+                .unwrap_or_default()
+        };
+        RawEmoteTexts {
+            name: column(row, NAME_COLUMNS).unwrap_or_default().to_string(),
+            command: self
+                .text_command
+                .linked_column(row, TEXT_COMMAND_COLUMNS, "Command")
+                .map(ToString::to_string),
+            alias: self
+                .text_command
+                .linked_column(row, TEXT_COMMAND_COLUMNS, "Alias")
+                .map(ToString::to_string),
+            targeted: log_message(TARGETED_COLUMNS),
+            untargeted: log_message(UNTARGETED_COLUMNS),
+        }
+    }
+}
+
+#[async_trait]
+impl EmoteSource for SheetsSource {
+    #[instrument(level = "debug", skip(self), fields(path = ?self.path))]
+    async fn load(&self) -> Result<Vec<RawEmoteData>, HandlerError> {
+        let en = LanguageSheets::load(&self.path.join("en"))?;
+        let ja = LanguageSheets::load(&self.path.join("ja"))?;
+        let de = LanguageSheets::load_optional(&self.path.join("de"))?;
+        let fr = LanguageSheets::load_optional(&self.path.join("fr"))?;
+        let optional_texts = |sheets: &Option<LanguageSheets>, id| {
+            sheets
+                .as_ref()
+                .map(|sheets| sheets.texts(id))
+                .unwrap_or_default()
+        };
+
+        let mut ids: Vec<_> = en.emote.rows.keys().copied().collect();
+        ids.sort_unstable();
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| {
+                let row = en.emote.get(id)?;
+                let name = column(row, NAME_COLUMNS).filter(|name| !name.is_empty())?;
+                if column(row, TEXT_COMMAND_COLUMNS)
+                    .and_then(parse_id)
+                    .is_none()
+                {
+                    trace!(id, name, "ignoring emote without a text command");
+                    return None;
+                }
+                Some(RawEmoteData {
+                    id,
+                    name: name.to_string(),
+                    category: column(row, CATEGORY_COLUMNS).and_then(parse_id),
+                    order: column(row, ORDER_COLUMNS)
+                        .and_then(|order| order.trim().parse().ok())
+                        .unwrap_or_default(),
+                    en: en.texts(id),
+                    ja: ja.texts(id),
+                    de: optional_texts(&de, id),
+                    fr: optional_texts(&fr, id),
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use serde_json::json;
+
+    use crate::catalog::{fixture, RawEmoteTexts};
+
+    use super::*;
+
+    fn fixture_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("dxe-sheets-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    struct TestSheet {
+        name: &'static str,
+        columns: Vec<&'static str>,
+        rows: Vec<Vec<String>>,
+    }
+
+    /// The columns of the three sheets for one language, with each emote's log messages and text
+    /// command placed in rows numbered after the emote
+    fn sheet_rows(
+        raw_emotes: &[RawEmoteData],
+        texts: impl Fn(&RawEmoteData) -> &RawEmoteTexts,
+    ) -> [TestSheet; 3] {
+        let mut emotes = Vec::new();
+        let mut log_messages = Vec::new();
+        let mut text_commands = Vec::new();
+        for raw in raw_emotes {
+            let texts = texts(raw);
+            let id = raw.id * 10;
+            emotes.push(vec![
+                raw.id.to_string(),
+                texts.name.clone(),
+                raw.category.unwrap_or_default().to_string(),
+                raw.order.to_string(),
+                (id + 1).to_string(),
+                (id + 2).to_string(),
+                id.to_string(),
+            ]);
+            log_messages.push(vec![(id + 1).to_string(), texts.targeted.clone()]);
+            log_messages.push(vec![(id + 2).to_string(), texts.untargeted.clone()]);
+            text_commands.push(vec![
+                id.to_string(),
+                texts.command.clone().unwrap_or_default(),
+                texts.alias.clone().unwrap_or_default(),
+            ]);
+        }
+        // an emote without a text command, which is left out
+        emotes.push(vec![
+            "900".into(),
+            "Unused".into(),
+            "0".into(),
+            "0".into(),
+            "0".into(),
+            "0".into(),
+            "0".into(),
+        ]);
+        [
+            TestSheet {
+                name: "Emote",
+                columns: vec![
+                    "#",
+                    "Name",
+                    "EmoteCategory",
+                    "Order",
+                    "LogMessage{Targeted}",
+                    "LogMessage{Untargeted}",
+                    "TextCommand",
+                ],
+                rows: emotes,
+            },
+            TestSheet {
+                name: "LogMessage",
+                columns: vec!["#", "Text"],
+                rows: log_messages,
+            },
+            TestSheet {
+                name: "TextCommand",
+                columns: vec!["#", "Command", "Alias"],
+                rows: text_commands,
+            },
+        ]
+    }
+
+    fn write_csv(dir: &Path, datamining_header: bool, sheets: &[TestSheet]) {
+        fs::create_dir_all(dir).unwrap();
+        for TestSheet {
+            name,
+            columns,
+            rows,
+        } in sheets
+        {
+            let mut writer = csv::Writer::from_path(dir.join(format!("{}.csv", name))).unwrap();
+            if datamining_header {
+                let indices: Vec<_> = std::iter::once("key".to_string())
+                    .chain((0..columns.len() - 1).map(|i| i.to_string()))
+                    .collect();
+                writer.write_record(&indices).unwrap();
+                writer.write_record(columns).unwrap();
+                writer
+                    .write_record(std::iter::repeat_n("str", columns.len()))
+                    .unwrap();
+            } else {
+                writer.write_record(columns).unwrap();
+            }
+            for row in rows {
+                writer.write_record(row).unwrap();
+            }
+            writer.flush().unwrap();
+        }
+    }
+
+    fn write_json(dir: &Path, sheets: &[TestSheet]) {
+        fs::create_dir_all(dir).unwrap();
+        for TestSheet {
+            name,
+            columns,
+            rows,
+        } in sheets
+        {
+            let objects: Vec<_> = rows
+                .iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .zip(row)
+                        .map(|(column, value)| (column.to_string(), json!(value)))
+                        .collect::<serde_json::Map<_, _>>()
+                })
+                .collect();
+            fs::write(
+                dir.join(format!("{}.json", name)),
+                serde_json::to_string(&objects).unwrap(),
+            )
+            .unwrap();
+        }
+    }
+
+    fn write_fixture_sheets(dir: &Path) {
+        let raw_emotes = fixture::raw_emotes();
+        write_csv(
+            &dir.join("en"),
+            true,
+            &sheet_rows(&raw_emotes, |raw| &raw.en),
+        );
+        write_csv(
+            &dir.join("ja"),
+            false,
+            &sheet_rows(&raw_emotes, |raw| &raw.ja),
+        );
+        write_json(&dir.join("de"), &sheet_rows(&raw_emotes, |raw| &raw.de));
+    }
+
+    #[tokio::test]
+    async fn loads_every_header_layout_and_format() {
+        let dir = fixture_dir("load");
+        write_fixture_sheets(&dir);
+
+        let loaded = SheetsSource::new(dir.clone()).load().await.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // French wasn't exported
+        let expected: Vec<_> = fixture::raw_emotes()
+            .into_iter()
+            .map(|raw| RawEmoteData {
+                fr: RawEmoteTexts::default(),
+                ..raw
+            })
+            .collect();
+        assert_eq!(loaded, expected);
+    }
+
+    #[tokio::test]
+    async fn requires_english_and_japanese() {
+        let dir = fixture_dir("missing");
+        write_csv(
+            &dir.join("en"),
+            true,
+            &sheet_rows(&fixture::raw_emotes(), |raw| &raw.en),
+        );
+
+        let result = SheetsSource::new(dir.clone()).load().await;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn builds_a_handler() {
+        let dir = fixture_dir("handler");
+        write_fixture_sheets(&dir);
+
+        let handler = fixture::handler_from(Box::new(SheetsSource::new(dir.clone()))).await;
+        fs::remove_dir_all(&dir).unwrap();
+        let catalog = handler.catalog();
+
+        assert_eq!(catalog.get("/hug").unwrap().id, 112);
+        assert_eq!(catalog.get("/はぐ").unwrap().id, 112);
+        assert!(catalog.get("/umarmen").unwrap().de.is_some());
+        assert!(catalog.get("/airquotes").unwrap().synthetic);
+        assert!(catalog.raw_emote(900).is_none());
+    }
+}
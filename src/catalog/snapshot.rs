@@ -0,0 +1,119 @@
+//! Versioned local copy of the raw emote data so that startup doesn't depend on the source
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::*;
+
+use crate::HandlerError;
+
+use super::{EmoteSource, RawEmoteData, SnapshotMode};
+
+/// Bump whenever the serialized format of [RawEmoteData] changes incompatibly
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmoteSnapshot {
+    pub version: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub emotes: Vec<RawEmoteData>,
+}
+
+// read before the rest of the file so that an old snapshot gives a version error
+// rather than whatever field happened to change
+#[derive(Debug, Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+}
+
+impl EmoteSnapshot {
+    pub fn new(emotes: Vec<RawEmoteData>) -> EmoteSnapshot {
+        EmoteSnapshot {
+            version: SNAPSHOT_VERSION,
+            created_at: OffsetDateTime::now_utc(),
+            emotes,
+        }
+    }
+
+    #[instrument(level = "debug")]
+    pub fn load(path: &Path) -> Result<EmoteSnapshot, HandlerError> {
+        debug!("loading emote snapshot");
+        let contents = fs::read_to_string(path)?;
+        let SnapshotHeader { version } = serde_json::from_str(&contents)?;
+        if version != SNAPSHOT_VERSION {
+            error!(
+                version,
+                SNAPSHOT_VERSION, "unsupported emote snapshot version"
+            );
+            return Err(HandlerError::SnapshotVersion(version));
+        }
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Writes to a temporary file first so that a failed write can't clobber a good snapshot
+    #[instrument(level = "debug", skip(self))]
+    pub fn save(&self, path: &Path) -> Result<(), HandlerError> {
+        debug!(emotes = self.emotes.len(), "saving emote snapshot");
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Wraps another source, saving what it loads to a snapshot and falling back to that snapshot
+/// according to the [SnapshotMode]
+#[derive(Debug)]
+pub struct SnapshotSource {
+    inner: Box<dyn EmoteSource>,
+    path: PathBuf,
+    mode: SnapshotMode,
+}
+
+impl SnapshotSource {
+    pub fn new(inner: Box<dyn EmoteSource>, path: PathBuf, mode: SnapshotMode) -> SnapshotSource {
+        SnapshotSource { inner, path, mode }
+    }
+
+    fn load_snapshot(&self) -> Result<Vec<RawEmoteData>, HandlerError> {
+        let snapshot = EmoteSnapshot::load(&self.path)?;
+        info!(created_at = %snapshot.created_at, "loaded emotes from snapshot");
+        Ok(snapshot.emotes)
+    }
+}
+
+#[async_trait]
+impl EmoteSource for SnapshotSource {
+    #[instrument(level = "debug", skip(self), fields(path = ?self.path, mode = %self.mode))]
+    async fn load(&self) -> Result<Vec<RawEmoteData>, HandlerError> {
+        match self.mode {
+            SnapshotMode::Disabled => self.inner.load().await,
+            SnapshotMode::Offline => self.load_snapshot(),
+            SnapshotMode::Refresh => match self.inner.load().await {
+                Ok(emotes) => {
+                    let snapshot = EmoteSnapshot::new(emotes);
+                    if let Err(err) = snapshot.save(&self.path) {
+                        // not fatal, the next startup will just have an older (or no) fallback
+                        warn!(?err, "could not save emote snapshot");
+                    } else {
+                        info!("saved emote snapshot");
+                    }
+                    Ok(snapshot.emotes)
+                }
+                Err(err) => {
+                    warn!(
+                        ?err,
+                        "could not load emotes from source, falling back to snapshot"
+                    );
+                    self.load_snapshot()
+                }
+            },
+        }
+    }
+}
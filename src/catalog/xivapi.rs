@@ -0,0 +1,290 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::*;
+use xiv_emote_parser::repository::{LogMessageRepositoryError, XIVAPI_REQUEST_LIMIT};
+
+use crate::HandlerError;
+
+use super::{EmoteSource, RawEmoteData, RawEmoteTexts};
+
+const XIVAPI_EMOTE_URL: &str = "https://xivapi.com/emote";
+const XIVAPI_COLUMNS: &str = "ID,Name,Name_ja,Name_de,Name_fr,EmoteCategoryTargetID,Order,\
+    LogMessageTargeted,LogMessageUntargeted,TextCommand";
+
+#[derive(Debug, Clone)]
+pub struct XivapiSource {
+    api_key: Option<String>,
+}
+
+impl XivapiSource {
+    pub fn new(api_key: Option<String>) -> XivapiSource {
+        XivapiSource { api_key }
+    }
+
+    fn query(&self) -> Vec<(String, String)> {
+        let mut query = vec![
+            ("snake_case".to_string(), "1".to_string()),
+            ("columns".to_string(), XIVAPI_COLUMNS.to_string()),
+        ];
+        if let Some(key) = &self.api_key {
+            trace!("adding xivapi private key");
+            query.push(("private_key".to_string(), key.clone()));
+        }
+        query
+    }
+}
+
+// the parser's own xivapi types only keep the English and Japanese columns, and none of the
+// emote's metadata
+
+#[derive(Debug, Clone, Deserialize)]
+struct Response {
+    pagination: Pagination,
+    results: Vec<EmoteData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Pagination {
+    page_next: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmoteData {
+    log_message_targeted: Option<LogMessageData>,
+    log_message_untargeted: Option<LogMessageData>,
+    text_command: Option<TextCommand>,
+    name: Option<String>,
+    #[serde(default)]
+    name_ja: String,
+    #[serde(default)]
+    name_de: String,
+    #[serde(default)]
+    name_fr: String,
+    emote_category_target_id: Option<u32>,
+    #[serde(default)]
+    order: u32,
+    id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LogMessageData {
+    text_en: String,
+    text_ja: String,
+    #[serde(default)]
+    text_de: String,
+    #[serde(default)]
+    text_fr: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TextCommand {
+    alias_en: Option<String>,
+    alias_ja: Option<String>,
+    alias_de: Option<String>,
+    alias_fr: Option<String>,
+    command_en: Option<String>,
+    command_ja: Option<String>,
+    command_de: Option<String>,
+    command_fr: Option<String>,
+}
+
+impl RawEmoteData {
+    pub fn from_xivapi(data: EmoteData) -> Option<RawEmoteData> {
+        if let EmoteData {
+            log_message_targeted: targeted,
+            log_message_untargeted: untargeted,
+            text_command: Some(text_command),
+            name: Some(name),
+            name_ja,
+            name_de,
+            name_fr,
+            emote_category_target_id,
+            order,
+            id: Some(id),
+        } = data
+        {
+            // emotes such as expressions have no log messages, which is left to the catalog
+            let targeted = targeted.unwrap_or_default();
+            let untargeted = untargeted.unwrap_or_default();
+            Some(RawEmoteData {
+                id,
+                name: name.clone(),
+                // links to row 0 are how the sheets represent an empty link
+                category: emote_category_target_id.filter(|category| *category != 0),
+                order,
+                en: RawEmoteTexts {
+                    name,
+                    command: text_command.command_en,
+                    alias: text_command.alias_en,
+                    targeted: targeted.text_en,
+                    untargeted: untargeted.text_en,
+                },
+                ja: RawEmoteTexts {
+                    name: name_ja,
+                    command: text_command.command_ja,
+                    alias: text_command.alias_ja,
+                    targeted: targeted.text_ja,
+                    untargeted: untargeted.text_ja,
+                },
+                de: RawEmoteTexts {
+                    name: name_de,
+                    command: text_command.command_de,
+                    alias: text_command.alias_de,
+                    targeted: targeted.text_de,
+                    untargeted: untargeted.text_de,
+                },
+                fr: RawEmoteTexts {
+                    name: name_fr,
+                    command: text_command.command_fr,
+                    alias: text_command.alias_fr,
+                    targeted: targeted.text_fr,
+                    untargeted: untargeted.text_fr,
+                },
+            })
+        } else {
+            trace!("ignoring invalid emote data ({:?})", data);
+            None
+        }
+    }
+}
+
+/// Pages through the emote endpoint the same way the parser's own loader does
+fn load_pages(query: &[(String, String)]) -> Result<Vec<EmoteData>, HandlerError> {
+    let agent = ureq::AgentBuilder::new().build();
+    let mut results = Vec::new();
+    let mut page = 0;
+    loop {
+        page += 1;
+        if page >= XIVAPI_REQUEST_LIMIT {
+            std::thread::sleep(Duration::from_secs(2));
+        }
+        debug!(page, "loading page");
+        let mut req = agent.get(XIVAPI_EMOTE_URL);
+        for (key, value) in query {
+            req = req.query(key, value);
+        }
+        let text = req
+            .query("page", &page.to_string())
+            .call()
+            .map_err(LogMessageRepositoryError::from)?
+            .into_string()?;
+        trace!("loaded from xivapi: {:?}", text);
+        let mut data: Response = serde_json::from_str(&text)?;
+        results.append(&mut data.results);
+        if data.pagination.page_next.is_none() {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+#[async_trait]
+impl EmoteSource for XivapiSource {
+    #[instrument(level = "debug", skip(self))]
+    async fn load(&self) -> Result<Vec<RawEmoteData>, HandlerError> {
+        let query = self.query();
+        // xivapi is queried with a blocking client
+        let results = tokio::task::spawn_blocking(move || load_pages(&query)).await??;
+        Ok(results
+            .into_iter()
+            .filter_map(RawEmoteData::from_xivapi)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::catalog::fixture;
+
+    use super::*;
+
+    /// An emote as the emote endpoint returns it with the columns that are queried
+    fn xivapi_emote(raw: &RawEmoteData) -> Value {
+        let log_message = |text: fn(&RawEmoteTexts) -> &String| {
+            json!({
+                "text_en": text(&raw.en),
+                "text_ja": text(&raw.ja),
+                "text_de": text(&raw.de),
+                "text_fr": text(&raw.fr),
+            })
+        };
+        json!({
+            "id": raw.id,
+            "name": raw.name,
+            "name_ja": raw.ja.name,
+            "name_de": raw.de.name,
+            "name_fr": raw.fr.name,
+            "emote_category_target_id": raw.category,
+            "order": raw.order,
+            "log_message_targeted": log_message(|texts| &texts.targeted),
+            "log_message_untargeted": log_message(|texts| &texts.untargeted),
+            "text_command": {
+                "command_en": raw.en.command,
+                "command_ja": raw.ja.command,
+                "command_de": raw.de.command,
+                "command_fr": raw.fr.command,
+                "alias_en": raw.en.alias,
+                "alias_ja": raw.ja.alias,
+                "alias_de": raw.de.alias,
+                "alias_fr": raw.fr.alias,
+            },
+        })
+    }
+
+    fn parse_page(page: Value) -> Vec<RawEmoteData> {
+        let response: Response = serde_json::from_value(page).unwrap();
+        response
+            .results
+            .into_iter()
+            .filter_map(RawEmoteData::from_xivapi)
+            .collect()
+    }
+
+    #[test]
+    fn parses_emotes() {
+        let raw_emotes = fixture::raw_emotes();
+        let results: Vec<_> = raw_emotes.iter().map(xivapi_emote).collect();
+        let page = json!({ "pagination": { "page_next": null }, "results": results });
+
+        assert_eq!(parse_page(page), raw_emotes);
+    }
+
+    #[test]
+    fn skips_emotes_without_text_commands() {
+        let page = json!({
+            "pagination": { "page_next": 2 },
+            "results": [
+                { "id": 1, "name": "Unused", "text_command": null },
+                { "id": null, "name": null, "text_command": null },
+            ],
+        });
+
+        assert!(parse_page(page).is_empty());
+    }
+
+    #[test]
+    fn treats_category_zero_as_none() {
+        let mut emote = xivapi_emote(&fixture::raw_emotes()[0]);
+        emote["emote_category_target_id"] = json!(0);
+        let page = json!({ "pagination": { "page_next": null }, "results": [emote] });
+
+        assert_eq!(parse_page(page)[0].category, None);
+    }
+
+    #[tokio::test]
+    async fn builds_a_handler() {
+        let results: Vec<_> = fixture::raw_emotes().iter().map(xivapi_emote).collect();
+        let page = json!({ "pagination": { "page_next": null }, "results": results });
+
+        let handler = fixture::handler_from(Box::new(parse_page(page))).await;
+
+        let catalog = handler.catalog();
+        assert_eq!(catalog.get("/hug").unwrap().id, 112);
+        assert_eq!(catalog.get("/おどろく").unwrap().id, 1);
+        assert!(catalog.get("/エアクオート").unwrap().synthetic);
+    }
+}
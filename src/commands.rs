@@ -1,12 +1,20 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash, str::FromStr};
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
 
 use async_trait::async_trait;
 use serenity::{
     builder::CreateApplicationCommand,
-    model::prelude::{interaction::application_command::ApplicationCommandInteraction, CommandId},
+    model::prelude::{
+        command::CommandType,
+        interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+        },
+        CommandId,
+    },
     prelude::{Context, TypeMapKey},
 };
 
+use tracing::*;
+
 use crate::{util::LocalizedString, Handler, HandlerError, MessageDbData};
 
 pub mod global;
@@ -27,12 +35,21 @@ trait AppCmd {
     where
         Self: Sized;
     fn name() -> LocalizedString;
+    fn kind() -> CommandType
+    where
+        Self: Sized,
+    {
+        CommandType::ChatInput
+    }
 }
 
 #[async_trait]
 pub trait CommandsEnum:
-    FromStr + TypeMapKey<Value = HashMap<CommandId, Self>> + Debug + Copy + Eq + Hash
+    TypeMapKey<Value = HashMap<CommandId, Self>> + Debug + Copy + Eq + Hash
 {
+    /// Finds the command that Discord registered, by kind as well as name since context menu
+    /// commands are named separately from chat input commands
+    fn from_registered(name: &str, kind: CommandType) -> Option<Self>;
     async fn handle(
         self,
         cmd: &ApplicationCommandInteraction,
@@ -41,3 +58,14 @@ pub trait CommandsEnum:
         message_db_data: &MessageDbData,
     ) -> Result<(), HandlerError>;
 }
+
+/// Reads a string option of a subcommand by its position
+fn string_option(opt: &CommandDataOption, ind: usize) -> Result<&str, HandlerError> {
+    match opt.options.get(ind).and_then(|o| o.resolved.as_ref()) {
+        Some(CommandDataOptionValue::String(s)) => Ok(s),
+        v => {
+            error!(?v, ind, "expected a string option");
+            Err(HandlerError::UnexpectedData)
+        }
+    }
+}
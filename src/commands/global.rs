@@ -1,27 +1,36 @@
-use std::{collections::HashMap, str::FromStr};
+use std::collections::HashMap;
 
 use async_trait::async_trait;
 use serenity::{
     builder::CreateApplicationCommand,
-    model::prelude::{interaction::application_command::ApplicationCommandInteraction, CommandId},
+    model::prelude::{
+        command::CommandType, interaction::application_command::ApplicationCommandInteraction,
+        CommandId,
+    },
     prelude::{Context, TypeMapKey},
 };
 use strum::IntoEnumIterator;
 use strum_macros::{AsRefStr, Display, EnumIter};
-use thiserror::Error;
 
 use crate::{util::LocalizedString, Handler, HandlerError, MessageDbData};
 
 use self::{
-    emote::EmoteCmd, emote_select::EmoteSelectCmd, list_emotes::ListEmotesCmd,
+    emote::EmoteCmd, emote_aliases::EmoteAliasesCmd, emote_at_message::EmoteAtMessageCmd,
+    emote_at_user::EmoteAtUserCmd, emote_collisions::EmoteCollisionsCmd,
+    emote_select::EmoteSelectCmd, list_emotes::ListEmotesCmd, reload_emotes::ReloadEmotesCmd,
     stats::GlobalStatsCmd, user_settings::UserSettingsCmd,
 };
 
 use super::{AppCmd, CommandsEnum};
 
 pub mod emote;
+pub mod emote_aliases;
+pub mod emote_at_message;
+pub mod emote_at_user;
+pub mod emote_collisions;
 pub mod emote_select;
 pub mod list_emotes;
+pub mod reload_emotes;
 pub mod stats;
 pub mod user_settings;
 
@@ -32,6 +41,11 @@ pub enum GlobalCommands {
     Emote,
     ListEmotes,
     Stats,
+    ReloadEmotes,
+    EmoteCollisions,
+    EmoteAliases,
+    EmoteAtUser,
+    EmoteAtMessage,
 }
 
 impl GlobalCommands {
@@ -42,6 +56,11 @@ impl GlobalCommands {
             GlobalCommands::Emote => EmoteCmd::to_application_command(),
             GlobalCommands::ListEmotes => ListEmotesCmd::to_application_command(),
             GlobalCommands::Stats => GlobalStatsCmd::to_application_command(),
+            GlobalCommands::ReloadEmotes => ReloadEmotesCmd::to_application_command(),
+            GlobalCommands::EmoteCollisions => EmoteCollisionsCmd::to_application_command(),
+            GlobalCommands::EmoteAliases => EmoteAliasesCmd::to_application_command(),
+            GlobalCommands::EmoteAtUser => EmoteAtUserCmd::to_application_command(),
+            GlobalCommands::EmoteAtMessage => EmoteAtMessageCmd::to_application_command(),
         }
     }
 
@@ -56,12 +75,35 @@ impl GlobalCommands {
             GlobalCommands::Emote => EmoteCmd::name(),
             GlobalCommands::ListEmotes => ListEmotesCmd::name(),
             GlobalCommands::Stats => GlobalStatsCmd::name(),
+            GlobalCommands::ReloadEmotes => ReloadEmotesCmd::name(),
+            GlobalCommands::EmoteCollisions => EmoteCollisionsCmd::name(),
+            GlobalCommands::EmoteAliases => EmoteAliasesCmd::name(),
+            GlobalCommands::EmoteAtUser => EmoteAtUserCmd::name(),
+            GlobalCommands::EmoteAtMessage => EmoteAtMessageCmd::name(),
+        }
+    }
+    pub fn kind(self) -> CommandType {
+        match self {
+            GlobalCommands::EmoteSelect => EmoteSelectCmd::kind(),
+            GlobalCommands::UserSettings => UserSettingsCmd::kind(),
+            GlobalCommands::Emote => EmoteCmd::kind(),
+            GlobalCommands::ListEmotes => ListEmotesCmd::kind(),
+            GlobalCommands::Stats => GlobalStatsCmd::kind(),
+            GlobalCommands::ReloadEmotes => ReloadEmotesCmd::kind(),
+            GlobalCommands::EmoteCollisions => EmoteCollisionsCmd::kind(),
+            GlobalCommands::EmoteAliases => EmoteAliasesCmd::kind(),
+            GlobalCommands::EmoteAtUser => EmoteAtUserCmd::kind(),
+            GlobalCommands::EmoteAtMessage => EmoteAtMessageCmd::kind(),
         }
     }
 }
 
 #[async_trait]
 impl CommandsEnum for GlobalCommands {
+    fn from_registered(name: &str, kind: CommandType) -> Option<Self> {
+        GlobalCommands::iter().find(|cmd| cmd.kind() == kind && cmd.name().any_eq(name))
+    }
+
     async fn handle(
         self,
         cmd: &ApplicationCommandInteraction,
@@ -81,6 +123,21 @@ impl CommandsEnum for GlobalCommands {
                 ListEmotesCmd::handle(cmd, handler, context, message_db_data)
             }
             GlobalCommands::Stats => GlobalStatsCmd::handle(cmd, handler, context, message_db_data),
+            GlobalCommands::ReloadEmotes => {
+                ReloadEmotesCmd::handle(cmd, handler, context, message_db_data)
+            }
+            GlobalCommands::EmoteCollisions => {
+                EmoteCollisionsCmd::handle(cmd, handler, context, message_db_data)
+            }
+            GlobalCommands::EmoteAliases => {
+                EmoteAliasesCmd::handle(cmd, handler, context, message_db_data)
+            }
+            GlobalCommands::EmoteAtUser => {
+                EmoteAtUserCmd::handle(cmd, handler, context, message_db_data)
+            }
+            GlobalCommands::EmoteAtMessage => {
+                EmoteAtMessageCmd::handle(cmd, handler, context, message_db_data)
+            }
         }
         .await
     }
@@ -89,17 +146,3 @@ impl CommandsEnum for GlobalCommands {
 impl TypeMapKey for GlobalCommands {
     type Value = HashMap<CommandId, Self>;
 }
-
-#[derive(Debug, Clone, Error)]
-#[error("Not a valid command: {0}")]
-pub struct InvalidGlobalCommand(String);
-
-impl FromStr for GlobalCommands {
-    type Err = InvalidGlobalCommand;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        GlobalCommands::iter()
-            .find(|cmd| cmd.name().any_eq(s))
-            .ok_or_else(|| InvalidGlobalCommand(s.to_string()))
-    }
-}
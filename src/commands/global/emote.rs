@@ -1,12 +1,13 @@
-use std::borrow::Cow;
-
 use async_trait::async_trait;
 use const_format::concatcp;
 use serenity::{
     builder::CreateApplicationCommand,
     model::prelude::{
         command::{CommandOptionType, CommandType},
-        interaction::application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+        interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+            InteractionResponseType,
+        },
     },
     prelude::Context,
 };
@@ -14,6 +15,12 @@ use tracing::*;
 
 use crate::{
     commands::AppCmd,
+    handler::{
+        emotes::{EmoteMentions, PlainTarget},
+        normalize::normalize_emote_input,
+        send::OutgoingEmote,
+        suggestions::did_you_mean,
+    },
     util::{CreateApplicationCommandExt, CreateApplicationCommandOptionExt, LocalizedString},
     Handler, HandlerError, MessageDbData,
 };
@@ -23,26 +30,50 @@ use super::list_emotes::NAME as LIST_EMOTES_NAME;
 pub const NAME: LocalizedString = LocalizedString {
     en: "emote",
     ja: "エモート",
+    de: "emote",
+    fr: "emote",
 };
 pub const DESC: LocalizedString = LocalizedString {
     en: "Send an emote with an optional target user",
     ja: "エモートを選択してターゲットを任意選択して送信",
+    de: "Sende ein Emote mit optionalem Ziel",
+    fr: "Envoyer une emote avec une cible facultative",
 };
 pub const EMOTE_OPTION_NAME: LocalizedString = LocalizedString {
     en: "emote",
     ja: "エモート",
+    de: "emote",
+    fr: "emote",
 };
 pub const EMOTE_OPTION_DESC: LocalizedString = LocalizedString {
     en: "Which emote to send",
     ja: "エモートの指定",
+    de: "Welches Emote gesendet werden soll",
+    fr: "L'emote à envoyer",
 };
 pub const TARGET_OPTION_NAME: LocalizedString = LocalizedString {
     en: "target",
     ja: "ターゲット",
+    de: "ziel",
+    fr: "cible",
 };
 pub const TARGET_OPTION_DESC: LocalizedString = LocalizedString {
     en: "Who to target with the emote (can be a mention)",
     ja: "エモートのターゲット（メンション可）",
+    de: "Wer das Ziel des Emotes ist (kann eine Erwähnung sein)",
+    fr: "La cible de l'emote (peut être une mention)",
+};
+pub const TARGET_NPC_OPTION_NAME: LocalizedString = LocalizedString {
+    en: "target-npc",
+    ja: "npcターゲット",
+    de: "ziel-npc",
+    fr: "cible-pnj",
+};
+pub const TARGET_NPC_OPTION_DESC: LocalizedString = LocalizedString {
+    en: "Whether a target that isn't a mention is an NPC rather than a player",
+    ja: "メンション以外のターゲットがプレイヤーではなくNPCかどうか",
+    de: "Ob ein Ziel, das keine Erwähnung ist, ein NPC statt eines Spielers ist",
+    fr: "Si une cible qui n'est pas une mention est un PNJ plutôt qu'un joueur",
 };
 pub const EMOTE_NOT_EXISTS: LocalizedString = LocalizedString {
     en: concatcp!(
@@ -53,10 +84,20 @@ pub const EMOTE_NOT_EXISTS: LocalizedString = LocalizedString {
         "存在しないエモートを入力しました。エモート一覧のコマンドは/",
         LIST_EMOTES_NAME.ja
     ),
+    de: concatcp!(
+        "Das ist kein gültiges Emote! Die Liste der bekannten Emotes gibt es mit /",
+        LIST_EMOTES_NAME.de
+    ),
+    fr: concatcp!(
+        "Cette emote n'existe pas ! Consultez la liste des emotes connues avec /",
+        LIST_EMOTES_NAME.fr
+    ),
 };
 pub const EMOTE_SENT: LocalizedString = LocalizedString {
     en: "Emote sent!",
     ja: "送信しました！",
+    de: "Emote gesendet!",
+    fr: "Emote envoyée !",
 };
 
 pub struct EmoteCmd;
@@ -76,12 +117,18 @@ impl AppCmd for EmoteCmd {
                     .localized_name(EMOTE_OPTION_NAME)
                     .localized_desc(EMOTE_OPTION_DESC)
                     .required(true)
+                    .set_autocomplete(true)
             })
             .create_option(|opt| {
                 opt.kind(CommandOptionType::String)
                     .localized_name(TARGET_OPTION_NAME)
                     .localized_desc(TARGET_OPTION_DESC)
             })
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::Boolean)
+                    .localized_name(TARGET_NPC_OPTION_NAME)
+                    .localized_desc(TARGET_NPC_OPTION_DESC)
+            })
             .dm_permission(true);
         cmd
     }
@@ -96,10 +143,16 @@ impl AppCmd for EmoteCmd {
     where
         Self: Sized,
     {
+        if !handler
+            .check_emote_command_allowed(cmd, context, message_db_data)
+            .await?
+        {
+            return Ok(());
+        }
         let emote = &cmd
             .data
             .options
-            .get(0)
+            .first()
             .and_then(|o| o.resolved.as_ref())
             .and_then(|v| {
                 if let CommandDataOptionValue::String(s) = v {
@@ -111,69 +164,105 @@ impl AppCmd for EmoteCmd {
             .ok_or(HandlerError::UnexpectedData)?;
 
         let user_settings = message_db_data.determine_user_settings().await?;
-        let guild = message_db_data.guild().await?.unwrap_or_default();
+        let prefix = message_db_data.prefix().await?;
 
         info!(emote, "emote command");
 
-        let emote = match emote.get(0..0) {
-            None => {
-                error!("emote is empty");
-                return Err(HandlerError::UnrecognizedEmote("(empty)".to_string()));
-            }
-            Some("/") => Cow::Borrowed(emote.as_str()),
-            Some(s) if s == guild.prefix => Cow::Borrowed(emote.trim_start_matches(&guild.prefix)),
-            Some(_) => Cow::Owned(["/", emote].concat()),
+        let Some(emote) = normalize_emote_input(emote, &prefix) else {
+            error!("emote is empty");
+            return Err(HandlerError::UnrecognizedEmote("(empty)".to_string()));
         };
         trace!(?emote, "checking if emote exists");
-        if !handler.contains_emote(&emote) {
+        let Some(emote_data) = handler
+            .find_emote_for_user(&emote, &cmd.user.id, cmd.guild_id)
+            .await?
+        else {
+            let suggestions = handler.suggest_emotes(&emote, cmd.guild_id).await?;
+            let mut content = EMOTE_NOT_EXISTS.for_user(&user_settings).to_string();
+            if let Some(did_you_mean) = did_you_mean(&suggestions, &user_settings) {
+                content.push('\n');
+                content.push_str(&did_you_mean);
+            }
             cmd.create_interaction_response(context, |res| {
-                res.interaction_response_data(|data| {
-                    data.ephemeral(true)
-                        .content(EMOTE_NOT_EXISTS.for_user(&user_settings))
-                })
+                res.interaction_response_data(|data| data.ephemeral(true).content(content))
             })
             .await?;
             return Ok(());
-        }
+        };
 
-        let emote_data = handler
-            .get_emote_data(&emote)
-            .ok_or_else(|| HandlerError::UnrecognizedEmote(emote.to_string()))?;
-        let target = cmd
-            .data
-            .options
-            .get(1)
-            .and_then(|opt| opt.value.clone())
-            .and_then(|value| value.as_str().map(ToString::to_string));
-        let body = handler
-            .build_emote_message(emote_data, message_db_data, &cmd.user, target.as_deref())
+        // finding the channel's webhook and sending each message can take longer than the initial
+        // response window
+        cmd.create_interaction_response(context, |res| {
+            res.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|data| data.ephemeral(true))
+        })
+        .await?;
+
+        // optional options are only sent when given, so they can't be found by position
+        let option = |name: LocalizedString| {
+            cmd.data
+                .options
+                .iter()
+                .find(|opt| name.any_eq(&opt.name))
+                .and_then(|opt| opt.resolved.as_ref())
+        };
+        let target = match option(TARGET_OPTION_NAME) {
+            Some(CommandDataOptionValue::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let plain_target = PlainTarget {
+            is_npc: matches!(
+                option(TARGET_NPC_OPTION_NAME),
+                Some(CommandDataOptionValue::Boolean(true))
+            ),
+        };
+        let target_users: Vec<_> = cmd.data.resolved.users.keys().copied().collect();
+        let bodies = handler
+            .build_emote_messages(
+                &emote_data,
+                message_db_data,
+                &cmd.user,
+                target.as_deref(),
+                &target_users,
+                plain_target,
+            )
             .await?;
-        debug!(body, resolved = ?cmd.data.resolved, "processed emote");
-        cmd.channel_id
-            .send_message(context, |m| m.content(body))
+        debug!(?bodies, resolved = ?cmd.data.resolved, "processed emote");
+        let mentions = EmoteMentions::new(message_db_data, cmd.user.id, &target_users).await?;
+        handler
+            .send_emote(
+                context,
+                message_db_data,
+                OutgoingEmote {
+                    channel_id: cmd.channel_id,
+                    author: &cmd.user,
+                    emote: &emote_data,
+                    bodies,
+                    mentions,
+                    reply_to: None,
+                },
+            )
             .await?;
         handler
             .log_emote(
                 &cmd.user.id,
                 cmd.guild_id.as_ref(),
-                cmd.data.resolved.users.keys(),
-                emote_data,
+                target_users.iter(),
+                &emote_data,
             )
             .await?;
 
-        cmd.create_interaction_response(context, |res| {
-            res.interaction_response_data(|d| {
-                d.ephemeral(true).content(format!(
-                    "{} ({}{})",
-                    EMOTE_SENT.for_user(&user_settings),
-                    emote,
-                    if let Some(t) = &target {
-                        [" ".to_string(), t.to_string()].concat()
-                    } else {
-                        "".to_string()
-                    }
-                ))
-            })
+        let sent = if let Some(t) = &target {
+            format!("{} {}", emote, t)
+        } else {
+            emote.to_string()
+        };
+        cmd.edit_original_interaction_response(context, |d| {
+            d.content(format!(
+                "{} ({})",
+                EMOTE_SENT.for_user(&user_settings),
+                sent
+            ))
         })
         .await?;
 
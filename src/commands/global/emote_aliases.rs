@@ -0,0 +1,280 @@
+use async_trait::async_trait;
+use serenity::{
+    builder::CreateApplicationCommand,
+    model::prelude::{
+        command::{CommandOptionType, CommandType},
+        interaction::application_command::ApplicationCommandInteraction,
+    },
+    prelude::Context,
+};
+use tracing::*;
+
+use crate::{
+    commands::{string_option, AppCmd},
+    handler::guild_emotes::normalize_command,
+    util::{CreateApplicationCommandExt, CreateApplicationCommandOptionExt, LocalizedString},
+    Handler, HandlerError, MessageDbData,
+};
+
+use super::list_emotes::split_by_max_message_len;
+
+pub const NAME: LocalizedString = LocalizedString {
+    en: "emote-alias",
+    ja: "エモート略称",
+    de: "emote-kürzel",
+    fr: "raccourci-emote",
+};
+pub const DESC: LocalizedString = LocalizedString {
+    en: "Manage your own shortcuts for emotes",
+    ja: "自分専用のエモートの略称の管理",
+    de: "Eigene Kürzel für Emotes verwalten",
+    fr: "Gérer vos propres raccourcis d'emotes",
+};
+pub const SET_SUB_NAME: LocalizedString = LocalizedString {
+    en: "set",
+    ja: "設定",
+    de: "festlegen",
+    fr: "définir",
+};
+pub const SET_SUB_DESC: LocalizedString = LocalizedString {
+    en: "Add a shortcut, or change which emote it sends",
+    ja: "略称を追加、または送信するエモートを変更",
+    de: "Ein Kürzel hinzufügen oder ändern, welches Emote es sendet",
+    fr: "Ajouter un raccourci, ou changer l'emote qu'il envoie",
+};
+pub const REMOVE_SUB_NAME: LocalizedString = LocalizedString {
+    en: "remove",
+    ja: "削除",
+    de: "entfernen",
+    fr: "supprimer",
+};
+pub const REMOVE_SUB_DESC: LocalizedString = LocalizedString {
+    en: "Remove one of your shortcuts",
+    ja: "略称を削除",
+    de: "Eines deiner Kürzel entfernen",
+    fr: "Supprimer un de vos raccourcis",
+};
+pub const LIST_SUB_NAME: LocalizedString = LocalizedString {
+    en: "list",
+    ja: "一覧",
+    de: "liste",
+    fr: "liste",
+};
+pub const LIST_SUB_DESC: LocalizedString = LocalizedString {
+    en: "List your shortcuts",
+    ja: "略称の一覧",
+    de: "Deine Kürzel auflisten",
+    fr: "Lister vos raccourcis",
+};
+pub const ALIAS_OPT_NAME: LocalizedString = LocalizedString {
+    en: "alias",
+    ja: "略称",
+    de: "kürzel",
+    fr: "raccourci",
+};
+pub const ALIAS_OPT_DESC: LocalizedString = LocalizedString {
+    en: "Shortcut to type instead of the emote's command, such as h",
+    ja: "エモートのコマンドの代わりに入力する略称（例：h）",
+    de: "Kürzel, das statt des Befehls des Emotes eingegeben wird, etwa h",
+    fr: "Raccourci à taper à la place de la commande de l'emote, par exemple h",
+};
+pub const EMOTE_OPT_NAME: LocalizedString = LocalizedString {
+    en: "emote",
+    ja: "エモート",
+    de: "emote",
+    fr: "emote",
+};
+pub const EMOTE_OPT_DESC: LocalizedString = LocalizedString {
+    en: "Command of the emote the shortcut sends, such as /hug",
+    ja: "略称で送信するエモートのコマンド（例：/hug）",
+    de: "Befehl des Emotes, das das Kürzel sendet, etwa /hug",
+    fr: "Commande de l'emote envoyée par le raccourci, par exemple /hug",
+};
+pub const ALIAS_SET: LocalizedString = LocalizedString {
+    en: "Shortcut set:",
+    ja: "略称を設定しました：",
+    de: "Kürzel festgelegt:",
+    fr: "Raccourci défini :",
+};
+pub const ALIAS_REMOVED: LocalizedString = LocalizedString {
+    en: "Shortcut removed:",
+    ja: "略称を削除しました：",
+    de: "Kürzel entfernt:",
+    fr: "Raccourci supprimé :",
+};
+pub const ALIAS_NOT_FOUND: LocalizedString = LocalizedString {
+    en: "You have no such shortcut:",
+    ja: "この略称は存在しません：",
+    de: "Du hast kein solches Kürzel:",
+    fr: "Vous n'avez pas ce raccourci :",
+};
+pub const INVALID_ALIAS: LocalizedString = LocalizedString {
+    en: "Shortcuts can't be empty, contain spaces or be longer than 30 characters",
+    ja: "略称は空欄、空白を含むもの、30文字を超えるものにはできません",
+    de: "Kürzel dürfen nicht leer sein, keine Leerzeichen enthalten und höchstens 30 Zeichen lang sein",
+    fr: "Les raccourcis ne peuvent pas être vides, contenir d'espaces ou dépasser 30 caractères",
+};
+pub const ALIAS_IS_EMOTE: LocalizedString = LocalizedString {
+    en: "That shortcut is already the command of an emote:",
+    ja: "この略称は既にエモートのコマンドです：",
+    de: "Dieses Kürzel ist bereits der Befehl eines Emotes:",
+    fr: "Ce raccourci est déjà la commande d'une emote :",
+};
+pub const EMOTE_NOT_FOUND: LocalizedString = LocalizedString {
+    en: "There's no emote with that command:",
+    ja: "このコマンドのエモートは存在しません：",
+    de: "Es gibt kein Emote mit diesem Befehl:",
+    fr: "Il n'y a pas d'emote avec cette commande :",
+};
+pub const NO_ALIASES: LocalizedString = LocalizedString {
+    en: "You have no shortcuts",
+    ja: "略称はありません",
+    de: "Du hast keine Kürzel",
+    fr: "Vous n'avez aucun raccourci",
+};
+pub const LIST_MSG_PREFIX: LocalizedString = LocalizedString {
+    en: "Your shortcuts",
+    ja: "略称一覧",
+    de: "Deine Kürzel",
+    fr: "Vos raccourcis",
+};
+
+pub struct EmoteAliasesCmd;
+
+#[async_trait]
+impl AppCmd for EmoteAliasesCmd {
+    fn to_application_command() -> CreateApplicationCommand
+    where
+        Self: Sized,
+    {
+        let mut cmd = CreateApplicationCommand::default();
+        cmd.localized_name(NAME)
+            .kind(CommandType::ChatInput)
+            .localized_desc(DESC)
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::SubCommand)
+                    .localized_name(SET_SUB_NAME)
+                    .localized_desc(SET_SUB_DESC)
+                    .create_sub_option(|sub| {
+                        sub.kind(CommandOptionType::String)
+                            .localized_name(ALIAS_OPT_NAME)
+                            .localized_desc(ALIAS_OPT_DESC)
+                            .required(true)
+                    })
+                    .create_sub_option(|sub| {
+                        sub.kind(CommandOptionType::String)
+                            .localized_name(EMOTE_OPT_NAME)
+                            .localized_desc(EMOTE_OPT_DESC)
+                            .required(true)
+                    })
+            })
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::SubCommand)
+                    .localized_name(REMOVE_SUB_NAME)
+                    .localized_desc(REMOVE_SUB_DESC)
+                    .create_sub_option(|sub| {
+                        sub.kind(CommandOptionType::String)
+                            .localized_name(ALIAS_OPT_NAME)
+                            .localized_desc(ALIAS_OPT_DESC)
+                            .required(true)
+                    })
+            })
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::SubCommand)
+                    .localized_name(LIST_SUB_NAME)
+                    .localized_desc(LIST_SUB_DESC)
+            })
+            .dm_permission(true);
+        cmd
+    }
+
+    #[instrument(skip(cmd, handler, context))]
+    async fn handle(
+        cmd: &ApplicationCommandInteraction,
+        handler: &Handler,
+        context: &Context,
+        message_db_data: &MessageDbData,
+    ) -> Result<(), HandlerError>
+    where
+        Self: Sized,
+    {
+        let user = message_db_data.determine_user_settings().await?;
+        let sub = cmd
+            .data
+            .options
+            .first()
+            .ok_or(HandlerError::UnexpectedData)?;
+        info!(sub.name, "emote alias command");
+
+        let bodies = if LIST_SUB_NAME.any_eq(&sub.name) {
+            let aliases = handler.db.find_user_emote_aliases(&cmd.user.id).await?;
+            let mut bodies = split_by_max_message_len(
+                LIST_MSG_PREFIX.for_user(&user),
+                aliases
+                    .into_iter()
+                    .map(|alias| format!("{} → {}", alias.alias, alias.command)),
+            );
+            if bodies.is_empty() {
+                bodies.push(NO_ALIASES.for_user(&user).to_string());
+            }
+            bodies
+        } else {
+            let alias = string_option(sub, 0)?;
+            let body = match normalize_command(alias) {
+                None => INVALID_ALIAS.for_user(&user).to_string(),
+                Some(alias) if REMOVE_SUB_NAME.any_eq(&sub.name) => {
+                    if handler
+                        .db
+                        .delete_user_emote_alias(&cmd.user.id, &alias)
+                        .await?
+                    {
+                        format!("{} {}", ALIAS_REMOVED.for_user(&user), alias)
+                    } else {
+                        format!("{} {}", ALIAS_NOT_FOUND.for_user(&user), alias)
+                    }
+                }
+                Some(alias) if SET_SUB_NAME.any_eq(&sub.name) => {
+                    let emote = string_option(sub, 1)?;
+                    let emote = normalize_command(emote).unwrap_or_else(|| emote.to_string());
+                    // guild emotes are checked too, so that an alias can't hide one
+                    if handler.find_emote(&alias, cmd.guild_id).await?.is_some() {
+                        format!("{} {}", ALIAS_IS_EMOTE.for_user(&user), alias)
+                    } else if handler.find_emote(&emote, cmd.guild_id).await?.is_none() {
+                        format!("{} {}", EMOTE_NOT_FOUND.for_user(&user), emote)
+                    } else {
+                        handler
+                            .db
+                            .upsert_user_emote_alias(&cmd.user.id, &alias, &emote)
+                            .await?;
+                        format!("{} {} → {}", ALIAS_SET.for_user(&user), alias, emote)
+                    }
+                }
+                Some(_) => {
+                    error!(sub.name, "unknown emote alias subcommand");
+                    return Err(HandlerError::UnexpectedData);
+                }
+            };
+            vec![body]
+        };
+
+        let mut body_iter = bodies.into_iter();
+
+        if let Some(body) = body_iter.next() {
+            cmd.create_interaction_response(context, |res| {
+                res.interaction_response_data(|data| data.ephemeral(true).content(body))
+            })
+            .await?;
+        }
+
+        for body in body_iter {
+            cmd.create_followup_message(context, |data| data.ephemeral(true).content(body))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn name() -> LocalizedString {
+        NAME
+    }
+}
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use serenity::{
+    builder::CreateApplicationCommand,
+    model::prelude::{
+        command::CommandType,
+        interaction::application_command::{ApplicationCommandInteraction, ResolvedTarget},
+    },
+    prelude::Context,
+};
+use tracing::*;
+
+use crate::{
+    commands::AppCmd,
+    util::{CreateApplicationCommandExt, LocalizedString},
+    Handler, HandlerError, MessageDbData,
+};
+
+use super::emote_select::pick_and_send_emote;
+
+pub const NAME: LocalizedString = LocalizedString {
+    en: "Emote at…",
+    ja: "エモートを送る…",
+    de: "Emote senden an…",
+    fr: "Emote vers…",
+};
+
+pub struct EmoteAtUserCmd;
+
+#[async_trait]
+impl AppCmd for EmoteAtUserCmd {
+    fn to_application_command() -> CreateApplicationCommand
+    where
+        Self: Sized,
+    {
+        let mut cmd = CreateApplicationCommand::default();
+        // context menu commands can't have descriptions
+        cmd.localized_name(NAME)
+            .kind(CommandType::User)
+            .dm_permission(true);
+        cmd
+    }
+
+    #[instrument(skip(cmd, handler, context))]
+    async fn handle(
+        cmd: &ApplicationCommandInteraction,
+        handler: &Handler,
+        context: &Context,
+        message_db_data: &MessageDbData,
+    ) -> Result<(), HandlerError>
+    where
+        Self: Sized,
+    {
+        let Some(ResolvedTarget::User(target, _)) = cmd.data.target() else {
+            error!(target_id = ?cmd.data.target_id, "user command without a resolved user");
+            return Err(HandlerError::UnexpectedData);
+        };
+        info!(?target.id, "emote at user command");
+        pick_and_send_emote(cmd, handler, context, message_db_data, Some(target.id)).await
+    }
+
+    fn name() -> LocalizedString {
+        NAME
+    }
+
+    fn kind() -> CommandType
+    where
+        Self: Sized,
+    {
+        CommandType::User
+    }
+}
@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use serenity::{
+    builder::CreateApplicationCommand,
+    model::{
+        prelude::{
+            command::CommandType, interaction::application_command::ApplicationCommandInteraction,
+        },
+        Permissions,
+    },
+    prelude::Context,
+};
+use tracing::*;
+
+use crate::{
+    catalog::CommandCollision,
+    commands::AppCmd,
+    util::{CreateApplicationCommandExt, LocalizedString},
+    Handler, HandlerError, MessageDbData,
+};
+
+use super::list_emotes::split_by_max_message_len;
+
+pub const NAME: LocalizedString = LocalizedString {
+    en: "emote-collisions",
+    ja: "エモート重複",
+    de: "emote-kollisionen",
+    fr: "collisions-emotes",
+};
+pub const DESC: LocalizedString = LocalizedString {
+    en: "List commands that more than one emote uses (bot owner only)",
+    ja: "複数のエモートで重複しているコマンドの一覧（ボット所有者のみ）",
+    de: "Befehle auflisten, die mehrere Emotes verwenden (nur Bot-Besitzer)",
+    fr: "Lister les commandes utilisées par plusieurs emotes (propriétaire du bot uniquement)",
+};
+pub const NOT_OWNER: LocalizedString = LocalizedString {
+    en: "Only the bot owner can view emote collisions",
+    ja: "エモート重複の確認はボット所有者のみ可能です",
+    de: "Nur der Bot-Besitzer kann Emote-Kollisionen einsehen",
+    fr: "Seul le propriétaire du bot peut consulter les collisions d'emotes",
+};
+pub const NO_COLLISIONS: LocalizedString = LocalizedString {
+    en: "No commands are used by more than one emote",
+    ja: "重複しているコマンドはありません",
+    de: "Keine Befehle werden von mehreren Emotes verwendet",
+    fr: "Aucune commande n'est utilisée par plusieurs emotes",
+};
+pub const COLLISIONS_PREFIX: LocalizedString = LocalizedString {
+    en: "Command collisions (winner first)",
+    ja: "重複コマンド（優先順）",
+    de: "Befehlskollisionen (Gewinner zuerst)",
+    fr: "Collisions de commandes (gagnante en premier)",
+};
+
+/// Marks collisions that were only decided by emote id
+const AMBIGUOUS_MARKER: &str = "⚠️";
+
+pub struct EmoteCollisionsCmd;
+
+fn collision_item(collision: &CommandCollision) -> String {
+    let owners: Vec<_> = [&collision.winner]
+        .into_iter()
+        .chain(&collision.losers)
+        .map(|owner| owner.name.as_str())
+        .collect();
+    format!(
+        "{}{}: {}",
+        if collision.is_ambiguous() {
+            AMBIGUOUS_MARKER
+        } else {
+            ""
+        },
+        collision.command,
+        owners.join(" > ")
+    )
+}
+
+#[async_trait]
+impl AppCmd for EmoteCollisionsCmd {
+    fn to_application_command() -> CreateApplicationCommand
+    where
+        Self: Sized,
+    {
+        let mut cmd = CreateApplicationCommand::default();
+        cmd.localized_name(NAME)
+            .kind(CommandType::ChatInput)
+            .localized_desc(DESC)
+            // hides it from most users, but ownership is still checked when handling
+            .default_member_permissions(Permissions::ADMINISTRATOR)
+            .dm_permission(true);
+        cmd
+    }
+
+    #[instrument(skip(cmd, handler, context))]
+    async fn handle(
+        cmd: &ApplicationCommandInteraction,
+        handler: &Handler,
+        context: &Context,
+        message_db_data: &MessageDbData,
+    ) -> Result<(), HandlerError>
+    where
+        Self: Sized,
+    {
+        let user = message_db_data.determine_user_settings().await?;
+
+        if !handler.is_owner(context, cmd.user.id).await? {
+            warn!("non-owner tried to view emote collisions");
+            cmd.create_interaction_response(context, |res| {
+                res.interaction_response_data(|data| {
+                    data.ephemeral(true).content(NOT_OWNER.for_user(&user))
+                })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        info!("emote collisions command");
+        let catalog = handler.catalog();
+        let mut bodies = split_by_max_message_len(
+            COLLISIONS_PREFIX.for_user(&user),
+            catalog.collisions().iter().map(collision_item),
+        );
+        if bodies.is_empty() {
+            bodies.push(NO_COLLISIONS.for_user(&user).to_string());
+        }
+        debug!("collisions response is {} messages long", bodies.len());
+
+        let mut body_iter = bodies.into_iter();
+
+        if let Some(body) = body_iter.next() {
+            cmd.create_interaction_response(context, |res| {
+                res.interaction_response_data(|data| data.ephemeral(true).content(body))
+            })
+            .await?;
+        }
+
+        for body in body_iter {
+            cmd.create_followup_message(context, |data| data.ephemeral(true).content(body))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn name() -> LocalizedString {
+        NAME
+    }
+}
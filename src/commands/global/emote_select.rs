@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use futures::stream::StreamExt;
@@ -9,7 +12,7 @@ use serenity::{
         id::UserId,
         prelude::{
             command::CommandType,
-            component::{ActionRowComponent, InputTextStyle},
+            component::{ActionRowComponent, ButtonStyle, InputTextStyle},
             interaction::{
                 application_command::ApplicationCommandInteraction,
                 message_component::MessageComponentInteraction, InteractionResponseType,
@@ -20,77 +23,139 @@ use serenity::{
     },
     prelude::{Context, Mentionable},
 };
+use strum::IntoEnumIterator;
 use thiserror::Error;
 use tracing::*;
 
 use crate::{
     commands::AppCmd,
-    db::models::DbUser,
-    handler::emotes::UNTARGETED_TARGET,
+    db::models::{DbEmoteCategory, DbUser},
+    handler::{
+        emotes::{EmoteListItem, EmoteMentions, PlainTarget, UNTARGETED_TARGET},
+        send::OutgoingEmote,
+    },
     util::{CreateApplicationCommandExt, LocalizedString},
-    HandlerError, MessageDbData, INTERACTION_TIMEOUT,
+    Handler, HandlerError, MessageDbData, INTERACTION_TIMEOUT,
 };
 
 pub const CONTENT: LocalizedString = LocalizedString {
     en: "Select an emote and optionally a target",
     ja: "エモートを選択してターゲットを任意選択して送信",
+    de: "Wähle ein Emote und optional ein Ziel",
+    fr: "Choisissez une emote et éventuellement une cible",
+};
+pub const FIXED_TARGET_CONTENT: LocalizedString = LocalizedString {
+    en: "Select an emote to send to",
+    ja: "送信するエモートを選択してください。ターゲット：",
+    de: "Wähle ein Emote zum Senden an",
+    fr: "Choisissez une emote à envoyer à",
 };
 pub const NO_USER_SELECTED: LocalizedString = LocalizedString {
     en: "No user selected",
     ja: "ユーザー未選択",
+    de: "Kein Benutzer ausgewählt",
+    fr: "Aucun utilisateur sélectionné",
 };
 pub const INPUT_USER_BTN: LocalizedString = LocalizedString {
     en: "Input custom target",
     ja: "ターゲット指定入力",
+    de: "Eigenes Ziel eingeben",
+    fr: "Saisir une cible personnalisée",
 };
 pub const INPUT_TARGET_MODAL_CONTENT: LocalizedString = LocalizedString {
     en: "Input target name",
     ja: "ターゲットの名前を入力してください",
+    de: "Namen des Ziels eingeben",
+    fr: "Saisissez le nom de la cible",
 };
 pub const INPUT_TARGET_MODAL_INPUT: LocalizedString = LocalizedString {
     en: "Target name",
     ja: "ターゲットの名前",
+    de: "Name des Ziels",
+    fr: "Nom de la cible",
 };
 pub const INPUT_TARGET_MODAL_TITLE: LocalizedString = LocalizedString {
     en: "Custom emote target",
     ja: "エモートのターゲット指定",
+    de: "Eigenes Emote-Ziel",
+    fr: "Cible d'emote personnalisée",
+};
+pub const PLAYER_TARGET_BTN: LocalizedString = LocalizedString {
+    en: "Custom target is a player",
+    ja: "ターゲット：プレイヤー",
+    de: "Eigenes Ziel ist ein Spieler",
+    fr: "La cible est un joueur",
+};
+pub const NPC_TARGET_BTN: LocalizedString = LocalizedString {
+    en: "Custom target is an NPC",
+    ja: "ターゲット：NPC",
+    de: "Eigenes Ziel ist ein NPC",
+    fr: "La cible est un PNJ",
+};
+pub const ALL_CATEGORIES: LocalizedString = LocalizedString {
+    en: "All categories",
+    ja: "すべてのカテゴリ",
+    de: "Alle Kategorien",
+    fr: "Toutes les catégories",
 };
 pub const NO_EMOTE_SELECTED: LocalizedString = LocalizedString {
     en: "No emote selected",
     ja: "エモート未選択",
+    de: "Kein Emote ausgewählt",
+    fr: "Aucune emote sélectionnée",
+};
+pub const NO_EMOTES_IN_CATEGORY: LocalizedString = LocalizedString {
+    en: "No emotes in this category",
+    ja: "このカテゴリーにはエモートがありません",
+    de: "Keine Emotes in dieser Kategorie",
+    fr: "Aucune emote dans cette catégorie",
 };
 pub const PREV_EMOTE_PAGE: LocalizedString = LocalizedString {
     en: "Previous emote page",
     ja: "前のエモートページへ",
+    de: "Vorherige Emote-Seite",
+    fr: "Page d'emotes précédente",
 };
 pub const NEXT_EMOTE_PAGE: LocalizedString = LocalizedString {
     en: "Next emote page",
     ja: "次のエモートページへ",
+    de: "Nächste Emote-Seite",
+    fr: "Page d'emotes suivante",
 };
 pub const SEND_BTN: LocalizedString = LocalizedString {
     en: "Send",
     ja: "送信",
+    de: "Senden",
+    fr: "Envoyer",
 };
 pub const EMOTE_SENT: LocalizedString = LocalizedString {
     en: "Emote sent!",
     ja: "送信しました！",
+    de: "Emote gesendet!",
+    fr: "Emote envoyée !",
 };
 pub const NAME: LocalizedString = LocalizedString {
     en: "emote-select",
     ja: "エモート選択",
+    de: "emote-auswahl",
+    fr: "choix-emote",
 };
 pub const DESC: LocalizedString = LocalizedString {
     en: "Interactively select and send an emote with an optional target user",
     // todo figure out better translation for this
     ja: "エモートを選択してターゲットを任意選択して送信",
+    de: "Interaktiv ein Emote mit optionalem Ziel auswählen und senden",
+    fr: "Choisir et envoyer une emote de façon interactive, avec une cible facultative",
 };
 
 const INPUT_TARGET_MODAL: &str = "input_target_modal";
 const INPUT_TARGET_COMPONENT: &str = "input_target_input";
 
 enum Ids {
+    CategorySelect,
     TargetSelect,
     InputTargetBtn,
+    NpcTargetBtn,
     EmoteSelect,
     EmotePrevBtn,
     EmoteNextBtn,
@@ -106,8 +171,10 @@ impl From<Ids> for &'static str {
 impl From<&Ids> for &'static str {
     fn from(ids: &Ids) -> Self {
         match ids {
+            Ids::CategorySelect => "category_select",
             Ids::TargetSelect => "user_select",
             Ids::InputTargetBtn => "input_target_btn",
+            Ids::NpcTargetBtn => "npc_target_btn",
             Ids::EmoteSelect => "emote_select",
             Ids::EmotePrevBtn => "prev_emotes",
             Ids::EmoteNextBtn => "next_emotes",
@@ -116,9 +183,9 @@ impl From<&Ids> for &'static str {
     }
 }
 
-impl ToString for Ids {
-    fn to_string(&self) -> String {
-        Into::<&'static str>::into(self).to_string()
+impl Display for Ids {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.into())
     }
 }
 
@@ -131,8 +198,10 @@ impl TryFrom<&str> for Ids {
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
+            "category_select" => Ok(Ids::CategorySelect),
             "user_select" => Ok(Ids::TargetSelect),
             "input_target_btn" => Ok(Ids::InputTargetBtn),
+            "npc_target_btn" => Ok(Ids::NpcTargetBtn),
             "emote_select" => Ok(Ids::EmoteSelect),
             "prev_emotes" => Ok(Ids::EmotePrevBtn),
             "next_emotes" => Ok(Ids::EmoteNextBtn),
@@ -144,7 +213,7 @@ impl TryFrom<&str> for Ids {
 
 #[derive(Debug, Clone)]
 enum Target {
-    User(UserId),
+    Users(Vec<UserId>),
     // Role(Role),
     Plain(String),
 }
@@ -155,27 +224,35 @@ impl Default for Target {
     }
 }
 
-impl ToString for Target {
-    fn to_string(&self) -> String {
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Target::User(u) => u.mention().to_string(),
-            // Target::Role(r) => r.name.clone(),
-            Target::Plain(s) => s.to_string(),
+            Target::Users(users) => {
+                let mentions: Vec<_> = users.iter().map(|u| u.mention().to_string()).collect();
+                f.write_str(&mentions.join(" "))
+            }
+            // Target::Role(r) => f.write_str(&r.name),
+            Target::Plain(s) => f.write_str(s),
         }
     }
 }
 
 impl Target {
-    fn user_id(&self) -> Option<&UserId> {
+    fn user_ids(&self) -> &[UserId] {
         match self {
-            Target::User(u) => Some(u),
-            Target::Plain(_) => None,
+            Target::Users(users) => users,
+            Target::Plain(_) => &[],
         }
     }
 }
 
 // max number of select menu options
 const EMOTE_LIST_OFFSET_STEP: usize = 25;
+// the only option of the emote select when the category is empty, which Discord requires at
+// least one option for even though it's disabled
+const NO_EMOTES_VALUE: &str = "none";
+// categories start from 1, the same way empty links are row 0 in the game's sheets
+const ALL_CATEGORIES_VALUE: i32 = 0;
 
 #[derive(Debug, Clone)]
 struct UserInfo {
@@ -213,29 +290,63 @@ impl From<&User> for UserInfo {
 struct InteractionResult {
     emote: String,
     target: Option<Target>,
+    npc_target: bool,
 }
 
 fn interaction_response_content(
     emote_list_len: usize,
-    emote_list_offset: Option<usize>,
+    selection: &Selection,
     user: &DbUser,
 ) -> String {
+    let content = match &selection.selected_target_value {
+        Some(target) if selection.fixed_target => {
+            format!("{} {}", FIXED_TARGET_CONTENT.for_user(user), target)
+        }
+        _ => CONTENT.for_user(user).to_string(),
+    };
     format!(
         "{} ({}/{})",
-        CONTENT.for_user(user),
-        emote_list_offset
+        content,
+        selection
+            .emote_list_offset
             .map(|off| off / EMOTE_LIST_OFFSET_STEP)
             .unwrap_or(0)
             + 1,
-        emote_list_len / EMOTE_LIST_OFFSET_STEP + 1
+        emote_list_len.max(1).div_ceil(EMOTE_LIST_OFFSET_STEP)
     )
 }
 
+/// Every emote that can be selected, both built-in and defined by the guild
+#[derive(Clone, Copy)]
+struct SelectableEmotes<'a> {
+    handler: &'a Handler,
+    guild_emotes: &'a [EmoteListItem],
+}
+
+impl<'a> SelectableEmotes<'a> {
+    /// Guild emotes come first when listing every category, as there are far fewer of them
+    fn list(self, category: Option<DbEmoteCategory>) -> Vec<EmoteListItem> {
+        match category {
+            Some(_) => self.handler.emote_list_in_category(category),
+            None => self
+                .guild_emotes
+                .iter()
+                .cloned()
+                .chain(self.handler.emote_list_in_category(None))
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 struct Selection {
+    category: Option<DbEmoteCategory>,
     emote_list_offset: Option<usize>,
     selected_emote_value: Option<String>,
     selected_target_value: Option<Target>,
+    npc_target: bool,
+    /// Whether the target was chosen before the picker was opened, so it can't be changed
+    fixed_target: bool,
 }
 
 #[instrument(skip(res))]
@@ -243,7 +354,7 @@ fn create_response<'a, 'b>(
     res: &'a mut CreateInteractionResponse<'b>,
     kind: InteractionResponseType,
     user: &DbUser,
-    emote_list: &[impl AsRef<str> + std::fmt::Debug],
+    emote_list: &[EmoteListItem],
     selection: &Selection,
     members: &[UserInfo],
 ) -> &'a mut CreateInteractionResponse<'b> {
@@ -251,12 +362,43 @@ fn create_response<'a, 'b>(
         d.ephemeral(true)
             .content(interaction_response_content(
                 emote_list.len(),
-                selection.emote_list_offset,
+                selection,
                 user,
             ))
             .components(|c| {
                 c.create_action_row(|row| {
                     row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::CategorySelect).options(|opts| {
+                            opts.create_option(|o| {
+                                o.label(ALL_CATEGORIES.for_user(user))
+                                    .value(ALL_CATEGORIES_VALUE)
+                                    .default_selection(selection.category.is_none())
+                            });
+                            DbEmoteCategory::iter().for_each(|category| {
+                                opts.create_option(|o| {
+                                    o.label(category.for_user(user))
+                                        .value(category as i32)
+                                        .default_selection(selection.category == Some(category))
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        if emote_list.is_empty() {
+                            return menu
+                                .custom_id(Ids::EmoteSelect)
+                                .placeholder(NO_EMOTES_IN_CATEGORY.for_user(user))
+                                .disabled(true)
+                                .options(|opts| {
+                                    opts.create_option(|o| {
+                                        o.label(NO_EMOTES_IN_CATEGORY.for_user(user))
+                                            .value(NO_EMOTES_VALUE)
+                                    })
+                                });
+                        }
                         menu.custom_id(Ids::EmoteSelect)
                             .placeholder(NO_EMOTE_SELECTED.for_user(user))
                             .options(|opts| {
@@ -265,15 +407,16 @@ fn create_response<'a, 'b>(
                                     .skip(selection.emote_list_offset.unwrap_or(0))
                                     .take(EMOTE_LIST_OFFSET_STEP)
                                 {
-                                    let emote = emote.as_ref();
                                     opts.create_option(|o| {
-                                        o.label(emote).value(emote).default_selection(
-                                            selection
-                                                .selected_emote_value
-                                                .as_ref()
-                                                .map(|v| v == emote)
-                                                .unwrap_or(false),
-                                        )
+                                        o.label(emote.label())
+                                            .value(&emote.command)
+                                            .default_selection(
+                                                selection
+                                                    .selected_emote_value
+                                                    .as_ref()
+                                                    .map(|v| *v == emote.command)
+                                                    .unwrap_or(false),
+                                            )
                                     });
                                 }
                                 opts
@@ -295,16 +438,23 @@ fn create_response<'a, 'b>(
                         btn.custom_id(Ids::EmoteNextBtn)
                             .label(NEXT_EMOTE_PAGE.for_user(user))
                             .disabled(
-                                selection
-                                    .emote_list_offset
-                                    .map(|off| off + EMOTE_LIST_OFFSET_STEP >= emote_list.len())
-                                    .unwrap_or(false),
+                                selection.emote_list_offset.unwrap_or(0) + EMOTE_LIST_OFFSET_STEP
+                                    >= emote_list.len(),
                             )
                     })
                 });
+                if selection.fixed_target {
+                    c.create_action_row(|row| {
+                        row.create_button(|btn| {
+                            btn.custom_id(Ids::Submit).label(SEND_BTN.for_user(user))
+                        })
+                    });
+                    return c;
+                }
                 c.create_action_row(|row| {
                     row.create_select_menu(|menu| {
                         menu.custom_id(Ids::TargetSelect)
+                            .max_values(members.len().clamp(1, EMOTE_LIST_OFFSET_STEP) as u64)
                             .placeholder(
                                 selection
                                     .selected_target_value
@@ -323,9 +473,7 @@ fn create_response<'a, 'b>(
                                             selection
                                                 .selected_target_value
                                                 .as_ref()
-                                                .map(
-                                                    |t| matches!(t, Target::User(u) if *u == value),
-                                                )
+                                                .map(|t| t.user_ids().contains(&value))
                                                 .unwrap_or(false),
                                         )
                                     });
@@ -334,13 +482,26 @@ fn create_response<'a, 'b>(
                             })
                     })
                 });
+                // messages can only have five action rows
                 c.create_action_row(|row| {
                     row.create_button(|btn| {
                         btn.custom_id(Ids::InputTargetBtn)
                             .label(INPUT_USER_BTN.for_user(user))
-                    })
-                });
-                c.create_action_row(|row| {
+                    });
+                    // only custom targets can be NPCs, users always use their own settings
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::NpcTargetBtn)
+                            .style(ButtonStyle::Secondary)
+                            .label(if selection.npc_target {
+                                NPC_TARGET_BTN.for_user(user)
+                            } else {
+                                PLAYER_TARGET_BTN.for_user(user)
+                            })
+                            .disabled(!matches!(
+                                selection.selected_target_value,
+                                Some(Target::Plain(_))
+                            ))
+                    });
                     row.create_button(|btn| {
                         btn.custom_id(Ids::Submit).label(SEND_BTN.for_user(user))
                     })
@@ -349,16 +510,17 @@ fn create_response<'a, 'b>(
     })
 }
 
-#[instrument(skip(context))]
+#[instrument(skip(context, emotes))]
 async fn handle_interaction(
     context: &Context,
+    emotes: SelectableEmotes<'_>,
     msg: &Message,
     user: &DbUser,
-    emote_list: &[impl AsRef<str> + std::fmt::Debug],
     members: &[UserInfo],
     interaction: Arc<MessageComponentInteraction>,
     selection: &mut Selection,
 ) -> Result<Option<InteractionResult>, HandlerError> {
+    let emote_list = emotes.list(selection.category);
     match Ids::try_from(interaction.data.custom_id.as_str()) {
         Ok(Ids::InputTargetBtn) => {
             debug!("target input");
@@ -400,7 +562,7 @@ async fn handle_interaction(
                                         res,
                                         InteractionResponseType::UpdateMessage,
                                         user,
-                                        emote_list,
+                                        &emote_list,
                                         selection,
                                         members,
                                     )
@@ -420,6 +582,33 @@ async fn handle_interaction(
             // don't send typical interaction response
             return Ok(None);
         }
+        Ok(Ids::NpcTargetBtn) => {
+            selection.npc_target = !selection.npc_target;
+            debug!(selection.npc_target, "npc target toggled");
+        }
+        Ok(Ids::CategorySelect) => {
+            let value = &interaction.data.values[0];
+            let value = if let Ok(v) = value.parse() {
+                v
+            } else {
+                error!(value, "unexpected category selected (not numeric)");
+                return Err(HandlerError::UnexpectedData);
+            };
+            let category = match DbEmoteCategory::from_repr(value) {
+                Some(c) => Some(c),
+                None if value == ALL_CATEGORIES_VALUE => None,
+                None => {
+                    error!(value, "unexpected category selected (invalid number)");
+                    return Err(HandlerError::UnexpectedData);
+                }
+            };
+            debug!(?category, "category selected");
+            if selection.category != category {
+                selection.category = category;
+                selection.emote_list_offset = None;
+                selection.selected_emote_value = None;
+            }
+        }
         Ok(Ids::EmoteSelect) => {
             let em = interaction.data.values[0].clone();
             debug!(em, "emote selected");
@@ -442,29 +631,36 @@ async fn handle_interaction(
             };
         }
         Ok(Ids::TargetSelect) => {
-            let ta = interaction.data.values[0].clone();
-            debug!(ta, "target selected");
-            let user_id: UserId = match ta.parse::<u64>() {
-                Ok(id) => id,
-                Err(err) => {
-                    error!(?err, "stored user id was not a number");
-                    return Err(HandlerError::UserNotFound);
-                }
-            }
-            .into();
-            selection.selected_target_value.replace(Target::User(
-                members
-                    .iter()
-                    .map(|member| member.id)
-                    .find(|user| *user == user_id)
-                    .ok_or(HandlerError::UserNotFound)?,
-            ));
+            let values = &interaction.data.values;
+            debug!(?values, "targets selected");
+            let users = values
+                .iter()
+                .map(|value| {
+                    let user_id: UserId = match value.parse::<u64>() {
+                        Ok(id) => id,
+                        Err(err) => {
+                            error!(?err, "stored user id was not a number");
+                            return Err(HandlerError::UserNotFound);
+                        }
+                    }
+                    .into();
+                    members
+                        .iter()
+                        .map(|member| member.id)
+                        .find(|user| *user == user_id)
+                        .ok_or(HandlerError::UserNotFound)
+                })
+                .collect::<Result<_, _>>()?;
+            selection
+                .selected_target_value
+                .replace(Target::Users(users));
         }
         Ok(Ids::Submit) => {
             if let Some(emote) = selection.selected_emote_value.take() {
                 return Ok(Some(InteractionResult {
                     emote,
                     target: selection.selected_target_value.take(),
+                    npc_target: selection.npc_target,
                 }));
             } else {
                 debug!("tried submitting without all necessary selections");
@@ -475,13 +671,14 @@ async fn handle_interaction(
         }
     }
 
+    let emote_list = emotes.list(selection.category);
     interaction
         .create_interaction_response(context, |res| {
             create_response(
                 res,
                 InteractionResponseType::UpdateMessage,
                 user,
-                emote_list,
+                &emote_list,
                 selection,
                 members,
             )
@@ -493,13 +690,12 @@ async fn handle_interaction(
 
 async fn handle_interactions(
     context: &Context,
+    emotes: SelectableEmotes<'_>,
     msg: &Message,
     user: &DbUser,
-    emote_list: &[impl AsRef<str> + std::fmt::Debug],
     members: Vec<UserInfo>,
+    mut selection: Selection,
 ) -> Result<InteractionResult, HandlerError> {
-    let mut selection = Selection::default();
-
     while let Some(interaction) = msg
         .await_component_interactions(context)
         .collect_limit(20)
@@ -510,9 +706,9 @@ async fn handle_interactions(
     {
         if let Some(res) = handle_interaction(
             context,
+            emotes,
             msg,
             user,
-            emote_list,
             &members,
             interaction,
             &mut selection,
@@ -525,6 +721,136 @@ async fn handle_interactions(
     Err(HandlerError::TimeoutOrOverLimit)
 }
 
+/// Sends the emote picker as the response to a command, targeting `target_user` without letting it
+/// be changed if there is one, and sends the picked emote
+#[instrument(skip(cmd, handler, context))]
+pub async fn pick_and_send_emote(
+    cmd: &ApplicationCommandInteraction,
+    handler: &Handler,
+    context: &Context,
+    message_db_data: &MessageDbData<'_>,
+    target_user: Option<UserId>,
+) -> Result<(), HandlerError> {
+    if !handler
+        .check_emote_command_allowed(cmd, context, message_db_data)
+        .await?
+    {
+        return Ok(());
+    }
+    let members = if target_user.is_some() {
+        // there's no target to select
+        vec![]
+    } else if let Some(guild_id) = cmd.guild_id {
+        guild_id
+            .members(context, None, None)
+            .await?
+            .into_iter()
+            .map(UserInfo::from)
+            .collect()
+    } else {
+        vec![
+            UserInfo::from(&cmd.user),
+            UserInfo::from(User::from(context.cache.current_user())),
+        ]
+    };
+
+    let user_settings = message_db_data.determine_user_settings().await?;
+
+    info!(?members, ?target_user, "emote picker");
+
+    let selection = Selection {
+        fixed_target: target_user.is_some(),
+        selected_target_value: target_user.map(|user| Target::Users(vec![user])),
+        ..Default::default()
+    };
+
+    let guild_emotes = handler.guild_emotes(cmd.guild_id).await?.list();
+    let emotes = SelectableEmotes {
+        handler,
+        guild_emotes: &guild_emotes,
+    };
+    let emote_list = emotes.list(None);
+    cmd.create_interaction_response(context, |res| {
+        create_response(
+            res,
+            InteractionResponseType::ChannelMessageWithSource,
+            &user_settings,
+            emote_list.as_slice(),
+            &selection,
+            &members,
+        )
+    })
+    .await?;
+    let msg = cmd.get_interaction_response(context).await?;
+
+    trace!("awaiting interactions");
+    let res =
+        handle_interactions(context, emotes, &msg, &user_settings, members, selection).await?;
+
+    let emote_data = handler
+        .find_emote(&res.emote, cmd.guild_id)
+        .await?
+        .ok_or_else(|| HandlerError::UnrecognizedEmote(res.emote.clone()))?;
+    let target_users = res
+        .target
+        .as_ref()
+        .map(Target::user_ids)
+        .unwrap_or_default();
+    let bodies = handler
+        .build_emote_messages(
+            &emote_data,
+            message_db_data,
+            &cmd.user,
+            res.target.as_ref().map(|t| t.to_string()).as_deref(),
+            target_users,
+            PlainTarget {
+                is_npc: res.npc_target,
+            },
+        )
+        .await?;
+    debug!(?bodies, "processed selected emote");
+    let mentions = EmoteMentions::new(message_db_data, cmd.user.id, target_users).await?;
+    handler
+        .send_emote(
+            context,
+            message_db_data,
+            OutgoingEmote {
+                channel_id: cmd.channel_id,
+                author: &cmd.user,
+                emote: &emote_data,
+                bodies,
+                mentions,
+                reply_to: None,
+            },
+        )
+        .await?;
+    handler
+        .log_emote(
+            &cmd.user.id,
+            cmd.guild_id.as_ref(),
+            target_users.iter(),
+            &emote_data,
+        )
+        .await?;
+
+    let sent = if let Some(t) = &res.target {
+        format!("{} {}", res.emote, t)
+    } else {
+        res.emote.clone()
+    };
+    cmd.edit_original_interaction_response(context, |d| {
+        d.content(format!(
+            "{} ({})",
+            EMOTE_SENT.for_user(&user_settings),
+            sent
+        ))
+        .components(|cmp| cmp)
+    })
+    .await?;
+
+    Ok(())
+}
+
 pub struct EmoteSelectCmd;
 
 #[async_trait]
@@ -544,99 +870,14 @@ impl AppCmd for EmoteSelectCmd {
     #[instrument(skip(cmd, handler, context))]
     async fn handle(
         cmd: &ApplicationCommandInteraction,
-        handler: &crate::Handler,
+        handler: &Handler,
         context: &Context,
         message_db_data: &MessageDbData,
     ) -> Result<(), HandlerError>
     where
         Self: Sized,
     {
-        let members = if let Some(guild_id) = cmd.guild_id {
-            guild_id
-                .members(context, None, None)
-                .await?
-                .into_iter()
-                .map(UserInfo::from)
-                .collect()
-        } else {
-            vec![
-                UserInfo::from(&cmd.user),
-                UserInfo::from(User::from(context.cache.current_user())),
-            ]
-        };
-
-        let user_settings = message_db_data.determine_user_settings().await?;
-
-        info!(?members, "emote select command");
-
-        let emote_list: Vec<_> = handler.emote_list_by_id().collect();
-        cmd.create_interaction_response(context, |res| {
-            create_response(
-                res,
-                InteractionResponseType::ChannelMessageWithSource,
-                &user_settings,
-                emote_list.as_slice(),
-                &Selection::default(),
-                &members,
-            )
-        })
-        .await?;
-        let msg = cmd.get_interaction_response(context).await?;
-
-        trace!("awaiting interactions");
-        let res = handle_interactions(
-            context,
-            &msg,
-            &user_settings,
-            emote_list.as_slice(),
-            members,
-        )
-        .await?;
-
-        let emote_data = handler
-            .get_emote_data(&res.emote)
-            .ok_or_else(|| HandlerError::UnrecognizedEmote(res.emote.clone()))?;
-        let body = handler
-            .build_emote_message(
-                emote_data,
-                message_db_data,
-                &cmd.user,
-                res.target.as_ref().map(|t| t.to_string()).as_deref(),
-            )
-            .await?;
-        debug!(body, "processed selected emote");
-        cmd.channel_id
-            .send_message(context, |m| m.content(body))
-            .await?;
-        handler
-            .log_emote(
-                &cmd.user.id,
-                cmd.guild_id.as_ref(),
-                res.target
-                    .as_ref()
-                    .and_then(Target::user_id)
-                    .cloned()
-                    .iter(),
-                emote_data,
-            )
-            .await?;
-
-        cmd.edit_original_interaction_response(context, |d| {
-            d.content(format!(
-                "{} ({}{})",
-                EMOTE_SENT.for_user(&user_settings),
-                res.emote,
-                if let Some(t) = &res.target {
-                    [" ".to_string(), t.to_string()].concat()
-                } else {
-                    "".to_string()
-                }
-            ))
-            .components(|cmp| cmp)
-        })
-        .await?;
-
-        Ok(())
+        pick_and_send_emote(cmd, handler, context, message_db_data, None).await
     }
 
     fn name() -> LocalizedString {
@@ -3,29 +3,69 @@ use serenity::{
     builder::CreateApplicationCommand,
     constants::MESSAGE_CODE_LIMIT,
     model::prelude::{
-        command::CommandType, interaction::application_command::ApplicationCommandInteraction,
+        command::{CommandOptionType, CommandType},
+        interaction::application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
     },
     prelude::Context,
 };
+use strum::IntoEnumIterator;
 use tracing::*;
 
 use crate::{
     commands::AppCmd,
-    util::{CreateApplicationCommandExt, LocalizedString},
+    db::models::DbEmoteCategory,
+    handler::emotes::SYNTHETIC_MARKER,
+    util::{CreateApplicationCommandExt, CreateApplicationCommandOptionExt, LocalizedString},
     Handler, HandlerError, MessageDbData,
 };
 
 pub const NAME: LocalizedString = LocalizedString {
     en: "list-emotes",
     ja: "エモート一覧",
+    de: "emote-liste",
+    fr: "liste-emotes",
 };
 pub const DESC: LocalizedString = LocalizedString {
     en: "List all available emotes",
     ja: "選択できるエモートの一覧",
+    de: "Alle verfügbaren Emotes auflisten",
+    fr: "Lister toutes les emotes disponibles",
+};
+pub const CATEGORY_OPTION_NAME: LocalizedString = LocalizedString {
+    en: "category",
+    ja: "カテゴリ",
+    de: "kategorie",
+    fr: "catégorie",
+};
+pub const CATEGORY_OPTION_DESC: LocalizedString = LocalizedString {
+    en: "Only list emotes in this category",
+    ja: "このカテゴリのエモートのみ表示",
+    de: "Nur Emotes dieser Kategorie auflisten",
+    fr: "Lister uniquement les emotes de cette catégorie",
 };
 pub const LIST_MSG_PREFIX: LocalizedString = LocalizedString {
     en: "List of emotes",
     ja: "エモート一覧",
+    de: "Liste der Emotes",
+    fr: "Liste des emotes",
+};
+pub const GUILD_LIST_MSG_PREFIX: LocalizedString = LocalizedString {
+    en: "Server emotes",
+    ja: "サーバーのエモート",
+    de: "Server-Emotes",
+    fr: "Emotes du serveur",
+};
+pub const NO_EMOTES: LocalizedString = LocalizedString {
+    en: "No emotes found",
+    ja: "エモートが見つかりません",
+    de: "Keine Emotes gefunden",
+    fr: "Aucune emote trouvée",
+};
+pub const SYNTHETIC_NOTE: LocalizedString = LocalizedString {
+    en: "has no message in the game, so a generic one is sent instead",
+    ja: "ゲーム内にメッセージがないため、汎用メッセージが送信されます",
+    de: "hat im Spiel keine Nachricht, daher wird eine allgemeine gesendet",
+    fr: "n'a pas de message en jeu, un message générique est donc envoyé",
 };
 
 pub struct ListEmotesCmd;
@@ -71,6 +111,23 @@ impl AppCmd for ListEmotesCmd {
         cmd.localized_name(NAME)
             .kind(CommandType::ChatInput)
             .localized_desc(DESC)
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::Integer)
+                    .localized_name(CATEGORY_OPTION_NAME)
+                    .localized_desc(CATEGORY_OPTION_DESC);
+                DbEmoteCategory::iter().for_each(|category| {
+                    opt.add_int_choice_localized(
+                        category.to_string_en(),
+                        category as i32,
+                        [
+                            ("ja", category.to_string_ja()),
+                            ("de", category.to_string_de()),
+                            ("fr", category.to_string_fr()),
+                        ],
+                    );
+                });
+                opt
+            })
             .dm_permission(true);
         cmd
     }
@@ -85,12 +142,58 @@ impl AppCmd for ListEmotesCmd {
     where
         Self: Sized,
     {
-        info!("list emotes command");
+        let category = match cmd.data.options.first().and_then(|o| o.resolved.as_ref()) {
+            Some(CommandDataOptionValue::Integer(value)) => {
+                match i32::try_from(*value)
+                    .ok()
+                    .and_then(DbEmoteCategory::from_repr)
+                {
+                    Some(category) => Some(category),
+                    None => {
+                        error!(value, "unexpected category (invalid number)");
+                        return Err(HandlerError::UnexpectedData);
+                    }
+                }
+            }
+            Some(v) => {
+                error!(?v, "unexpected category (not an integer)");
+                return Err(HandlerError::UnexpectedData);
+            }
+            None => None,
+        };
+        info!(?category, "list emotes command");
         let user = message_db_data.determine_user_settings().await?;
-        let bodies = split_by_max_message_len(
-            LIST_MSG_PREFIX.for_user(&user),
-            handler.emote_list_by_id().cloned(),
-        );
+        let prefix = match category {
+            Some(category) => format!(
+                "{} - {}",
+                LIST_MSG_PREFIX.for_user(&user),
+                category.for_user(&user)
+            ),
+            None => LIST_MSG_PREFIX.for_user(&user).to_string(),
+        };
+        let emote_list = handler.emote_list_in_category(category);
+        let has_synthetic = emote_list.iter().any(|item| item.synthetic);
+        let mut bodies =
+            split_by_max_message_len(&prefix, emote_list.iter().map(|item| item.label()));
+        if bodies.is_empty() {
+            // emote data from some sources doesn't have categories
+            bodies.push(format!("{}: {}", prefix, NO_EMOTES.for_user(&user)));
+        }
+        // guild emotes don't have categories
+        if category.is_none() {
+            let guild_emotes = handler.guild_emotes(cmd.guild_id).await?;
+            bodies.extend(split_by_max_message_len(
+                GUILD_LIST_MSG_PREFIX.for_user(&user),
+                guild_emotes.list().into_iter().map(|item| item.command),
+            ));
+        }
+        if has_synthetic {
+            bodies.push(format!(
+                "{} {}",
+                SYNTHETIC_MARKER,
+                SYNTHETIC_NOTE.for_user(&user)
+            ));
+        }
         debug!("emotes response is {} messages long", bodies.len());
 
         let mut body_iter = bodies.into_iter();
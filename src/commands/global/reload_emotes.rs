@@ -0,0 +1,163 @@
+use async_trait::async_trait;
+use serenity::{
+    builder::CreateApplicationCommand,
+    model::{
+        prelude::{
+            command::CommandType,
+            interaction::{
+                application_command::ApplicationCommandInteraction, InteractionResponseType,
+            },
+        },
+        Permissions,
+    },
+    prelude::Context,
+};
+use tracing::*;
+
+use crate::{
+    catalog::CatalogDiff,
+    commands::AppCmd,
+    db::models::DbUser,
+    util::{CreateApplicationCommandExt, LocalizedString},
+    Handler, HandlerError, MessageDbData,
+};
+
+use super::list_emotes::split_by_max_message_len;
+
+pub const NAME: LocalizedString = LocalizedString {
+    en: "reload-emotes",
+    ja: "エモート再読み込み",
+    de: "emotes-neu-laden",
+    fr: "recharger-emotes",
+};
+pub const DESC: LocalizedString = LocalizedString {
+    en: "Reload the emote list from its source (bot owner only)",
+    ja: "エモート一覧をデータ元から再読み込み（ボット所有者のみ）",
+    de: "Die Emote-Liste neu aus ihrer Quelle laden (nur Bot-Besitzer)",
+    fr: "Recharger la liste des emotes depuis sa source (propriétaire du bot uniquement)",
+};
+pub const NOT_OWNER: LocalizedString = LocalizedString {
+    en: "Only the bot owner can reload emotes",
+    ja: "エモートの再読み込みはボット所有者のみ可能です",
+    de: "Nur der Bot-Besitzer kann Emotes neu laden",
+    fr: "Seul le propriétaire du bot peut recharger les emotes",
+};
+pub const RELOADED: LocalizedString = LocalizedString {
+    en: "Reloaded emotes, now serving",
+    ja: "エモートを再読み込みしました。現在のエモート数：",
+    de: "Emotes neu geladen, verfügbare Emotes:",
+    fr: "Emotes rechargées, emotes disponibles :",
+};
+pub const NO_CHANGES: LocalizedString = LocalizedString {
+    en: "No emotes changed",
+    ja: "変更されたエモートはありません",
+    de: "Keine Emotes geändert",
+    fr: "Aucune emote modifiée",
+};
+pub const ADDED_PREFIX: LocalizedString = LocalizedString {
+    en: "Added",
+    ja: "追加",
+    de: "Hinzugefügt",
+    fr: "Ajoutées",
+};
+pub const CHANGED_PREFIX: LocalizedString = LocalizedString {
+    en: "Changed",
+    ja: "変更",
+    de: "Geändert",
+    fr: "Modifiées",
+};
+pub const REMOVED_PREFIX: LocalizedString = LocalizedString {
+    en: "Removed",
+    ja: "削除",
+    de: "Entfernt",
+    fr: "Supprimées",
+};
+
+pub struct ReloadEmotesCmd;
+
+fn diff_bodies(diff: CatalogDiff, emote_count: usize, user: &DbUser) -> Vec<String> {
+    let mut bodies = vec![format!("{} {}", RELOADED.for_user(user), emote_count)];
+    if diff.is_empty() {
+        bodies.push(NO_CHANGES.for_user(user).to_string());
+    }
+    for (prefix, names) in [
+        (ADDED_PREFIX, diff.added),
+        (CHANGED_PREFIX, diff.changed),
+        (REMOVED_PREFIX, diff.removed),
+    ] {
+        bodies.extend(split_by_max_message_len(
+            prefix.for_user(user),
+            names.into_iter(),
+        ));
+    }
+    bodies
+}
+
+#[async_trait]
+impl AppCmd for ReloadEmotesCmd {
+    fn to_application_command() -> CreateApplicationCommand
+    where
+        Self: Sized,
+    {
+        let mut cmd = CreateApplicationCommand::default();
+        cmd.localized_name(NAME)
+            .kind(CommandType::ChatInput)
+            .localized_desc(DESC)
+            // hides it from most users, but ownership is still checked when handling
+            .default_member_permissions(Permissions::ADMINISTRATOR)
+            .dm_permission(true);
+        cmd
+    }
+
+    #[instrument(skip(cmd, handler, context))]
+    async fn handle(
+        cmd: &ApplicationCommandInteraction,
+        handler: &Handler,
+        context: &Context,
+        message_db_data: &MessageDbData,
+    ) -> Result<(), HandlerError>
+    where
+        Self: Sized,
+    {
+        let user = message_db_data.determine_user_settings().await?;
+
+        if !handler.is_owner(context, cmd.user.id).await? {
+            warn!("non-owner tried to reload emotes");
+            cmd.create_interaction_response(context, |res| {
+                res.interaction_response_data(|data| {
+                    data.ephemeral(true).content(NOT_OWNER.for_user(&user))
+                })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        info!("reload emotes command");
+        // loading from the source can take longer than the initial response window
+        cmd.create_interaction_response(context, |res| {
+            res.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|data| data.ephemeral(true))
+        })
+        .await?;
+
+        let diff = handler.reload_emotes().await?;
+        let emote_count = handler.catalog().emote_count();
+        let mut body_iter = diff_bodies(diff, emote_count, &user).into_iter();
+
+        if let Some(body) = body_iter.next() {
+            cmd.edit_original_interaction_response(context, |res| res.content(body))
+                .await?;
+        }
+
+        for body in body_iter {
+            cmd.create_followup_message(context, |data| data.ephemeral(true).content(body))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn name() -> LocalizedString {
+        NAME
+    }
+}
@@ -18,18 +18,26 @@ use crate::{
 pub const USER_SUB_NAME: LocalizedString = LocalizedString {
     en: "user",
     ja: "ユーザー",
+    de: "benutzer",
+    fr: "utilisateur",
 };
 pub const USER_SUB_DESC: LocalizedString = LocalizedString {
     en: "Global emote usage statistics for a user",
     ja: "ユーザーの全体使用統計",
+    de: "Globale Emote-Nutzungsstatistiken eines Benutzers",
+    fr: "Statistiques globales d'utilisation des emotes d'un utilisateur",
 };
 pub const RECEIVED_USER_SUB_NAME: LocalizedString = LocalizedString {
     en: "user",
     ja: "ユーザー",
+    de: "benutzer",
+    fr: "utilisateur",
 };
 pub const RECEIVED_USER_SUB_DESC: LocalizedString = LocalizedString {
     en: "Global emote received usage statistics for a user",
     ja: "ユーザーの全体使用統計",
+    de: "Globale Statistiken der von einem Benutzer erhaltenen Emotes",
+    fr: "Statistiques globales des emotes reçues par un utilisateur",
 };
 
 pub struct GlobalStatsCmd;
@@ -58,6 +66,7 @@ impl AppCmd for GlobalStatsCmd {
                         sub.kind(CommandOptionType::String)
                             .localized_name(EMOTE_OPT_NAME)
                             .localized_desc(EMOTE_OPT_DESC)
+                            .set_autocomplete(true)
                     })
             })
             .create_option(|opt| {
@@ -78,6 +87,7 @@ impl AppCmd for GlobalStatsCmd {
                                 sub.kind(CommandOptionType::String)
                                     .localized_name(EMOTE_OPT_NAME)
                                     .localized_desc(EMOTE_OPT_DESC)
+                                    .set_autocomplete(true)
                             })
                     })
             });
@@ -96,9 +106,15 @@ impl AppCmd for GlobalStatsCmd {
     {
         let user = message_db_data.user().await?.unwrap_or_default();
         let user_id_opt = cmd.data.resolved.users.keys().next().cloned();
-        let kind =
-            EmoteLogQuery::from_command_data(&handler.emotes, &cmd.data.options, None, user_id_opt)
-                .ok_or(HandlerError::UnexpectedData)?;
+        let guild_emotes = handler.guild_emotes(cmd.guild_id).await?;
+        let kind = EmoteLogQuery::from_command_data(
+            &handler.catalog(),
+            &guild_emotes,
+            &cmd.data.options,
+            None,
+            user_id_opt,
+        )
+        .ok_or(HandlerError::UnexpectedData)?;
         info!(?kind, "global stat command");
 
         let count = handler.db.fetch_emote_log_count(&kind).await?;
@@ -1,4 +1,8 @@
-use std::{mem, sync::Arc};
+use std::{
+    fmt::{self, Display, Formatter},
+    mem,
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -6,6 +10,7 @@ use serenity::{
     builder::{CreateApplicationCommand, CreateInteractionResponse},
     model::prelude::{
         command::CommandType,
+        component::{ActionRowComponent, ButtonStyle, InputTextStyle},
         interaction::{
             application_command::ApplicationCommandInteraction,
             message_component::MessageComponentInteraction, InteractionResponseType,
@@ -20,7 +25,8 @@ use tracing::*;
 
 use crate::{
     commands::AppCmd,
-    db::models::{DbGender, DbLanguage, DbUser},
+    db::models::{DbGender, DbLanguage, DbOutputFormat, DbUser},
+    handler::game_log::{format_utc_offset, parse_utc_offset},
     util::{CreateApplicationCommandExt, LocalizedString},
     HandlerError, MessageDbData, INTERACTION_TIMEOUT,
 };
@@ -28,27 +34,75 @@ use crate::{
 pub const CONTENT: LocalizedString = LocalizedString {
     en: "Emote message settings",
     ja: "エモート設定",
+    de: "Emote-Nachrichteneinstellungen",
+    fr: "Paramètres des messages d'emote",
+};
+pub const USE_SERVER_SETTING: LocalizedString = LocalizedString {
+    en: "Use the server's output format",
+    ja: "サーバーの表示形式を使用",
+    de: "Ausgabeformat des Servers verwenden",
+    fr: "Utiliser le format d'affichage du serveur",
+};
+pub const UTC_OFFSET_BTN: LocalizedString = LocalizedString {
+    en: "Game log timezone: ",
+    ja: "ゲームログのタイムゾーン：",
+    de: "Zeitzone des Spiel-Logs: ",
+    fr: "Fuseau horaire du journal : ",
+};
+pub const UTC_OFFSET_MODAL_CONTENT: LocalizedString = LocalizedString {
+    en: "Input your offset from UTC, such as +9 or -05:30",
+    ja: "UTCとの時差を入力してください（例：+9、-05:30）",
+    de: "Abweichung von UTC eingeben, etwa +9 oder -05:30",
+    fr: "Saisissez votre décalage par rapport à UTC, par exemple +9 ou -05:30",
+};
+pub const UTC_OFFSET_MODAL_INPUT: LocalizedString = LocalizedString {
+    en: "Offset from UTC",
+    ja: "UTCとの時差",
+    de: "Abweichung von UTC",
+    fr: "Décalage par rapport à UTC",
+};
+pub const UTC_OFFSET_MODAL_TITLE: LocalizedString = LocalizedString {
+    en: "Timezone for game log timestamps",
+    ja: "ゲームログのタイムスタンプのタイムゾーン",
+    de: "Zeitzone für Spiel-Log-Zeitstempel",
+    fr: "Fuseau horaire des horodatages du journal",
 };
 pub const SAVE_BTN: LocalizedString = LocalizedString {
     en: "Save",
     ja: "保存",
+    de: "Speichern",
+    fr: "Enregistrer",
 };
 pub const SETTINGS_SAVED: LocalizedString = LocalizedString {
     en: "Settings saved!",
     ja: "設定を保存しました！",
+    de: "Einstellungen gespeichert!",
+    fr: "Paramètres enregistrés !",
 };
 pub const NAME: LocalizedString = LocalizedString {
     en: "settings",
     ja: "設定",
+    de: "einstellungen",
+    fr: "paramètres",
 };
 pub const DESC: LocalizedString = LocalizedString {
     en: "Set personal emote message settings",
     ja: "個人エモート設定",
+    de: "Persönliche Emote-Einstellungen festlegen",
+    fr: "Définir vos paramètres d'emote personnels",
 };
 
+// enum values start from 0, so the server's setting needs one that isn't a valid repr
+const USE_SERVER_SETTING_VALUE: i32 = -1;
+
+const UTC_OFFSET_MODAL: &str = "utc_offset_modal";
+const UTC_OFFSET_MODAL_BTN: &str = "utc_offset_modal_btn";
+
 enum Ids {
     GenderSelect,
     LanguageSelect,
+    OutputFormatSelect,
+    UtcOffsetBtn,
     Submit,
 }
 
@@ -63,14 +117,16 @@ impl From<&Ids> for &'static str {
         match ids {
             Ids::GenderSelect => "gender_select",
             Ids::LanguageSelect => "language_select",
+            Ids::OutputFormatSelect => "output_format_select",
+            Ids::UtcOffsetBtn => "utc_offset_btn",
             Ids::Submit => "submit",
         }
     }
 }
 
-impl ToString for Ids {
-    fn to_string(&self) -> String {
-        Into::<&'static str>::into(self).to_string()
+impl Display for Ids {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.into())
     }
 }
 
@@ -85,6 +141,8 @@ impl TryFrom<&str> for Ids {
         match value {
             "gender_select" => Ok(Ids::GenderSelect),
             "language_select" => Ok(Ids::LanguageSelect),
+            "output_format_select" => Ok(Ids::OutputFormatSelect),
+            "utc_offset_btn" => Ok(Ids::UtcOffsetBtn),
             "submit" => Ok(Ids::Submit),
             s => Err(InvalidComponentId(s.to_string())),
         }
@@ -135,6 +193,92 @@ async fn handle_interaction(
             debug!(?lang, "language selected");
             user.language = lang;
         }
+        Ok(Ids::OutputFormatSelect) => {
+            let value = &interaction.data.values[0];
+            let value = if let Ok(v) = value.parse() {
+                v
+            } else {
+                error!(value, "unexpected output format selected (not numeric)");
+                return Err(HandlerError::UnexpectedData);
+            };
+            let format = match DbOutputFormat::from_repr(value) {
+                Some(f) => Some(f),
+                None if value == USE_SERVER_SETTING_VALUE => None,
+                None => {
+                    error!(value, "unexpected output format selected (invalid number)");
+                    return Err(HandlerError::UnexpectedData);
+                }
+            };
+            debug!(?format, "output format selected");
+            user.output_format = format;
+        }
+        Ok(Ids::UtcOffsetBtn) => {
+            debug!("utc offset input");
+            let span = debug_span!("utc_offset_modal_interaction");
+            async move {
+                interaction
+                    .create_interaction_response(context, |res| {
+                        res.kind(InteractionResponseType::Modal)
+                            .interaction_response_data(|d| {
+                                d.content(UTC_OFFSET_MODAL_CONTENT.for_user(user))
+                                    .components(|c| {
+                                        c.create_action_row(|row| {
+                                            row.create_input_text(|inp| {
+                                                inp.custom_id(UTC_OFFSET_MODAL)
+                                                    .style(InputTextStyle::Short)
+                                                    .label(UTC_OFFSET_MODAL_INPUT.for_user(user))
+                                                    .placeholder(
+                                                        UTC_OFFSET_MODAL_CONTENT.for_user(user),
+                                                    )
+                                                    .max_length(10)
+                                            })
+                                        })
+                                    })
+                                    .title(UTC_OFFSET_MODAL_TITLE.for_user(user))
+                                    .custom_id(UTC_OFFSET_MODAL_BTN)
+                            })
+                    })
+                    .await?;
+
+                if let Some(modal_interaction) = msg
+                    .await_modal_interaction(context)
+                    .timeout(INTERACTION_TIMEOUT)
+                    .await
+                {
+                    match &modal_interaction.data.components[0].components[0] {
+                        ActionRowComponent::InputText(cmp) => {
+                            // an offset that can't be parsed leaves the current one, which the
+                            // button still shows
+                            match parse_utc_offset(&cmp.value) {
+                                Some(offset) => {
+                                    trace!(offset, "setting utc offset");
+                                    user.utc_offset = offset;
+                                }
+                                None => debug!(cmp.value, "invalid utc offset"),
+                            }
+                            modal_interaction
+                                .create_interaction_response(context, |res| {
+                                    create_response(
+                                        res,
+                                        InteractionResponseType::UpdateMessage,
+                                        user,
+                                    )
+                                })
+                                .await?;
+                        }
+                        cmp => {
+                            error!(?cmp, "modal component was not an input text");
+                            return Err(HandlerError::UnexpectedData);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            .instrument(span)
+            .await?;
+            // don't send typical interaction response
+            return Ok(None);
+        }
         Ok(Ids::Submit) => {
             interaction
                 .create_interaction_response(context, |res| {
@@ -219,7 +363,37 @@ fn create_response<'a, 'b>(
                         })
                     })
                 });
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::OutputFormatSelect).options(|opts| {
+                            opts.create_option(|o| {
+                                o.label(USE_SERVER_SETTING.for_user(user))
+                                    .value(USE_SERVER_SETTING_VALUE)
+                                    .default_selection(user.output_format.is_none())
+                            });
+                            DbOutputFormat::iter().for_each(|format| {
+                                opts.create_option(|o| {
+                                    o.label(format.for_user(user))
+                                        .value(format as i32)
+                                        .default_selection(user.output_format == Some(format))
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
                 c.create_action_row(|row| {
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::UtcOffsetBtn)
+                            .style(ButtonStyle::Secondary)
+                            .label(
+                                [
+                                    UTC_OFFSET_BTN.for_user(user),
+                                    &format_utc_offset(user.utc_offset),
+                                ]
+                                .concat(),
+                            )
+                    });
                     row.create_button(|btn| {
                         btn.custom_id(Ids::Submit).label(SAVE_BTN.for_user(user))
                     })
@@ -269,10 +443,7 @@ impl AppCmd for UserSettingsCmd {
         trace!("awaiting interactions");
         let user = handle_interactions(context, &msg, user.into_owned()).await?;
 
-        handler
-            .db
-            .upsert_user(&cmd.user.id, user.language, user.gender)
-            .await?;
+        handler.db.upsert_user(&cmd.user.id, &user).await?;
 
         Ok(())
     }
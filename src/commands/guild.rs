@@ -1,21 +1,28 @@
+pub mod channel_settings;
+pub mod custom_emotes;
 pub mod server_settings;
 pub mod stats;
 
-use std::{collections::HashMap, str::FromStr};
+use std::collections::HashMap;
 
 use async_trait::async_trait;
 use serenity::{
     builder::CreateApplicationCommand,
-    model::prelude::{interaction::application_command::ApplicationCommandInteraction, CommandId},
+    model::prelude::{
+        command::CommandType, interaction::application_command::ApplicationCommandInteraction,
+        CommandId,
+    },
     prelude::{Context, TypeMapKey},
 };
 use strum::IntoEnumIterator;
 use strum_macros::{AsRefStr, Display, EnumIter};
-use thiserror::Error;
 
 use crate::{util::LocalizedString, Handler, HandlerError, MessageDbData};
 
-use self::{server_settings::ServerSettingsCmd, stats::GuildStatsCmd};
+use self::{
+    channel_settings::ChannelSettingsCmd, custom_emotes::CustomEmotesCmd,
+    server_settings::ServerSettingsCmd, stats::GuildStatsCmd,
+};
 
 use super::{AppCmd, CommandsEnum};
 
@@ -23,6 +30,8 @@ use super::{AppCmd, CommandsEnum};
 pub enum GuildCommands {
     ServerSettings,
     Stats,
+    CustomEmotes,
+    ChannelSettings,
 }
 
 impl GuildCommands {
@@ -30,6 +39,8 @@ impl GuildCommands {
         match self {
             GuildCommands::ServerSettings => ServerSettingsCmd::to_application_command(),
             GuildCommands::Stats => GuildStatsCmd::to_application_command(),
+            GuildCommands::CustomEmotes => CustomEmotesCmd::to_application_command(),
+            GuildCommands::ChannelSettings => ChannelSettingsCmd::to_application_command(),
         }
     }
 
@@ -41,12 +52,26 @@ impl GuildCommands {
         match self {
             GuildCommands::ServerSettings => ServerSettingsCmd::name(),
             GuildCommands::Stats => GuildStatsCmd::name(),
+            GuildCommands::CustomEmotes => CustomEmotesCmd::name(),
+            GuildCommands::ChannelSettings => ChannelSettingsCmd::name(),
+        }
+    }
+    pub fn kind(self) -> CommandType {
+        match self {
+            GuildCommands::ServerSettings => ServerSettingsCmd::kind(),
+            GuildCommands::Stats => GuildStatsCmd::kind(),
+            GuildCommands::CustomEmotes => CustomEmotesCmd::kind(),
+            GuildCommands::ChannelSettings => ChannelSettingsCmd::kind(),
         }
     }
 }
 
 #[async_trait]
 impl CommandsEnum for GuildCommands {
+    fn from_registered(name: &str, kind: CommandType) -> Option<Self> {
+        GuildCommands::iter().find(|cmd| cmd.kind() == kind && cmd.name().any_eq(name))
+    }
+
     async fn handle(
         self,
         cmd: &ApplicationCommandInteraction,
@@ -59,6 +84,12 @@ impl CommandsEnum for GuildCommands {
                 ServerSettingsCmd::handle(cmd, handler, context, message_db_data)
             }
             GuildCommands::Stats => GuildStatsCmd::handle(cmd, handler, context, message_db_data),
+            GuildCommands::CustomEmotes => {
+                CustomEmotesCmd::handle(cmd, handler, context, message_db_data)
+            }
+            GuildCommands::ChannelSettings => {
+                ChannelSettingsCmd::handle(cmd, handler, context, message_db_data)
+            }
         }
         .await
     }
@@ -67,17 +98,3 @@ impl CommandsEnum for GuildCommands {
 impl TypeMapKey for GuildCommands {
     type Value = HashMap<CommandId, Self>;
 }
-
-#[derive(Debug, Clone, Error)]
-#[error("Not a valid command: {0}")]
-pub struct InvalidGuildCommand(String);
-
-impl FromStr for GuildCommands {
-    type Err = InvalidGuildCommand;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        GuildCommands::iter()
-            .find(|cmd| cmd.name().any_eq(s))
-            .ok_or_else(|| InvalidGuildCommand(s.to_string()))
-    }
-}
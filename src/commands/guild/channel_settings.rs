@@ -0,0 +1,519 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    mem,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serenity::{
+    builder::{CreateApplicationCommand, CreateInteractionResponse},
+    model::{
+        prelude::{
+            command::CommandType,
+            component::{ActionRowComponent, ButtonStyle, InputTextStyle},
+            interaction::{
+                application_command::ApplicationCommandInteraction,
+                message_component::MessageComponentInteraction, InteractionResponseType,
+            },
+            ChannelId, Message,
+        },
+        Permissions,
+    },
+    prelude::{Context, Mentionable},
+};
+use strum::IntoEnumIterator;
+use thiserror::Error;
+use tracing::*;
+
+use crate::{
+    commands::AppCmd,
+    db::models::{DbChannel, DbGender, DbLanguage, DbOutputFormat, DbSendMode, DbUser},
+    util::{CreateApplicationCommandExt, LocalizedString},
+    Handler, HandlerError, MessageDbData, INTERACTION_TIMEOUT,
+};
+
+pub const CONTENT: LocalizedString = LocalizedString {
+    en: "Emote message settings for",
+    ja: "チャンネルのエモート設定：",
+    de: "Emote-Einstellungen für",
+    fr: "Paramètres d'emote pour",
+};
+pub const USE_SERVER_SETTING: LocalizedString = LocalizedString {
+    en: "Use the server's setting",
+    ja: "サーバーの設定を使用",
+    de: "Servereinstellung verwenden",
+    fr: "Utiliser le paramètre du serveur",
+};
+pub const PREFIX_INPUT_BTN: LocalizedString = LocalizedString {
+    en: "Command prefix: ",
+    ja: "コマンドプレフィックス：",
+    de: "Befehlspräfix: ",
+    fr: "Préfixe de commande : ",
+};
+pub const PREFIX_INPUT_MODAL_INPUT: LocalizedString = LocalizedString {
+    en: "Command prefix, or empty to use the server's",
+    ja: "コマンドプレフィックス（空欄でサーバーの設定を使用）",
+    de: "Befehlspräfix, leer für das des Servers",
+    fr: "Préfixe de commande, vide pour celui du serveur",
+};
+pub const PREFIX_INPUT_MODAL_TITLE: LocalizedString = LocalizedString {
+    en: "Channel command prefix",
+    ja: "チャンネルのコマンドプレフィックス",
+    de: "Befehlspräfix des Kanals",
+    fr: "Préfixe de commande du salon",
+};
+pub const PREFIX_EMOTES_ENABLED_BTN: LocalizedString = LocalizedString {
+    en: "Prefix emotes are on",
+    ja: "プレフィックスのエモートは有効",
+    de: "Präfix-Emotes sind an",
+    fr: "Les emotes à préfixe sont activées",
+};
+pub const PREFIX_EMOTES_DISABLED_BTN: LocalizedString = LocalizedString {
+    en: "Prefix emotes are off",
+    ja: "プレフィックスのエモートは無効",
+    de: "Präfix-Emotes sind aus",
+    fr: "Les emotes à préfixe sont désactivées",
+};
+pub const SAVE_BTN: LocalizedString = LocalizedString {
+    en: "Save",
+    ja: "保存",
+    de: "Speichern",
+    fr: "Enregistrer",
+};
+pub const SETTINGS_SAVED: LocalizedString = LocalizedString {
+    en: "Settings saved!",
+    ja: "設定を保存しました！",
+    de: "Einstellungen gespeichert!",
+    fr: "Paramètres enregistrés !",
+};
+pub const NAME: LocalizedString = LocalizedString {
+    en: "channel-settings",
+    ja: "チャンネル設定",
+    de: "kanal-einstellungen",
+    fr: "paramètres-salon",
+};
+pub const DESC: LocalizedString = LocalizedString {
+    en: "Override the server's emote message settings in this channel",
+    ja: "このチャンネルでサーバーのエモート設定を上書き",
+    de: "Die Emote-Einstellungen des Servers in diesem Kanal überschreiben",
+    fr: "Remplacer les paramètres d'emote du serveur dans ce salon",
+};
+
+// enum values start from 0, so the server's setting needs one that isn't a valid repr
+const USE_SERVER_SETTING_VALUE: i32 = -1;
+
+const PREFIX_INPUT_MODAL: &str = "prefix_input_modal";
+const PREFIX_INPUT_MODAL_BTN: &str = "prefix_input_modal_btn";
+
+enum Ids {
+    LanguageSelect,
+    GenderSelect,
+    OutputFormatSelect,
+    SendModeSelect,
+    PrefixInputBtn,
+    PrefixEmotesBtn,
+    Submit,
+}
+
+impl From<Ids> for &'static str {
+    fn from(ids: Ids) -> Self {
+        From::<&Ids>::from(&ids)
+    }
+}
+
+impl From<&Ids> for &'static str {
+    fn from(ids: &Ids) -> Self {
+        match ids {
+            Ids::LanguageSelect => "language_select",
+            Ids::GenderSelect => "gender_select",
+            Ids::OutputFormatSelect => "output_format_select",
+            Ids::SendModeSelect => "send_mode_select",
+            Ids::PrefixInputBtn => "prefix_input_btn",
+            Ids::PrefixEmotesBtn => "prefix_emotes_btn",
+            Ids::Submit => "submit",
+        }
+    }
+}
+
+impl Display for Ids {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.into())
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Unrecognized component id ({0})")]
+struct InvalidComponentId(String);
+
+impl TryFrom<&str> for Ids {
+    type Error = InvalidComponentId;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "language_select" => Ok(Ids::LanguageSelect),
+            "gender_select" => Ok(Ids::GenderSelect),
+            "output_format_select" => Ok(Ids::OutputFormatSelect),
+            "send_mode_select" => Ok(Ids::SendModeSelect),
+            "prefix_input_btn" => Ok(Ids::PrefixInputBtn),
+            "prefix_emotes_btn" => Ok(Ids::PrefixEmotesBtn),
+            "submit" => Ok(Ids::Submit),
+            s => Err(InvalidComponentId(s.to_string())),
+        }
+    }
+}
+
+/// The setting picked in a select menu of overrides, where `None` is the server's setting
+fn selected_override<T>(
+    interaction: &MessageComponentInteraction,
+    from_repr: impl FnOnce(i32) -> Option<T>,
+    setting: &str,
+) -> Result<Option<T>, HandlerError> {
+    let value = &interaction.data.values[0];
+    let value = if let Ok(v) = value.parse() {
+        v
+    } else {
+        error!(value, setting, "unexpected setting selected (not numeric)");
+        return Err(HandlerError::UnexpectedData);
+    };
+    match from_repr(value) {
+        Some(v) => Ok(Some(v)),
+        None if value == USE_SERVER_SETTING_VALUE => Ok(None),
+        None => {
+            error!(
+                value,
+                setting, "unexpected setting selected (invalid number)"
+            );
+            Err(HandlerError::UnexpectedData)
+        }
+    }
+}
+
+#[instrument(skip(context))]
+async fn handle_interaction(
+    context: &Context,
+    msg: &Message,
+    user: &DbUser,
+    channel_id: ChannelId,
+    interaction: Arc<MessageComponentInteraction>,
+    channel: &mut DbChannel,
+) -> Result<Option<DbChannel>, HandlerError> {
+    match Ids::try_from(interaction.data.custom_id.as_str()) {
+        Ok(Ids::LanguageSelect) => {
+            channel.language = selected_override(&interaction, DbLanguage::from_repr, "language")?;
+            debug!(?channel.language, "language selected");
+        }
+        Ok(Ids::GenderSelect) => {
+            channel.gender = selected_override(&interaction, DbGender::from_repr, "gender")?;
+            debug!(?channel.gender, "gender selected");
+        }
+        Ok(Ids::OutputFormatSelect) => {
+            channel.output_format =
+                selected_override(&interaction, DbOutputFormat::from_repr, "output format")?;
+            debug!(?channel.output_format, "output format selected");
+        }
+        Ok(Ids::SendModeSelect) => {
+            channel.send_mode =
+                selected_override(&interaction, DbSendMode::from_repr, "send mode")?;
+            debug!(?channel.send_mode, "send mode selected");
+        }
+        Ok(Ids::PrefixInputBtn) => {
+            debug!("prefix input");
+            let span = debug_span!("prefix_input_modal_interaction");
+            async move {
+                interaction
+                    .create_interaction_response(context, |res| {
+                        res.kind(InteractionResponseType::Modal)
+                            .interaction_response_data(|d| {
+                                d.components(|c| {
+                                    c.create_action_row(|row| {
+                                        row.create_input_text(|inp| {
+                                            inp.custom_id(PREFIX_INPUT_MODAL)
+                                                .style(InputTextStyle::Short)
+                                                .label(PREFIX_INPUT_MODAL_INPUT.for_user(user))
+                                                .required(false)
+                                                .max_length(5)
+                                        })
+                                    })
+                                })
+                                .title(PREFIX_INPUT_MODAL_TITLE.for_user(user))
+                                .custom_id(PREFIX_INPUT_MODAL_BTN)
+                            })
+                    })
+                    .await?;
+
+                if let Some(modal_interaction) = msg
+                    .await_modal_interaction(context)
+                    .timeout(INTERACTION_TIMEOUT)
+                    .await
+                {
+                    match &modal_interaction.data.components[0].components[0] {
+                        ActionRowComponent::InputText(cmp) => {
+                            trace!(prefix = cmp.value, "setting prefix");
+                            channel.prefix = Some(cmp.value.clone()).filter(|p| !p.is_empty());
+                            modal_interaction
+                                .create_interaction_response(context, |res| {
+                                    create_response(
+                                        res,
+                                        InteractionResponseType::UpdateMessage,
+                                        user,
+                                        channel_id,
+                                        channel,
+                                    )
+                                })
+                                .await?;
+                        }
+                        cmp => {
+                            error!(?cmp, "modal component was not an input text");
+                            return Err(HandlerError::UnexpectedData);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            .instrument(span)
+            .await?;
+            // don't send typical interaction response
+            return Ok(None);
+        }
+        Ok(Ids::PrefixEmotesBtn) => {
+            channel.prefix_emotes_enabled = !channel.prefix_emotes_enabled;
+            debug!(channel.prefix_emotes_enabled, "prefix emotes toggled");
+        }
+        Ok(Ids::Submit) => {
+            interaction
+                .create_interaction_response(context, |res| {
+                    res.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.content(SETTINGS_SAVED.for_user(user))
+                                .components(|cmp| cmp)
+                        })
+                })
+                .await?;
+            return Ok(Some(mem::take(channel)));
+        }
+        Err(err) => {
+            error!(?err, "unexpected component id");
+        }
+    }
+
+    interaction
+        .create_interaction_response(context, |res| {
+            create_response(
+                res,
+                InteractionResponseType::UpdateMessage,
+                user,
+                channel_id,
+                channel,
+            )
+        })
+        .await?;
+
+    Ok(None)
+}
+
+async fn handle_interactions(
+    context: &Context,
+    msg: &Message,
+    user: &DbUser,
+    channel_id: ChannelId,
+    mut db_channel: DbChannel,
+) -> Result<DbChannel, HandlerError> {
+    while let Some(interaction) = msg
+        .await_component_interactions(context)
+        .collect_limit(20)
+        .timeout(INTERACTION_TIMEOUT)
+        .build()
+        .next()
+        .await
+    {
+        if let Some(res) =
+            handle_interaction(context, msg, user, channel_id, interaction, &mut db_channel).await?
+        {
+            return Ok(res);
+        }
+    }
+    Err(HandlerError::TimeoutOrOverLimit)
+}
+
+#[instrument(skip(res))]
+fn create_response<'a, 'b>(
+    res: &'a mut CreateInteractionResponse<'b>,
+    kind: InteractionResponseType,
+    user: &DbUser,
+    channel_id: ChannelId,
+    db_channel: &DbChannel,
+) -> &'a mut CreateInteractionResponse<'b> {
+    res.kind(kind).interaction_response_data(|data| {
+        data.ephemeral(true)
+            .content(format!(
+                "{} {}",
+                CONTENT.for_user(user),
+                channel_id.mention()
+            ))
+            .components(|c| {
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::LanguageSelect).options(|opts| {
+                            opts.create_option(|o| {
+                                o.label(USE_SERVER_SETTING.for_user(user))
+                                    .value(USE_SERVER_SETTING_VALUE)
+                                    .default_selection(db_channel.language.is_none())
+                            });
+                            DbLanguage::iter().for_each(|lang| {
+                                opts.create_option(|o| {
+                                    o.label(lang.for_user(user))
+                                        .value(lang as i32)
+                                        .default_selection(db_channel.language == Some(lang))
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::GenderSelect).options(|opts| {
+                            opts.create_option(|o| {
+                                o.label(USE_SERVER_SETTING.for_user(user))
+                                    .value(USE_SERVER_SETTING_VALUE)
+                                    .default_selection(db_channel.gender.is_none())
+                            });
+                            DbGender::iter().for_each(|gender| {
+                                opts.create_option(|o| {
+                                    o.label(gender.for_user(user))
+                                        .value(gender as i32)
+                                        .default_selection(db_channel.gender == Some(gender))
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::OutputFormatSelect).options(|opts| {
+                            opts.create_option(|o| {
+                                o.label(USE_SERVER_SETTING.for_user(user))
+                                    .value(USE_SERVER_SETTING_VALUE)
+                                    .default_selection(db_channel.output_format.is_none())
+                            });
+                            DbOutputFormat::iter().for_each(|format| {
+                                opts.create_option(|o| {
+                                    o.label(format.for_user(user))
+                                        .value(format as i32)
+                                        .default_selection(db_channel.output_format == Some(format))
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::SendModeSelect).options(|opts| {
+                            opts.create_option(|o| {
+                                o.label(USE_SERVER_SETTING.for_user(user))
+                                    .value(USE_SERVER_SETTING_VALUE)
+                                    .default_selection(db_channel.send_mode.is_none())
+                            });
+                            DbSendMode::iter().for_each(|mode| {
+                                opts.create_option(|o| {
+                                    o.label(mode.for_user(user))
+                                        .value(mode as i32)
+                                        .default_selection(db_channel.send_mode == Some(mode))
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
+                // messages can only have five action rows
+                c.create_action_row(|row| {
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::PrefixInputBtn).label(
+                            [
+                                PREFIX_INPUT_BTN.for_user(user),
+                                db_channel
+                                    .prefix
+                                    .as_deref()
+                                    .unwrap_or(USE_SERVER_SETTING.for_user(user)),
+                            ]
+                            .concat(),
+                        )
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::PrefixEmotesBtn)
+                            .style(ButtonStyle::Secondary)
+                            .label(if db_channel.prefix_emotes_enabled {
+                                PREFIX_EMOTES_ENABLED_BTN.for_user(user)
+                            } else {
+                                PREFIX_EMOTES_DISABLED_BTN.for_user(user)
+                            })
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::Submit).label(SAVE_BTN.for_user(user))
+                    })
+                })
+            })
+    })
+}
+
+pub struct ChannelSettingsCmd;
+
+#[async_trait]
+impl AppCmd for ChannelSettingsCmd {
+    fn to_application_command() -> CreateApplicationCommand
+    where
+        Self: Sized,
+    {
+        let mut cmd = CreateApplicationCommand::default();
+        cmd.localized_name(NAME)
+            .kind(CommandType::ChatInput)
+            .localized_desc(DESC)
+            .default_member_permissions(Permissions::MANAGE_CHANNELS);
+        cmd
+    }
+
+    #[instrument(skip(cmd, handler, context))]
+    async fn handle(
+        cmd: &ApplicationCommandInteraction,
+        handler: &Handler,
+        context: &Context,
+        message_db_data: &MessageDbData,
+    ) -> Result<(), HandlerError>
+    where
+        Self: Sized,
+    {
+        let user = message_db_data.determine_user_settings().await?;
+        let guild_id = cmd.guild_id.ok_or(HandlerError::NotGuild)?;
+        let channel = message_db_data.channel().await?.unwrap_or_default();
+        info!(?guild_id, ?cmd.channel_id, "channel settings command");
+
+        cmd.create_interaction_response(context, |res| {
+            create_response(
+                res,
+                InteractionResponseType::ChannelMessageWithSource,
+                &user,
+                cmd.channel_id,
+                &channel,
+            )
+        })
+        .await?;
+        let msg = cmd.get_interaction_response(context).await?;
+        trace!("awaiting interactions");
+        let channel =
+            handle_interactions(context, &msg, &user, cmd.channel_id, channel.into_owned()).await?;
+
+        handler
+            .db
+            .upsert_channel(&cmd.channel_id, &guild_id, &channel)
+            .await?;
+
+        Ok(())
+    }
+
+    fn name() -> LocalizedString {
+        NAME
+    }
+}
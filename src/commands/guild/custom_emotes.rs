@@ -0,0 +1,319 @@
+use async_trait::async_trait;
+use serenity::{
+    builder::CreateApplicationCommand,
+    model::{
+        prelude::{
+            command::{CommandOptionType, CommandType},
+            interaction::application_command::ApplicationCommandInteraction,
+        },
+        Permissions,
+    },
+    prelude::Context,
+};
+use tracing::*;
+
+use crate::{
+    commands::{string_option, AppCmd},
+    db::models::DbUser,
+    handler::guild_emotes::{normalize_command, parse_guild_emote, GuildEmoteError},
+    util::{CreateApplicationCommandExt, CreateApplicationCommandOptionExt, LocalizedString},
+    Handler, HandlerError, MessageDbData,
+};
+
+pub const NAME: LocalizedString = LocalizedString {
+    en: "custom-emote",
+    ja: "カスタムエモート",
+    de: "eigenes-emote",
+    fr: "emote-perso",
+};
+pub const DESC: LocalizedString = LocalizedString {
+    en: "Manage this server's own emotes",
+    ja: "サーバー独自のエモートの管理",
+    de: "Die eigenen Emotes dieses Servers verwalten",
+    fr: "Gérer les emotes propres à ce serveur",
+};
+pub const ADD_SUB_NAME: LocalizedString = LocalizedString {
+    en: "add",
+    ja: "追加",
+    de: "hinzufügen",
+    fr: "ajouter",
+};
+pub const ADD_SUB_DESC: LocalizedString = LocalizedString {
+    en: "Add a new emote to this server",
+    ja: "サーバーにエモートを追加",
+    de: "Diesem Server ein neues Emote hinzufügen",
+    fr: "Ajouter une nouvelle emote à ce serveur",
+};
+pub const EDIT_SUB_NAME: LocalizedString = LocalizedString {
+    en: "edit",
+    ja: "編集",
+    de: "bearbeiten",
+    fr: "modifier",
+};
+pub const EDIT_SUB_DESC: LocalizedString = LocalizedString {
+    en: "Change the messages of one of this server's emotes",
+    ja: "サーバーのエモートのメッセージを変更",
+    de: "Die Nachrichten eines Emotes dieses Servers ändern",
+    fr: "Modifier les messages d'une emote de ce serveur",
+};
+pub const DELETE_SUB_NAME: LocalizedString = LocalizedString {
+    en: "delete",
+    ja: "削除",
+    de: "löschen",
+    fr: "supprimer",
+};
+pub const DELETE_SUB_DESC: LocalizedString = LocalizedString {
+    en: "Delete one of this server's emotes",
+    ja: "サーバーのエモートを削除",
+    de: "Ein Emote dieses Servers löschen",
+    fr: "Supprimer une emote de ce serveur",
+};
+pub const COMMAND_OPT_NAME: LocalizedString = LocalizedString {
+    en: "command",
+    ja: "コマンド",
+    de: "befehl",
+    fr: "commande",
+};
+pub const COMMAND_OPT_DESC: LocalizedString = LocalizedString {
+    en: "Command that sends the emote, such as /boop",
+    ja: "エモートを送信するコマンド（例：/boop）",
+    de: "Befehl, der das Emote sendet, etwa /boop",
+    fr: "Commande qui envoie l'emote, par exemple /boop",
+};
+pub const TARGETED_OPT_NAME: LocalizedString = LocalizedString {
+    en: "targeted",
+    ja: "ターゲットあり",
+    de: "mit-ziel",
+    fr: "avec-cible",
+};
+pub const TARGETED_OPT_DESC: LocalizedString = LocalizedString {
+    en: "Message when sent with a target, in the game's log message markup",
+    ja: "ターゲットありのメッセージ（ゲームのログメッセージ記法）",
+    de: "Nachricht mit Ziel, im Format der Log-Nachrichten des Spiels",
+    fr: "Message avec une cible, au format des messages du journal du jeu",
+};
+pub const UNTARGETED_OPT_NAME: LocalizedString = LocalizedString {
+    en: "untargeted",
+    ja: "ターゲットなし",
+    de: "ohne-ziel",
+    fr: "sans-cible",
+};
+pub const UNTARGETED_OPT_DESC: LocalizedString = LocalizedString {
+    en: "Message when sent without a target, in the game's log message markup",
+    ja: "ターゲットなしのメッセージ（ゲームのログメッセージ記法）",
+    de: "Nachricht ohne Ziel, im Format der Log-Nachrichten des Spiels",
+    fr: "Message sans cible, au format des messages du journal du jeu",
+};
+pub const ADDED: LocalizedString = LocalizedString {
+    en: "Emote added:",
+    ja: "エモートを追加しました：",
+    de: "Emote hinzugefügt:",
+    fr: "Emote ajoutée :",
+};
+pub const UPDATED: LocalizedString = LocalizedString {
+    en: "Emote updated:",
+    ja: "エモートを更新しました：",
+    de: "Emote aktualisiert:",
+    fr: "Emote modifiée :",
+};
+pub const DELETED: LocalizedString = LocalizedString {
+    en: "Emote deleted:",
+    ja: "エモートを削除しました：",
+    de: "Emote gelöscht:",
+    fr: "Emote supprimée :",
+};
+pub const ALREADY_EXISTS: LocalizedString = LocalizedString {
+    en: "This server already has an emote with that command:",
+    ja: "このコマンドのエモートは既に存在します：",
+    de: "Dieser Server hat bereits ein Emote mit diesem Befehl:",
+    fr: "Ce serveur a déjà une emote avec cette commande :",
+};
+pub const NOT_FOUND: LocalizedString = LocalizedString {
+    en: "This server has no emote with that command:",
+    ja: "このコマンドのエモートは存在しません：",
+    de: "Dieser Server hat kein Emote mit diesem Befehl:",
+    fr: "Ce serveur n'a pas d'emote avec cette commande :",
+};
+pub const INVALID_COMMAND: LocalizedString = LocalizedString {
+    en: "Commands can't be empty, contain spaces or be longer than 30 characters",
+    ja: "コマンドは空欄、空白を含むもの、30文字を超えるものにはできません",
+    de: "Befehle dürfen nicht leer sein, keine Leerzeichen enthalten und höchstens 30 Zeichen lang sein",
+    fr: "Les commandes ne peuvent pas être vides, contenir d'espaces ou dépasser 30 caractères",
+};
+pub const BUILT_IN: LocalizedString = LocalizedString {
+    en: "That command is already used by a built-in emote:",
+    ja: "このコマンドは既存のエモートで使われています：",
+    de: "Dieser Befehl wird bereits von einem eingebauten Emote verwendet:",
+    fr: "Cette commande est déjà utilisée par une emote intégrée :",
+};
+pub const INVALID_MESSAGE: LocalizedString = LocalizedString {
+    en: "Couldn't read the message markup",
+    ja: "メッセージの記法を読み取れませんでした",
+    de: "Das Format der Nachricht konnte nicht gelesen werden",
+    fr: "Impossible de lire le format du message",
+};
+pub const UNTARGETED_TARGET: LocalizedString = LocalizedString {
+    en: "The message without a target can't refer to the target",
+    ja: "ターゲットなしのメッセージではターゲットを参照できません",
+    de: "Die Nachricht ohne Ziel kann sich nicht auf das Ziel beziehen",
+    fr: "Le message sans cible ne peut pas faire référence à la cible",
+};
+
+pub struct CustomEmotesCmd;
+
+fn error_message(err: &GuildEmoteError, command: &str, user: &DbUser) -> String {
+    match err {
+        GuildEmoteError::InvalidCommand => INVALID_COMMAND.for_user(user).to_string(),
+        GuildEmoteError::BuiltIn => format!("{} {}", BUILT_IN.for_user(user), command),
+        GuildEmoteError::InvalidMessage(e) => {
+            format!("{}\n```\n{}\n```", INVALID_MESSAGE.for_user(user), e)
+        }
+        GuildEmoteError::UntargetedTarget => UNTARGETED_TARGET.for_user(user).to_string(),
+    }
+}
+
+fn create_messages_options(sub: &mut serenity::builder::CreateApplicationCommandOption) {
+    sub.create_sub_option(|opt| {
+        opt.kind(CommandOptionType::String)
+            .localized_name(COMMAND_OPT_NAME)
+            .localized_desc(COMMAND_OPT_DESC)
+            .required(true)
+    })
+    .create_sub_option(|opt| {
+        opt.kind(CommandOptionType::String)
+            .localized_name(TARGETED_OPT_NAME)
+            .localized_desc(TARGETED_OPT_DESC)
+            .required(true)
+    })
+    .create_sub_option(|opt| {
+        opt.kind(CommandOptionType::String)
+            .localized_name(UNTARGETED_OPT_NAME)
+            .localized_desc(UNTARGETED_OPT_DESC)
+            .required(true)
+    });
+}
+
+#[async_trait]
+impl AppCmd for CustomEmotesCmd {
+    fn to_application_command() -> CreateApplicationCommand
+    where
+        Self: Sized,
+    {
+        let mut cmd = CreateApplicationCommand::default();
+        cmd.localized_name(NAME)
+            .kind(CommandType::ChatInput)
+            .localized_desc(DESC)
+            .default_member_permissions(Permissions::MANAGE_CHANNELS)
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::SubCommand)
+                    .localized_name(ADD_SUB_NAME)
+                    .localized_desc(ADD_SUB_DESC);
+                create_messages_options(opt);
+                opt
+            })
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::SubCommand)
+                    .localized_name(EDIT_SUB_NAME)
+                    .localized_desc(EDIT_SUB_DESC);
+                create_messages_options(opt);
+                opt
+            })
+            .create_option(|opt| {
+                opt.kind(CommandOptionType::SubCommand)
+                    .localized_name(DELETE_SUB_NAME)
+                    .localized_desc(DELETE_SUB_DESC)
+                    .create_sub_option(|sub| {
+                        sub.kind(CommandOptionType::String)
+                            .localized_name(COMMAND_OPT_NAME)
+                            .localized_desc(COMMAND_OPT_DESC)
+                            .required(true)
+                    })
+            });
+        cmd
+    }
+
+    #[instrument(skip(cmd, handler, context))]
+    async fn handle(
+        cmd: &ApplicationCommandInteraction,
+        handler: &Handler,
+        context: &Context,
+        message_db_data: &MessageDbData,
+    ) -> Result<(), HandlerError>
+    where
+        Self: Sized,
+    {
+        let user = message_db_data.determine_user_settings().await?;
+        let guild_id = cmd.guild_id.ok_or(HandlerError::NotGuild)?;
+        let sub = cmd
+            .data
+            .options
+            .first()
+            .ok_or(HandlerError::UnexpectedData)?;
+        let command = string_option(sub, 0)?;
+        info!(sub.name, command, "custom emote command");
+
+        let content = match normalize_command(command) {
+            None => error_message(&GuildEmoteError::InvalidCommand, command, &user),
+            Some(command) if DELETE_SUB_NAME.any_eq(&sub.name) => {
+                if handler.db.delete_guild_emote(&guild_id, &command).await? {
+                    format!("{} {}", DELETED.for_user(&user), command)
+                } else {
+                    format!("{} {}", NOT_FOUND.for_user(&user), command)
+                }
+            }
+            Some(command) => {
+                let targeted = string_option(sub, 1)?;
+                let untargeted = string_option(sub, 2)?;
+                let validated = if handler.contains_emote(&command) {
+                    Err(GuildEmoteError::BuiltIn)
+                } else {
+                    parse_guild_emote(0, &command, targeted, untargeted).map(|_| ())
+                };
+                match validated {
+                    Err(err) => {
+                        debug!(?err, "invalid custom emote");
+                        error_message(&err, &command, &user)
+                    }
+                    Ok(()) if ADD_SUB_NAME.any_eq(&sub.name) => {
+                        if handler
+                            .db
+                            .insert_guild_emote(&guild_id, &command, targeted, untargeted)
+                            .await?
+                        {
+                            format!("{} {}", ADDED.for_user(&user), command)
+                        } else {
+                            format!("{} {}", ALREADY_EXISTS.for_user(&user), command)
+                        }
+                    }
+                    Ok(()) if EDIT_SUB_NAME.any_eq(&sub.name) => {
+                        if handler
+                            .db
+                            .update_guild_emote(&guild_id, &command, targeted, untargeted)
+                            .await?
+                        {
+                            format!("{} {}", UPDATED.for_user(&user), command)
+                        } else {
+                            format!("{} {}", NOT_FOUND.for_user(&user), command)
+                        }
+                    }
+                    Ok(()) => {
+                        error!(sub.name, "unknown custom emote subcommand");
+                        return Err(HandlerError::UnexpectedData);
+                    }
+                }
+            }
+        };
+        handler.forget_guild_emotes(guild_id).await;
+
+        cmd.create_interaction_response(context, |res| {
+            res.interaction_response_data(|data| data.ephemeral(true).content(content))
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    fn name() -> LocalizedString {
+        NAME
+    }
+}
@@ -1,4 +1,9 @@
-use std::{mem, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    mem,
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -7,12 +12,12 @@ use serenity::{
     model::{
         prelude::{
             command::CommandType,
-            component::{ActionRowComponent, InputTextStyle},
+            component::{ActionRowComponent, ButtonStyle, InputTextStyle},
             interaction::{
                 application_command::ApplicationCommandInteraction,
                 message_component::MessageComponentInteraction, InteractionResponseType,
             },
-            Message,
+            ChannelId, ChannelType, GuildChannel, Message,
         },
         Permissions,
     },
@@ -24,7 +29,13 @@ use tracing::*;
 
 use crate::{
     commands::AppCmd,
-    db::models::{DbGender, DbGuild, DbLanguage, DbUser},
+    db::{
+        models::{
+            DbChannelFilterMode, DbGender, DbGuild, DbLanguage, DbMultiTargetMode, DbOutputFormat,
+            DbSendMode, DbUser,
+        },
+        util::DiscordIdExt,
+    },
     util::{CreateApplicationCommandExt, LocalizedString},
     Handler, HandlerError, MessageDbData, INTERACTION_TIMEOUT,
 };
@@ -32,47 +43,189 @@ use crate::{
 pub const CONTENT: LocalizedString = LocalizedString {
     en: "Server-wide emote message settings",
     ja: "サーバーのエモート設定",
+    de: "Serverweite Emote-Einstellungen",
+    fr: "Paramètres d'emote du serveur",
 };
 pub const PREFIX_INPUT_BTN: LocalizedString = LocalizedString {
     en: "Input a command prefix, currently: ",
     ja: "コマンドプレフィックスを入力、現在：",
+    de: "Befehlspräfix eingeben, derzeit: ",
+    fr: "Saisir un préfixe de commande, actuellement : ",
 };
 pub const PREFIX_INPUT_MODAL_CONTENT: LocalizedString = LocalizedString {
     en: "Input a command prefix (up to 5 characters)",
     ja: "コマンドプレフィックスを入力してください（5文字まで）",
+    de: "Befehlspräfix eingeben (bis zu 5 Zeichen)",
+    fr: "Saisissez un préfixe de commande (5 caractères maximum)",
 };
 pub const PREFIX_INPUT_MODAL_INPUT: LocalizedString = LocalizedString {
     en: "Command prefix",
     ja: "コマンドプレフィックス",
+    de: "Befehlspräfix",
+    fr: "Préfixe de commande",
 };
 pub const PREFIX_INPUT_MODAL_TITLE: LocalizedString = LocalizedString {
     en: "Server-wide command prefix",
     ja: "サーバーのコマンドプレフィックス",
+    de: "Serverweites Befehlspräfix",
+    fr: "Préfixe de commande du serveur",
+};
+pub const PINGS_ENABLED_BTN: LocalizedString = LocalizedString {
+    en: "Emotes ping the users they mention",
+    ja: "エモートでメンションしたユーザーに通知する",
+    de: "Emotes pingen die erwähnten Benutzer",
+    fr: "Les emotes notifient les utilisateurs mentionnés",
+};
+pub const PINGS_DISABLED_BTN: LocalizedString = LocalizedString {
+    en: "Emotes don't ping anyone",
+    ja: "エモートで誰にも通知しない",
+    de: "Emotes pingen niemanden",
+    fr: "Les emotes ne notifient personne",
+};
+pub const OUTPUT_FORMAT_BTN: LocalizedString = LocalizedString {
+    en: "Output format: ",
+    ja: "表示形式：",
+    de: "Ausgabeformat: ",
+    fr: "Format d'affichage : ",
+};
+pub const CHANNEL_FILTER_BTN: LocalizedString = LocalizedString {
+    en: "Channels",
+    ja: "チャンネル",
+    de: "Kanäle",
+    fr: "Salons",
+};
+pub const CHANNEL_FILTER_CONTENT: LocalizedString = LocalizedString {
+    en: "Channels and categories for emotes, page",
+    ja: "エモートのチャンネルとカテゴリー、ページ",
+    de: "Kanäle und Kategorien für Emotes, Seite",
+    fr: "Salons et catégories pour les emotes, page",
+};
+pub const CHANNEL_FILTER_SELECT: LocalizedString = LocalizedString {
+    en: "No channels selected",
+    ja: "チャンネル未選択",
+    de: "Keine Kanäle ausgewählt",
+    fr: "Aucun salon sélectionné",
+};
+pub const PREV_BTN: LocalizedString = LocalizedString {
+    en: "Previous page",
+    ja: "前のページ",
+    de: "Vorherige Seite",
+    fr: "Page précédente",
+};
+pub const NEXT_BTN: LocalizedString = LocalizedString {
+    en: "Next page",
+    ja: "次のページ",
+    de: "Nächste Seite",
+    fr: "Page suivante",
+};
+pub const BACK_BTN: LocalizedString = LocalizedString {
+    en: "Back",
+    ja: "戻る",
+    de: "Zurück",
+    fr: "Retour",
 };
 pub const SAVE_BTN: LocalizedString = LocalizedString {
     en: "Save",
     ja: "保存",
+    de: "Speichern",
+    fr: "Enregistrer",
 };
 pub const SETTINGS_SAVED: LocalizedString = LocalizedString {
     en: "Settings saved!",
     ja: "設定を保存しました！",
+    de: "Einstellungen gespeichert!",
+    fr: "Paramètres enregistrés !",
 };
 pub const NAME: LocalizedString = LocalizedString {
     en: "server-settings",
     ja: "サーバー設定",
+    de: "server-einstellungen",
+    fr: "paramètres-serveur",
 };
 pub const DESC: LocalizedString = LocalizedString {
     en: "Set the default emote message settings used for this server",
     ja: "このサーバーのデフォルトのエモート設定",
+    de: "Standard-Emote-Einstellungen für diesen Server festlegen",
+    fr: "Définir les paramètres d'emote par défaut de ce serveur",
 };
 
 const PREFIX_INPUT_MODAL: &str = "prefix_input_modal";
 const PREFIX_INPUT_MODAL_BTN: &str = "prefix_input_modal_btn";
+const CHANNEL_LIST_OFFSET_STEP: usize = 25;
+const MAX_OPTION_LABEL_LEN: usize = 100;
+
+/// A channel or category that can be added to the channel filter
+#[derive(Debug, Clone)]
+struct ChannelInfo {
+    id: ChannelId,
+    label: String,
+}
+
+impl ChannelInfo {
+    /// The guild's text channels and categories in the order Discord lists them, with each category
+    /// before its channels
+    fn list(channels: HashMap<ChannelId, GuildChannel>) -> Vec<ChannelInfo> {
+        let position = |id: Option<ChannelId>| {
+            id.and_then(|id| channels.get(&id))
+                .map(|channel| channel.position)
+                .unwrap_or(-1)
+        };
+        let mut listed: Vec<_> = channels
+            .values()
+            .filter_map(|channel| match channel.kind {
+                ChannelType::Category => Some(((channel.position, 0, 0), channel)),
+                ChannelType::Text | ChannelType::News => {
+                    Some(((position(channel.parent_id), 1, channel.position), channel))
+                }
+                _ => None,
+            })
+            .collect();
+        listed.sort_unstable_by_key(|(key, channel)| (*key, channel.id));
+        listed
+            .into_iter()
+            .map(|(_, channel)| {
+                let label = match channel.kind {
+                    ChannelType::Category => channel.name.to_uppercase(),
+                    _ => format!("#{}", channel.name),
+                };
+                ChannelInfo {
+                    id: channel.id,
+                    label: label.chars().take(MAX_OPTION_LABEL_LEN).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The channels the channel filter is picked from, and the page of them being shown in place of the
+/// other settings, if any
+#[derive(Debug, Clone, Default)]
+struct ChannelFilterView {
+    channels: Vec<ChannelInfo>,
+    offset: Option<usize>,
+}
+
+impl ChannelFilterView {
+    fn page(&self, offset: usize) -> &[ChannelInfo] {
+        let end = (offset + CHANNEL_LIST_OFFSET_STEP).min(self.channels.len());
+        &self.channels[offset.min(end)..end]
+    }
+}
 
 enum Ids {
     GenderSelect,
     LanguageSelect,
+    MultiTargetSelect,
+    SendModeSelect,
     PrefixInputBtn,
+    PingsBtn,
+    OutputFormatBtn,
+    ChannelFilterBtn,
+    ChannelFilterModeSelect,
+    ChannelFilterSelect,
+    ChannelPagePrevBtn,
+    ChannelPageNextBtn,
+    ChannelFilterBackBtn,
     Submit,
 }
 
@@ -87,15 +240,25 @@ impl From<&Ids> for &'static str {
         match ids {
             Ids::GenderSelect => "gender_select",
             Ids::LanguageSelect => "language_select",
+            Ids::MultiTargetSelect => "multi_target_select",
+            Ids::SendModeSelect => "send_mode_select",
             Ids::PrefixInputBtn => "prefix_input_btn",
+            Ids::PingsBtn => "pings_btn",
+            Ids::OutputFormatBtn => "output_format_btn",
+            Ids::ChannelFilterBtn => "channel_filter_btn",
+            Ids::ChannelFilterModeSelect => "channel_filter_mode_select",
+            Ids::ChannelFilterSelect => "channel_filter_select",
+            Ids::ChannelPagePrevBtn => "channel_page_prev_btn",
+            Ids::ChannelPageNextBtn => "channel_page_next_btn",
+            Ids::ChannelFilterBackBtn => "channel_filter_back_btn",
             Ids::Submit => "submit",
         }
     }
 }
 
-impl ToString for Ids {
-    fn to_string(&self) -> String {
-        Into::<&'static str>::into(self).to_string()
+impl Display for Ids {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.into())
     }
 }
 
@@ -110,7 +273,17 @@ impl TryFrom<&str> for Ids {
         match value {
             "gender_select" => Ok(Ids::GenderSelect),
             "language_select" => Ok(Ids::LanguageSelect),
+            "multi_target_select" => Ok(Ids::MultiTargetSelect),
+            "send_mode_select" => Ok(Ids::SendModeSelect),
             "prefix_input_btn" => Ok(Ids::PrefixInputBtn),
+            "pings_btn" => Ok(Ids::PingsBtn),
+            "output_format_btn" => Ok(Ids::OutputFormatBtn),
+            "channel_filter_btn" => Ok(Ids::ChannelFilterBtn),
+            "channel_filter_mode_select" => Ok(Ids::ChannelFilterModeSelect),
+            "channel_filter_select" => Ok(Ids::ChannelFilterSelect),
+            "channel_page_prev_btn" => Ok(Ids::ChannelPagePrevBtn),
+            "channel_page_next_btn" => Ok(Ids::ChannelPageNextBtn),
+            "channel_filter_back_btn" => Ok(Ids::ChannelFilterBackBtn),
             "submit" => Ok(Ids::Submit),
             s => Err(InvalidComponentId(s.to_string())),
         }
@@ -124,6 +297,7 @@ async fn handle_interaction(
     user: &DbUser,
     interaction: Arc<MessageComponentInteraction>,
     guild: &mut DbGuild,
+    view: &mut ChannelFilterView,
 ) -> Result<Option<DbGuild>, HandlerError> {
     match Ids::try_from(interaction.data.custom_id.as_str()) {
         Ok(Ids::GenderSelect) => {
@@ -162,6 +336,45 @@ async fn handle_interaction(
             debug!(?lang, "language selected");
             guild.language = lang;
         }
+        Ok(Ids::MultiTargetSelect) => {
+            let value = &interaction.data.values[0];
+            let value = if let Ok(v) = value.parse() {
+                v
+            } else {
+                error!(value, "unexpected multi-target mode selected (not numeric)");
+                return Err(HandlerError::UnexpectedData);
+            };
+            let mode = match DbMultiTargetMode::from_repr(value) {
+                Some(m) => m,
+                None => {
+                    error!(
+                        value,
+                        "unexpected multi-target mode selected (invalid number)"
+                    );
+                    return Err(HandlerError::UnexpectedData);
+                }
+            };
+            debug!(?mode, "multi-target mode selected");
+            guild.multi_target_mode = mode;
+        }
+        Ok(Ids::SendModeSelect) => {
+            let value = &interaction.data.values[0];
+            let value = if let Ok(v) = value.parse() {
+                v
+            } else {
+                error!(value, "unexpected send mode selected (not numeric)");
+                return Err(HandlerError::UnexpectedData);
+            };
+            let mode = match DbSendMode::from_repr(value) {
+                Some(m) => m,
+                None => {
+                    error!(value, "unexpected send mode selected (invalid number)");
+                    return Err(HandlerError::UnexpectedData);
+                }
+            };
+            debug!(?mode, "send mode selected");
+            guild.send_mode = mode;
+        }
         Ok(Ids::PrefixInputBtn) => {
             debug!("prefix input");
             let span = debug_span!("prefix_input_modal_interaction");
@@ -203,6 +416,7 @@ async fn handle_interaction(
                                         InteractionResponseType::UpdateMessage,
                                         user,
                                         guild,
+                                        view,
                                     )
                                 })
                                 .await?;
@@ -220,6 +434,81 @@ async fn handle_interaction(
             // don't send typical interaction response
             return Ok(None);
         }
+        Ok(Ids::PingsBtn) => {
+            guild.pings_enabled = !guild.pings_enabled;
+            debug!(guild.pings_enabled, "pings toggled");
+        }
+        Ok(Ids::OutputFormatBtn) => {
+            // there's no room left for another select menu, so the button cycles through them
+            guild.output_format =
+                DbOutputFormat::from_repr(guild.output_format as i32 + 1).unwrap_or_default();
+            debug!(?guild.output_format, "output format changed");
+        }
+        Ok(Ids::ChannelFilterBtn) => {
+            debug!("showing channel filter");
+            view.offset = Some(0);
+        }
+        Ok(Ids::ChannelFilterBackBtn) => {
+            debug!("showing settings");
+            view.offset = None;
+        }
+        Ok(Ids::ChannelPagePrevBtn) => {
+            debug!(view.offset, "previous channel list page");
+            view.offset = view
+                .offset
+                .map(|offset| offset.saturating_sub(CHANNEL_LIST_OFFSET_STEP));
+        }
+        Ok(Ids::ChannelPageNextBtn) => {
+            debug!(view.offset, "next channel list page");
+            view.offset = view.offset.map(|offset| {
+                if offset + CHANNEL_LIST_OFFSET_STEP < view.channels.len() {
+                    offset + CHANNEL_LIST_OFFSET_STEP
+                } else {
+                    offset
+                }
+            });
+        }
+        Ok(Ids::ChannelFilterModeSelect) => {
+            let value = &interaction.data.values[0];
+            let value = if let Ok(v) = value.parse() {
+                v
+            } else {
+                error!(
+                    value,
+                    "unexpected channel filter mode selected (not numeric)"
+                );
+                return Err(HandlerError::UnexpectedData);
+            };
+            let mode = match DbChannelFilterMode::from_repr(value) {
+                Some(m) => m,
+                None => {
+                    error!(
+                        value,
+                        "unexpected channel filter mode selected (invalid number)"
+                    );
+                    return Err(HandlerError::UnexpectedData);
+                }
+            };
+            debug!(?mode, "channel filter mode selected");
+            guild.channel_filter_mode = mode;
+        }
+        Ok(Ids::ChannelFilterSelect) => {
+            // only the channels on the shown page can be (de)selected
+            let page: Vec<_> = view
+                .page(view.offset.unwrap_or(0))
+                .iter()
+                .map(|channel| channel.id.to_db_string())
+                .collect();
+            guild.channel_filter.retain(|id| !page.contains(id));
+            for value in &interaction.data.values {
+                if !page.contains(value) {
+                    error!(value, "unexpected channel selected");
+                    return Err(HandlerError::UnexpectedData);
+                }
+                guild.channel_filter.push(value.clone());
+            }
+            debug!(?guild.channel_filter, "channels selected");
+        }
         Ok(Ids::Submit) => {
             interaction
                 .create_interaction_response(context, |res| {
@@ -239,7 +528,13 @@ async fn handle_interaction(
 
     interaction
         .create_interaction_response(context, |res| {
-            create_response(res, InteractionResponseType::UpdateMessage, user, guild)
+            create_response(
+                res,
+                InteractionResponseType::UpdateMessage,
+                user,
+                guild,
+                view,
+            )
         })
         .await?;
 
@@ -251,6 +546,7 @@ async fn handle_interactions(
     msg: &Message,
     user: &DbUser,
     mut db_guild: DbGuild,
+    mut view: ChannelFilterView,
 ) -> Result<DbGuild, HandlerError> {
     while let Some(interaction) = msg
         .await_component_interactions(context)
@@ -261,7 +557,7 @@ async fn handle_interactions(
         .await
     {
         if let Some(res) =
-            handle_interaction(context, msg, user, interaction, &mut db_guild).await?
+            handle_interaction(context, msg, user, interaction, &mut db_guild, &mut view).await?
         {
             return Ok(res);
         }
@@ -275,7 +571,11 @@ fn create_response<'a, 'b>(
     kind: InteractionResponseType,
     user: &DbUser,
     db_guild: &DbGuild,
+    view: &ChannelFilterView,
 ) -> &'a mut CreateInteractionResponse<'b> {
+    if let Some(offset) = view.offset {
+        return create_channel_filter_response(res, kind, user, db_guild, view, offset);
+    }
     res.kind(kind).interaction_response_data(|data| {
         data.ephemeral(true)
             .content(CONTENT.for_user(user))
@@ -308,15 +608,147 @@ fn create_response<'a, 'b>(
                         })
                     })
                 });
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::MultiTargetSelect).options(|opts| {
+                            DbMultiTargetMode::iter().for_each(|mode| {
+                                opts.create_option(|o| {
+                                    o.label(mode.for_user(user))
+                                        .value(mode as i32)
+                                        .default_selection(db_guild.multi_target_mode == mode)
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::SendModeSelect).options(|opts| {
+                            DbSendMode::iter().for_each(|mode| {
+                                opts.create_option(|o| {
+                                    o.label(mode.for_user(user))
+                                        .value(mode as i32)
+                                        .default_selection(db_guild.send_mode == mode)
+                                });
+                            });
+                            opts
+                        })
+                    })
+                });
+                // messages can only have five action rows
                 c.create_action_row(|row| {
                     row.create_button(|btn| {
                         btn.custom_id(Ids::PrefixInputBtn)
                             .label([PREFIX_INPUT_BTN.for_user(user), &db_guild.prefix].concat())
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::PingsBtn)
+                            .style(ButtonStyle::Secondary)
+                            .label(if db_guild.pings_enabled {
+                                PINGS_ENABLED_BTN.for_user(user)
+                            } else {
+                                PINGS_DISABLED_BTN.for_user(user)
+                            })
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::OutputFormatBtn)
+                            .style(ButtonStyle::Secondary)
+                            .label(
+                                [
+                                    OUTPUT_FORMAT_BTN.for_user(user),
+                                    db_guild.output_format.for_user(user),
+                                ]
+                                .concat(),
+                            )
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::ChannelFilterBtn)
+                            .style(ButtonStyle::Secondary)
+                            .label(CHANNEL_FILTER_BTN.for_user(user))
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::Submit).label(SAVE_BTN.for_user(user))
+                    })
+                })
+            })
+    })
+}
+
+#[instrument(skip(res, view))]
+fn create_channel_filter_response<'a, 'b>(
+    res: &'a mut CreateInteractionResponse<'b>,
+    kind: InteractionResponseType,
+    user: &DbUser,
+    db_guild: &DbGuild,
+    view: &ChannelFilterView,
+    offset: usize,
+) -> &'a mut CreateInteractionResponse<'b> {
+    let page = view.page(offset);
+    res.kind(kind).interaction_response_data(|data| {
+        data.ephemeral(true)
+            .content(format!(
+                "{} {}/{}",
+                CHANNEL_FILTER_CONTENT.for_user(user),
+                offset / CHANNEL_LIST_OFFSET_STEP + 1,
+                view.channels
+                    .len()
+                    .max(1)
+                    .div_ceil(CHANNEL_LIST_OFFSET_STEP)
+            ))
+            .components(|c| {
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(Ids::ChannelFilterModeSelect)
+                            .options(|opts| {
+                                DbChannelFilterMode::iter().for_each(|mode| {
+                                    opts.create_option(|o| {
+                                        o.label(mode.for_user(user))
+                                            .value(mode as i32)
+                                            .default_selection(db_guild.channel_filter_mode == mode)
+                                    });
+                                });
+                                opts
+                            })
                     })
                 });
+                // select menus need at least one option
+                if !page.is_empty() {
+                    c.create_action_row(|row| {
+                        row.create_select_menu(|menu| {
+                            menu.custom_id(Ids::ChannelFilterSelect)
+                                .placeholder(CHANNEL_FILTER_SELECT.for_user(user))
+                                .min_values(0)
+                                .max_values(page.len() as u64)
+                                .options(|opts| {
+                                    page.iter().for_each(|channel| {
+                                        let value = channel.id.to_db_string();
+                                        opts.create_option(|o| {
+                                            o.label(&channel.label).value(&value).default_selection(
+                                                db_guild.channel_filter.contains(&value),
+                                            )
+                                        });
+                                    });
+                                    opts
+                                })
+                        })
+                    });
+                }
                 c.create_action_row(|row| {
                     row.create_button(|btn| {
-                        btn.custom_id(Ids::Submit).label(SAVE_BTN.for_user(user))
+                        btn.custom_id(Ids::ChannelPagePrevBtn)
+                            .label(PREV_BTN.for_user(user))
+                            .disabled(offset == 0)
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::ChannelPageNextBtn)
+                            .label(NEXT_BTN.for_user(user))
+                            .disabled(offset + CHANNEL_LIST_OFFSET_STEP >= view.channels.len())
+                    });
+                    row.create_button(|btn| {
+                        btn.custom_id(Ids::ChannelFilterBackBtn)
+                            .style(ButtonStyle::Secondary)
+                            .label(BACK_BTN.for_user(user))
                     })
                 })
             })
@@ -353,6 +785,10 @@ impl AppCmd for ServerSettingsCmd {
         let guild = message_db_data.guild().await?.unwrap_or_default();
         let guild_id = cmd.guild_id.ok_or(HandlerError::NotGuild)?;
         info!(?guild_id, "server settings command");
+        let view = ChannelFilterView {
+            channels: ChannelInfo::list(guild_id.channels(context).await?),
+            offset: None,
+        };
 
         cmd.create_interaction_response(context, |res| {
             create_response(
@@ -360,17 +796,20 @@ impl AppCmd for ServerSettingsCmd {
                 InteractionResponseType::ChannelMessageWithSource,
                 &user,
                 &guild,
+                &view,
             )
         })
         .await?;
         let msg = cmd.get_interaction_response(context).await?;
         trace!("awaiting interactions");
-        let guild = handle_interactions(context, &msg, &user, guild.into_owned()).await?;
+        let filter = (guild.channel_filter_mode, guild.channel_filter.clone());
+        let guild = handle_interactions(context, &msg, &user, guild.into_owned(), view).await?;
 
-        handler
-            .db
-            .upsert_guild(&guild_id, guild.language, guild.gender, guild.prefix)
-            .await?;
+        handler.db.upsert_guild(&guild_id, &guild).await?;
+        // a channel that was blocked may not be anymore, and will need explaining if it is again
+        if filter != (guild.channel_filter_mode, guild.channel_filter.clone()) {
+            handler.forget_blocked_notices(guild_id).await;
+        }
 
         Ok(())
     }
@@ -18,34 +18,50 @@ use crate::{
 pub const GUILD_SUB_NAME: LocalizedString = LocalizedString {
     en: "guild",
     ja: "サーバー",
+    de: "server",
+    fr: "serveur",
 };
 pub const GUILD_SUB_DESC: LocalizedString = LocalizedString {
     en: "Emote usage statistics for the current guild",
     ja: "サーバーの使用統計",
+    de: "Emote-Nutzungsstatistiken für diesen Server",
+    fr: "Statistiques d'utilisation des emotes de ce serveur",
 };
 pub const GUILD_USER_SUB_NAME: LocalizedString = LocalizedString {
     en: "guild-user",
     ja: "サーバーのユーザー",
+    de: "server-benutzer",
+    fr: "serveur-utilisateur",
 };
 pub const GUILD_USER_SUB_DESC: LocalizedString = LocalizedString {
     en: "Emote usage statistics for a user within the current guild",
     ja: "ユーザーのサーバー内の使用統計",
+    de: "Emote-Nutzungsstatistiken eines Benutzers auf diesem Server",
+    fr: "Statistiques d'utilisation des emotes d'un utilisateur sur ce serveur",
 };
 pub const RECEIVED_GUILD_SUB_NAME: LocalizedString = LocalizedString {
     en: "guild",
     ja: "サーバー",
+    de: "server",
+    fr: "serveur",
 };
 pub const RECEIVED_GUILD_SUB_DESC: LocalizedString = LocalizedString {
     en: "Emote received usage statistics for the current guild",
     ja: "サーバーの使用統計",
+    de: "Statistiken der auf diesem Server erhaltenen Emotes",
+    fr: "Statistiques des emotes reçues sur ce serveur",
 };
 pub const RECEIVED_GUILD_USER_SUB_NAME: LocalizedString = LocalizedString {
     en: "guild-user",
     ja: "サーバーのユーザー",
+    de: "server-benutzer",
+    fr: "serveur-utilisateur",
 };
 pub const RECEIVED_GUILD_USER_SUB_DESC: LocalizedString = LocalizedString {
     en: "Emote received usage statistics for a user within the current guild",
     ja: "ユーザーのサーバー内の使用統計",
+    de: "Statistiken der von einem Benutzer auf diesem Server erhaltenen Emotes",
+    fr: "Statistiques des emotes reçues par un utilisateur sur ce serveur",
 };
 
 pub struct GuildStatsCmd;
@@ -68,6 +84,7 @@ impl AppCmd for GuildStatsCmd {
                         sub.kind(CommandOptionType::String)
                             .localized_name(EMOTE_OPT_NAME)
                             .localized_desc(EMOTE_OPT_DESC)
+                            .set_autocomplete(true)
                     })
             })
             .create_option(|opt| {
@@ -84,6 +101,7 @@ impl AppCmd for GuildStatsCmd {
                         sub.kind(CommandOptionType::String)
                             .localized_name(EMOTE_OPT_NAME)
                             .localized_desc(EMOTE_OPT_DESC)
+                            .set_autocomplete(true)
                     })
             })
             .create_option(|opt| {
@@ -98,6 +116,7 @@ impl AppCmd for GuildStatsCmd {
                                 sub.kind(CommandOptionType::String)
                                     .localized_name(EMOTE_OPT_NAME)
                                     .localized_desc(EMOTE_OPT_DESC)
+                                    .set_autocomplete(true)
                             })
                     })
                     .create_sub_option(|grp| {
@@ -114,6 +133,7 @@ impl AppCmd for GuildStatsCmd {
                                 sub.kind(CommandOptionType::String)
                                     .localized_name(EMOTE_OPT_NAME)
                                     .localized_desc(EMOTE_OPT_DESC)
+                                    .set_autocomplete(true)
                             })
                     })
             });
@@ -133,8 +153,10 @@ impl AppCmd for GuildStatsCmd {
         let user = message_db_data.user().await?.unwrap_or_default();
         let guild_id = cmd.guild_id.ok_or(HandlerError::NotGuild)?;
         let user_id_opt = cmd.data.resolved.users.keys().next().cloned();
+        let guild_emotes = handler.guild_emotes(cmd.guild_id).await?;
         let kind = EmoteLogQuery::from_command_data(
-            &handler.emotes,
+            &handler.catalog(),
+            &guild_emotes,
             &cmd.data.options,
             Some(guild_id),
             user_id_opt,
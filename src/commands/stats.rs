@@ -1,6 +1,6 @@
 //! Shared data between global and guild versions of the stats command
 
-use std::{borrow::Cow, collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use serenity::{
     model::prelude::{
@@ -12,9 +12,10 @@ use serenity::{
 use tracing::*;
 
 use crate::{
+    catalog::EmoteCatalog,
     commands::guild::stats::{RECEIVED_GUILD_SUB_NAME, RECEIVED_GUILD_USER_SUB_NAME},
     db::models::{DbLanguage, DbUser},
-    handler::EmoteData,
+    handler::{guild_emotes::GuildEmotes, normalize::normalize_emote_input, EmoteData},
     util::LocalizedString,
 };
 
@@ -25,34 +26,50 @@ use super::{
 pub const NAME: LocalizedString = LocalizedString {
     en: "stats",
     ja: "統計",
+    de: "statistik",
+    fr: "statistiques",
 };
 pub const DESC: LocalizedString = LocalizedString {
     en: "Emote usage statistics",
     ja: "エモート使用統計",
+    de: "Emote-Nutzungsstatistiken",
+    fr: "Statistiques d'utilisation des emotes",
 };
 pub const USER_OPT_NAME: LocalizedString = LocalizedString {
     en: "user",
     ja: "ユーザー",
+    de: "benutzer",
+    fr: "utilisateur",
 };
 pub const USER_OPT_DESC: LocalizedString = LocalizedString {
     en: "User to investigate",
     ja: "ユーザーの絞り込み",
+    de: "Zu untersuchender Benutzer",
+    fr: "Utilisateur à examiner",
 };
 pub const RECEIVED_GROUP_NAME: LocalizedString = LocalizedString {
     en: "received",
     ja: "受信",
+    de: "erhalten",
+    fr: "reçues",
 };
 pub const RECEIVED_GROUP_DESC: LocalizedString = LocalizedString {
     en: "Message statistics by targets received",
     ja: "ターゲット受信される側の使用統計",
+    de: "Statistiken nach erhaltenen Emotes",
+    fr: "Statistiques des emotes reçues en tant que cible",
 };
 pub const EMOTE_OPT_NAME: LocalizedString = LocalizedString {
     en: "emote",
     ja: "エモート",
+    de: "emote",
+    fr: "emote",
 };
 pub const EMOTE_OPT_DESC: LocalizedString = LocalizedString {
     en: "Emote to filter by",
     ja: "エモートの絞り込み",
+    de: "Nach Emote filtern",
+    fr: "Filtrer par emote",
 };
 
 #[derive(Debug, Clone)]
@@ -72,6 +89,8 @@ impl EmoteLogQuery {
         match user.language {
             DbLanguage::En => self.to_en_message(count),
             DbLanguage::Ja => self.to_ja_message(count),
+            DbLanguage::De => self.to_de_message(count),
+            DbLanguage::Fr => self.to_fr_message(count),
         }
     }
 
@@ -220,21 +239,161 @@ impl EmoteLogQuery {
         }
     }
 
-    #[instrument(skip(emotes))]
+    pub fn to_de_message(&self, count: i64) -> String {
+        let mut mb = MessageBuilder::new();
+        match self {
+            EmoteLogQuery::Guild((_, em_opt)) => {
+                mb.push("Bisher wurden ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("Emotes");
+                }
+                mb.push(" in diesem Server gesendet!").build()
+            }
+            EmoteLogQuery::GuildUser((_, u, em_opt)) => {
+                mb.push("Bisher wurden ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("Emotes");
+                }
+                mb.push(" von ")
+                    .mention(u)
+                    .push(" in diesem Server gesendet!")
+                    .build()
+            }
+            EmoteLogQuery::User((u, em_opt)) => {
+                mb.push("Bisher wurden ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("Emotes");
+                }
+                mb.push(" von ").mention(u).push(" gesendet!").build()
+            }
+            EmoteLogQuery::ReceivedGuild((_, em_opt)) => {
+                mb.push("Bisher wurden ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("Emotes");
+                }
+                mb.push(" in diesem Server empfangen!").build()
+            }
+            EmoteLogQuery::ReceivedGuildUser((_, u, em_opt)) => {
+                mb.push("Bisher wurden ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("Emotes");
+                }
+                mb.push(" von ")
+                    .mention(u)
+                    .push(" in diesem Server empfangen!")
+                    .build()
+            }
+            EmoteLogQuery::ReceivedUser((u, em_opt)) => {
+                mb.push("Bisher wurden ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("Emotes");
+                }
+                mb.push(" von ").mention(u).push(" empfangen!").build()
+            }
+        }
+    }
+
+    pub fn to_fr_message(&self, count: i64) -> String {
+        let mut mb = MessageBuilder::new();
+        match self {
+            EmoteLogQuery::Guild((_, em_opt)) => {
+                mb.push("Jusqu'à présent, ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("emotes");
+                }
+                mb.push(" ont été envoyées sur ce serveur !").build()
+            }
+            EmoteLogQuery::GuildUser((_, u, em_opt)) => {
+                mb.push("Jusqu'à présent, ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("emotes");
+                }
+                mb.push(" ont été envoyées par ")
+                    .mention(u)
+                    .push(" sur ce serveur !")
+                    .build()
+            }
+            EmoteLogQuery::User((u, em_opt)) => {
+                mb.push("Jusqu'à présent, ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("emotes");
+                }
+                mb.push(" ont été envoyées par ")
+                    .mention(u)
+                    .push(" !")
+                    .build()
+            }
+            EmoteLogQuery::ReceivedGuild((_, em_opt)) => {
+                mb.push("Jusqu'à présent, ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("emotes");
+                }
+                mb.push(" ont été reçues sur ce serveur !").build()
+            }
+            EmoteLogQuery::ReceivedGuildUser((_, u, em_opt)) => {
+                mb.push("Jusqu'à présent, ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("emotes");
+                }
+                mb.push(" ont été reçues par ")
+                    .mention(u)
+                    .push(" sur ce serveur !")
+                    .build()
+            }
+            EmoteLogQuery::ReceivedUser((u, em_opt)) => {
+                mb.push("Jusqu'à présent, ").push(count).push(" ");
+                if let Some(em) = em_opt {
+                    mb.push_mono(&em.name);
+                } else {
+                    mb.push("emotes");
+                }
+                mb.push(" ont été reçues par ")
+                    .mention(u)
+                    .push(" !")
+                    .build()
+            }
+        }
+    }
+
+    #[instrument(skip(emotes, guild_emotes))]
     pub fn from_command_data(
-        emotes: &HashMap<String, Arc<EmoteData>>,
+        emotes: &EmoteCatalog,
+        guild_emotes: &GuildEmotes,
         options: &[CommandDataOption],
         guild_id_opt: Option<GuildId>,
         user_id_opt: Option<UserId>,
     ) -> Option<EmoteLogQuery> {
         debug!("determining stat command query type");
         fn get_emote_opt(
-            emotes: &HashMap<String, Arc<EmoteData>>,
+            emotes: &EmoteCatalog,
+            guild_emotes: &GuildEmotes,
             opt: &CommandDataOption,
             ind: usize,
         ) -> Option<Arc<EmoteData>> {
-            let mut emote = match opt.options.get(ind).and_then(|o| o.resolved.as_ref()) {
-                Some(CommandDataOptionValue::String(s)) => Some(Cow::Borrowed(s.as_str())),
+            let emote = match opt.options.get(ind).and_then(|o| o.resolved.as_ref()) {
+                Some(CommandDataOptionValue::String(s)) => normalize_emote_input(s, ""),
                 Some(v) => {
                     warn!(?v, "resolved to non-string value, ignoring");
                     None
@@ -242,40 +401,44 @@ impl EmoteLogQuery {
                 None => None,
             };
             trace!(?emote, "resolved emote");
-            if let Some(e) = emote.as_mut() {
-                if !e.starts_with('/') {
-                    *e = Cow::Owned(["/", e].concat())
-                }
-            };
-            emote.and_then(|em| emotes.get(em.as_ref()).cloned())
+            emote.and_then(|em| {
+                emotes
+                    .get(em.as_ref())
+                    .or_else(|| guild_emotes.get(em.as_ref()))
+                    .cloned()
+            })
         }
 
-        if let Some(top) = &options.get(0) {
+        if let Some(top) = &options.first() {
             debug!(?top);
             match (&top.name, guild_id_opt, user_id_opt) {
                 // guild
                 (_s, Some(guild_id), _) if GUILD_SUB_NAME.any_eq(_s) => Some(EmoteLogQuery::Guild(
-                    (guild_id, get_emote_opt(emotes, top, 0)),
+                    (guild_id, get_emote_opt(emotes, guild_emotes, top, 0)),
                 )),
-                (_s, Some(guild_id), Some(user_id)) if GUILD_USER_SUB_NAME.any_eq(_s) => Some(
-                    EmoteLogQuery::GuildUser((guild_id, user_id, get_emote_opt(emotes, top, 1))),
-                ),
+                (_s, Some(guild_id), Some(user_id)) if GUILD_USER_SUB_NAME.any_eq(_s) => {
+                    Some(EmoteLogQuery::GuildUser((
+                        guild_id,
+                        user_id,
+                        get_emote_opt(emotes, guild_emotes, top, 1),
+                    )))
+                }
                 // global
                 (_s, _, Some(user_id)) if USER_SUB_NAME.any_eq(_s) => Some(EmoteLogQuery::User((
                     user_id,
-                    get_emote_opt(emotes, top, 1),
+                    get_emote_opt(emotes, guild_emotes, top, 1),
                 ))),
                 // received subcommand group
                 // everything shifted over, so re-match on guild_id_opt and user_id_opt
                 (_s, _, _) if RECEIVED_GROUP_NAME.any_eq(_s) => {
-                    if let Some(received) = top.options.get(0) {
+                    if let Some(received) = top.options.first() {
                         debug!(?received);
                         match (&received.name, guild_id_opt, user_id_opt) {
                             // guild
                             (_s, Some(guild_id), _) if RECEIVED_GUILD_SUB_NAME.any_eq(_s) => {
                                 Some(EmoteLogQuery::ReceivedGuild((
                                     guild_id,
-                                    get_emote_opt(emotes, received, 0),
+                                    get_emote_opt(emotes, guild_emotes, received, 0),
                                 )))
                             }
                             (_s, Some(guild_id), Some(user_id))
@@ -284,14 +447,14 @@ impl EmoteLogQuery {
                                 Some(EmoteLogQuery::ReceivedGuildUser((
                                     guild_id,
                                     user_id,
-                                    get_emote_opt(emotes, received, 1),
+                                    get_emote_opt(emotes, guild_emotes, received, 1),
                                 )))
                             }
                             // global
                             (_s, _, Some(user_id)) if RECEIVED_USER_SUB_NAME.any_eq(_s) => {
                                 Some(EmoteLogQuery::ReceivedUser((
                                     user_id,
-                                    get_emote_opt(emotes, received, 1),
+                                    get_emote_opt(emotes, guild_emotes, received, 1),
                                 )))
                             }
                             _ => {
@@ -2,21 +2,28 @@ pub mod models;
 pub mod util;
 
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 
 use futures::{stream, StreamExt, TryStreamExt};
-use serenity::model::prelude::{GuildId, UserId};
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
 use sqlx::{PgPool, QueryBuilder, Row};
+use strum::IntoEnumIterator;
 use tracing::*;
 
+use crate::catalog::{EmoteCatalog, RawEmoteData, RawEmoteTexts};
 use crate::handler::EmoteData;
 use crate::{commands::stats::EmoteLogQuery, HandlerError};
 
-use self::models::{DbGender, DbGuild, DbLanguage, DbUser};
+use self::models::{
+    DbChannel, DbChannelFilterMode, DbEmote, DbEmoteMessage, DbEmoteUsage, DbGender, DbGuild,
+    DbGuildEmote, DbLanguage, DbMultiTargetMode, DbOutputFormat, DbSendMode, DbUser,
+    DbUserEmoteAlias,
+};
 use self::util::DiscordIdExt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Db(pub PgPool);
 
 impl Db {
@@ -24,30 +31,34 @@ impl Db {
     pub async fn upsert_user(
         &self,
         discord_id: &UserId,
-        language: DbLanguage,
-        gender: DbGender,
+        user: &DbUser,
     ) -> Result<i64, HandlerError> {
         debug!("upserting user");
-        if let Some(rec) = sqlx::query!(
+        // users are also saved without settings whenever they send or receive an emote, so this
+        // has to update those as well
+        Ok(sqlx::query!(
             "
-            SELECT user_id FROM users WHERE discord_id = $1
+            INSERT INTO users
+                (
+                    discord_id, language, gender, output_format, utc_offset, is_set_flg, insert_tm,
+                    update_tm
+                )
+            VALUES ($1, $2, $3, $4, $5, true, $6, $6)
+            ON CONFLICT (discord_id) DO UPDATE SET
+                language = $2, gender = $3, output_format = $4, utc_offset = $5, is_set_flg = true,
+                update_tm = $6
+            RETURNING user_id
             ",
-            discord_id.to_db_string()
+            discord_id.to_db_string(),
+            user.language as i32,
+            user.gender as i32,
+            user.output_format.map(|format| format as i32),
+            user.utc_offset,
+            time::OffsetDateTime::now_utc()
         )
-        .fetch_optional(&self.0)
+        .fetch_one(&self.0)
         .await?
-        {
-            Ok(rec.user_id)
-        } else {
-            self.upsert_user_with_is_set(
-                discord_id,
-                language,
-                gender,
-                true,
-                time::OffsetDateTime::now_utc(),
-            )
-            .await
-        }
+        .user_id)
     }
 
     async fn upsert_user_not_set(
@@ -108,6 +119,8 @@ impl Db {
                 discord_id,
                 language as "language: DbLanguage",
                 gender as "gender: DbGender",
+                output_format as "output_format: DbOutputFormat",
+                utc_offset,
                 is_set_flg,
                 insert_tm,
                 update_tm
@@ -125,32 +138,39 @@ impl Db {
     pub async fn upsert_guild(
         &self,
         discord_id: &GuildId,
-        language: DbLanguage,
-        gender: DbGender,
-        prefix: String,
+        guild: &DbGuild,
     ) -> Result<i64, HandlerError> {
         debug!("upserting guild");
-        if let Some(rec) = sqlx::query!(
+        // guilds are also saved without settings whenever an emote is logged in them, so this has
+        // to update those as well
+        Ok(sqlx::query!(
             "
-            SELECT guild_id FROM guilds WHERE discord_id = $1
+            INSERT INTO guilds (
+                discord_id, language, gender, prefix, multi_target_mode, pings_enabled, send_mode,
+                output_format, channel_filter_mode, channel_filter, is_set_flg, insert_tm, update_tm
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, true, $11, $11)
+            ON CONFLICT (discord_id) DO UPDATE SET
+                language = $2, gender = $3, prefix = $4, multi_target_mode = $5, pings_enabled = $6,
+                send_mode = $7, output_format = $8, channel_filter_mode = $9, channel_filter = $10,
+                is_set_flg = true, update_tm = $11
+            RETURNING guild_id
             ",
-            discord_id.to_db_string()
+            discord_id.to_db_string(),
+            guild.language as i32,
+            guild.gender as i32,
+            guild.prefix,
+            guild.multi_target_mode as i32,
+            guild.pings_enabled,
+            guild.send_mode as i32,
+            guild.output_format as i32,
+            guild.channel_filter_mode as i32,
+            &guild.channel_filter,
+            time::OffsetDateTime::now_utc()
         )
-        .fetch_optional(&self.0)
+        .fetch_one(&self.0)
         .await?
-        {
-            Ok(rec.guild_id)
-        } else {
-            self.upsert_guild_with_is_set(
-                discord_id,
-                language,
-                gender,
-                prefix,
-                true,
-                time::OffsetDateTime::now_utc(),
-            )
-            .await
-        }
+        .guild_id)
     }
 
     async fn upsert_guild_not_set(
@@ -215,6 +235,12 @@ impl Db {
                 language as "language: DbLanguage",
                 gender as "gender: DbGender",
                 prefix,
+                multi_target_mode as "multi_target_mode: DbMultiTargetMode",
+                pings_enabled,
+                send_mode as "send_mode: DbSendMode",
+                output_format as "output_format: DbOutputFormat",
+                channel_filter_mode as "channel_filter_mode: DbChannelFilterMode",
+                channel_filter,
                 is_set_flg,
                 insert_tm,
                 update_tm
@@ -228,6 +254,84 @@ impl Db {
         Ok(res)
     }
 
+    #[instrument(level = "debug", ret)]
+    pub async fn find_channel(
+        &self,
+        discord_id: &ChannelId,
+    ) -> Result<Option<DbChannel>, HandlerError> {
+        debug!("finding channel");
+        let res = sqlx::query_as!(
+            DbChannel,
+            r#"
+            SELECT
+                discord_id,
+                language as "language: DbLanguage",
+                gender as "gender: DbGender",
+                prefix,
+                output_format as "output_format: DbOutputFormat",
+                send_mode as "send_mode: DbSendMode",
+                prefix_emotes_enabled
+            FROM channels
+            WHERE discord_id = $1
+            "#,
+            discord_id.to_db_string()
+        )
+        .fetch_optional(&self.0)
+        .await?;
+        Ok(res)
+    }
+
+    #[instrument(level = "debug")]
+    pub async fn upsert_channel(
+        &self,
+        discord_id: &ChannelId,
+        guild_discord_id: &GuildId,
+        channel: &DbChannel,
+    ) -> Result<i64, HandlerError> {
+        debug!("upserting channel");
+        let now = time::OffsetDateTime::now_utc();
+        let DbGuild {
+            language: guild_language,
+            gender: guild_gender,
+            prefix: guild_prefix,
+            ..
+        } = DbGuild::default();
+        let guild_id = self
+            .upsert_guild_not_set(
+                guild_discord_id,
+                guild_language,
+                guild_gender,
+                guild_prefix,
+                now,
+            )
+            .await?;
+        Ok(sqlx::query!(
+            "
+            INSERT INTO channels (
+                discord_id, guild_id, language, gender, prefix, output_format, send_mode,
+                prefix_emotes_enabled, insert_tm, update_tm
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9)
+            ON CONFLICT (discord_id) DO UPDATE SET
+                language = $3, gender = $4, prefix = $5, output_format = $6, send_mode = $7,
+                prefix_emotes_enabled = $8, update_tm = $9
+            RETURNING channel_id
+            ",
+            discord_id.to_db_string(),
+            guild_id,
+            channel.language.map(|language| language as i32),
+            channel.gender.map(|gender| gender as i32),
+            channel.prefix,
+            channel.output_format.map(|format| format as i32),
+            channel.send_mode.map(|mode| mode as i32),
+            channel.prefix_emotes_enabled,
+            now
+        )
+        .fetch_one(&self.0)
+        .await?
+        .channel_id)
+    }
+
     /// target_discord_ids is used in a WHERE IN, so any duplicates are ignored
     #[instrument(level = "debug")]
     pub async fn insert_emote_log(
@@ -235,7 +339,7 @@ impl Db {
         user_discord_id: &UserId,
         guild_discord_id: Option<&GuildId>,
         target_discord_ids: impl Iterator<Item = &UserId> + Debug,
-        emote_id: i32,
+        emote: &EmoteData,
     ) -> Result<(), HandlerError> {
         debug!("inserting emote log");
         let now = time::OffsetDateTime::now_utc();
@@ -263,15 +367,22 @@ impl Db {
             None
         };
 
+        // guild emotes have no xiv id
+        let emote_xiv_id = match emote.guild_emote_id {
+            Some(_) => None,
+            None => Some(emote.id as i32),
+        };
         let emote_log_id = sqlx::query!(
             "
-            INSERT INTO emote_logs (user_id, guild_id, emote_xiv_id, sent_at, insert_tm, update_tm)
-            VALUES ($1, $2, $3, $4, $4, $4)
+            INSERT INTO emote_logs
+                (user_id, guild_id, emote_xiv_id, guild_emote_id, sent_at, insert_tm, update_tm)
+            VALUES ($1, $2, $3, $4, $5, $5, $5)
             RETURNING emote_log_id
             ",
             user_id,
             guild_id,
-            emote_id,
+            emote_xiv_id,
+            emote.guild_emote_id,
             now
         )
         .fetch_one(&self.0)
@@ -301,37 +412,419 @@ impl Db {
         Ok(())
     }
 
-    pub async fn upsert_emotes(
-        &self,
-        emotes: impl Iterator<Item = (i32, String)>,
-    ) -> Result<(), HandlerError> {
+    /// Saves the raw data of every emote in the catalog, then replaces the saved command mapping
+    /// with the one the catalog resolved
+    #[instrument(level = "debug", skip(catalog))]
+    pub async fn upsert_emotes(&self, catalog: &EmoteCatalog) -> Result<(), HandlerError> {
         debug!("upserting emotes");
 
         let now = time::OffsetDateTime::now_utc();
-        for (id, command) in emotes {
+        let mut tx = self.0.begin().await?;
+        for emote in catalog.raw() {
             sqlx::query!(
                 "
-                INSERT INTO emotes (xiv_id, command, insert_tm, update_tm)
-                VALUES ($1, $2, $3, $3)
-                ON CONFLICT (xiv_id) DO UPDATE SET update_tm = $3
+                INSERT INTO emotes (xiv_id, name, category, sort_order, insert_tm, update_tm)
+                VALUES ($1, $2, $3, $4, $5, $5)
+                ON CONFLICT (xiv_id) DO UPDATE SET
+                    name = $2, category = $3, sort_order = $4, update_tm = $5
                 ",
-                id,
-                command,
+                emote.id as i32,
+                emote.name,
+                emote.category.map(|category| category as i32),
+                emote.order as i32,
                 now
             )
-            .execute(&self.0)
+            .execute(&mut tx)
             .await?;
+            for language in DbLanguage::iter() {
+                let texts = language.with_raw_emote_data(emote);
+                sqlx::query!(
+                    "
+                    INSERT INTO emote_messages
+                        (xiv_id, language, name, command, alias, targeted, untargeted, insert_tm, update_tm)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)
+                    ON CONFLICT (xiv_id, language) DO UPDATE SET
+                        name = $3, command = $4, alias = $5, targeted = $6, untargeted = $7,
+                        update_tm = $8
+                    ",
+                    emote.id as i32,
+                    language as i32,
+                    texts.name,
+                    texts.command,
+                    texts.alias,
+                    texts.targeted,
+                    texts.untargeted,
+                    now
+                )
+                .execute(&mut tx)
+                .await?;
+            }
+        }
+
+        debug!("replacing emote commands");
+        sqlx::query!("DELETE FROM emote_commands")
+            .execute(&mut tx)
+            .await?;
+        let commands: Vec<_> = catalog.commands().collect();
+        if !commands.is_empty() {
+            let mut query_builder = QueryBuilder::new(
+                "INSERT INTO emote_commands (command, xiv_id, insert_tm, update_tm) ",
+            );
+            query_builder.push_values(commands, |mut builder, (command, emote)| {
+                builder
+                    .push_bind(command)
+                    .push_bind(emote.id as i32)
+                    .push_bind(now)
+                    .push_bind(now);
+            });
+            query_builder.build().execute(&mut tx).await?;
         }
 
+        tx.commit().await?;
         Ok(())
     }
 
+    /// Rebuilds the raw emote data previously saved by [Db::upsert_emotes]
+    #[instrument(level = "debug")]
+    pub async fn find_raw_emotes(&self) -> Result<Vec<RawEmoteData>, HandlerError> {
+        debug!("finding raw emotes");
+        let emotes = sqlx::query_as!(
+            DbEmote,
+            "
+            SELECT xiv_id, name, category, sort_order, insert_tm, update_tm
+            FROM emotes
+            ORDER BY xiv_id
+            "
+        )
+        .fetch_all(&self.0)
+        .await?;
+        let messages = sqlx::query_as!(
+            DbEmoteMessage,
+            r#"
+            SELECT
+                xiv_id,
+                language as "language: DbLanguage",
+                name,
+                command,
+                alias,
+                targeted,
+                untargeted,
+                insert_tm,
+                update_tm
+            FROM emote_messages
+            "#
+        )
+        .fetch_all(&self.0)
+        .await?;
+
+        let mut raw_emotes: Vec<_> = emotes
+            .into_iter()
+            .map(|emote| RawEmoteData {
+                id: emote.xiv_id as u32,
+                name: emote.name,
+                category: emote.category.map(|category| category as u32),
+                order: emote.sort_order as u32,
+                en: RawEmoteTexts::default(),
+                ja: RawEmoteTexts::default(),
+                de: RawEmoteTexts::default(),
+                fr: RawEmoteTexts::default(),
+            })
+            .collect();
+        let indices: HashMap<_, _> = raw_emotes
+            .iter()
+            .enumerate()
+            .map(|(i, raw)| (raw.id, i))
+            .collect();
+        for message in messages {
+            if let Some(raw) = indices
+                .get(&(message.xiv_id as u32))
+                .and_then(|i| raw_emotes.get_mut(*i))
+            {
+                *message.language.with_raw_emote_data_mut(raw) = RawEmoteTexts {
+                    name: message.name,
+                    command: message.command,
+                    alias: message.alias,
+                    targeted: message.targeted,
+                    untargeted: message.untargeted,
+                };
+            } else {
+                warn!(message.xiv_id, "emote message without matching emote");
+            }
+        }
+
+        Ok(raw_emotes)
+    }
+
+    #[instrument(level = "debug")]
+    pub async fn find_guild_emotes(
+        &self,
+        discord_id: &GuildId,
+    ) -> Result<Vec<DbGuildEmote>, HandlerError> {
+        debug!("finding guild emotes");
+        let res = sqlx::query_as!(
+            DbGuildEmote,
+            "
+            SELECT
+                guild_emotes.guild_emote_id,
+                guild_emotes.guild_id,
+                guild_emotes.command,
+                guild_emotes.targeted,
+                guild_emotes.untargeted,
+                guild_emotes.insert_tm,
+                guild_emotes.update_tm
+            FROM guild_emotes
+            JOIN guilds ON guild_emotes.guild_id = guilds.guild_id
+            WHERE guilds.discord_id = $1
+            ORDER BY guild_emotes.command
+            ",
+            discord_id.to_db_string()
+        )
+        .fetch_all(&self.0)
+        .await?;
+        Ok(res)
+    }
+
+    /// Returns false if the guild already has an emote with the command
+    #[instrument(level = "debug")]
+    pub async fn insert_guild_emote(
+        &self,
+        discord_id: &GuildId,
+        command: &str,
+        targeted: &str,
+        untargeted: &str,
+    ) -> Result<bool, HandlerError> {
+        debug!("inserting guild emote");
+        let now = time::OffsetDateTime::now_utc();
+        let DbGuild {
+            language,
+            gender,
+            prefix,
+            ..
+        } = DbGuild::default();
+        let guild_id = self
+            .upsert_guild_not_set(discord_id, language, gender, prefix, now)
+            .await?;
+        let res = sqlx::query!(
+            "
+            INSERT INTO guild_emotes (guild_id, command, targeted, untargeted, insert_tm, update_tm)
+            VALUES ($1, $2, $3, $4, $5, $5)
+            ON CONFLICT (guild_id, command) DO NOTHING
+            ",
+            guild_id,
+            command,
+            targeted,
+            untargeted,
+            now
+        )
+        .execute(&self.0)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// Returns false if the guild has no emote with the command
+    #[instrument(level = "debug")]
+    pub async fn update_guild_emote(
+        &self,
+        discord_id: &GuildId,
+        command: &str,
+        targeted: &str,
+        untargeted: &str,
+    ) -> Result<bool, HandlerError> {
+        debug!("updating guild emote");
+        let res = sqlx::query!(
+            "
+            UPDATE guild_emotes
+            SET targeted = $3, untargeted = $4, update_tm = $5
+            FROM guilds
+            WHERE guild_emotes.guild_id = guilds.guild_id
+                AND guilds.discord_id = $1
+                AND guild_emotes.command = $2
+            ",
+            discord_id.to_db_string(),
+            command,
+            targeted,
+            untargeted,
+            time::OffsetDateTime::now_utc()
+        )
+        .execute(&self.0)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// Returns false if the guild has no emote with the command
+    #[instrument(level = "debug")]
+    pub async fn delete_guild_emote(
+        &self,
+        discord_id: &GuildId,
+        command: &str,
+    ) -> Result<bool, HandlerError> {
+        debug!("deleting guild emote");
+        let res = sqlx::query!(
+            "
+            DELETE FROM guild_emotes
+            USING guilds
+            WHERE guild_emotes.guild_id = guilds.guild_id
+                AND guilds.discord_id = $1
+                AND guild_emotes.command = $2
+            ",
+            discord_id.to_db_string(),
+            command
+        )
+        .execute(&self.0)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    #[instrument(level = "debug")]
+    pub async fn find_user_emote_aliases(
+        &self,
+        discord_id: &UserId,
+    ) -> Result<Vec<DbUserEmoteAlias>, HandlerError> {
+        debug!("finding user emote aliases");
+        let res = sqlx::query_as!(
+            DbUserEmoteAlias,
+            "
+            SELECT
+                user_emote_aliases.user_emote_alias_id,
+                user_emote_aliases.user_id,
+                user_emote_aliases.alias,
+                user_emote_aliases.command,
+                user_emote_aliases.insert_tm,
+                user_emote_aliases.update_tm
+            FROM user_emote_aliases
+            JOIN users ON user_emote_aliases.user_id = users.user_id
+            WHERE users.discord_id = $1
+            ORDER BY user_emote_aliases.alias
+            ",
+            discord_id.to_db_string()
+        )
+        .fetch_all(&self.0)
+        .await?;
+        Ok(res)
+    }
+
+    /// Returns the command that a user's alias stands for, if they have one
+    #[instrument(level = "debug", ret)]
+    pub async fn find_user_emote_alias(
+        &self,
+        discord_id: &UserId,
+        alias: &str,
+    ) -> Result<Option<String>, HandlerError> {
+        let res = sqlx::query!(
+            "
+            SELECT user_emote_aliases.command
+            FROM user_emote_aliases
+            JOIN users ON user_emote_aliases.user_id = users.user_id
+            WHERE users.discord_id = $1
+                AND user_emote_aliases.alias = $2
+            ",
+            discord_id.to_db_string(),
+            alias
+        )
+        .fetch_optional(&self.0)
+        .await?;
+        Ok(res.map(|rec| rec.command))
+    }
+
+    /// Adds the alias, or points it at the new command if the user already has it
+    #[instrument(level = "debug")]
+    pub async fn upsert_user_emote_alias(
+        &self,
+        discord_id: &UserId,
+        alias: &str,
+        command: &str,
+    ) -> Result<(), HandlerError> {
+        debug!("upserting user emote alias");
+        let now = time::OffsetDateTime::now_utc();
+        let DbUser {
+            language, gender, ..
+        } = DbUser::default();
+        let user_id = self
+            .upsert_user_not_set(discord_id, language, gender, now)
+            .await?;
+        sqlx::query!(
+            "
+            INSERT INTO user_emote_aliases (user_id, alias, command, insert_tm, update_tm)
+            VALUES ($1, $2, $3, $4, $4)
+            ON CONFLICT (user_id, alias) DO UPDATE
+            SET command = EXCLUDED.command, update_tm = EXCLUDED.update_tm
+            ",
+            user_id,
+            alias,
+            command,
+            now
+        )
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns false if the user has no such alias
+    #[instrument(level = "debug")]
+    pub async fn delete_user_emote_alias(
+        &self,
+        discord_id: &UserId,
+        alias: &str,
+    ) -> Result<bool, HandlerError> {
+        debug!("deleting user emote alias");
+        let res = sqlx::query!(
+            "
+            DELETE FROM user_emote_aliases
+            USING users
+            WHERE user_emote_aliases.user_id = users.user_id
+                AND users.discord_id = $1
+                AND user_emote_aliases.alias = $2
+            ",
+            discord_id.to_db_string(),
+            alias
+        )
+        .execute(&self.0)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// Counts the emotes among the ones a user sent most recently
+    #[instrument(level = "debug")]
+    pub async fn find_recent_emote_usage(
+        &self,
+        discord_id: &UserId,
+        limit: i64,
+    ) -> Result<Vec<DbEmoteUsage>, HandlerError> {
+        let res = sqlx::query_as!(
+            DbEmoteUsage,
+            r#"
+            SELECT recent.emote_xiv_id, recent.guild_emote_id, COUNT(*) AS "count!"
+            FROM (
+                SELECT emote_logs.emote_xiv_id, emote_logs.guild_emote_id
+                FROM emote_logs
+                JOIN users ON emote_logs.user_id = users.user_id
+                WHERE users.discord_id = $1
+                ORDER BY emote_logs.sent_at DESC
+                LIMIT $2
+            ) recent
+            GROUP BY recent.emote_xiv_id, recent.guild_emote_id
+            "#,
+            discord_id.to_db_string(),
+            limit
+        )
+        .fetch_all(&self.0)
+        .await?;
+        Ok(res)
+    }
+
     async fn try_add_emote_condition<'a>(
         &self,
         query_builder: &mut QueryBuilder<'a, sqlx::Postgres>,
         em_opt: &'a Option<Arc<EmoteData>>,
     ) -> Result<(), HandlerError> {
         if let Some(em) = em_opt {
+            if let Some(guild_emote_id) = em.guild_emote_id {
+                trace!(guild_emote_id, "adding guild emote cond");
+                query_builder
+                    .push(" AND emote_logs.guild_emote_id = ")
+                    .push_bind(guild_emote_id);
+                return Ok(());
+            }
             let emote_id = sqlx::query!(
                 "
                 SELECT xiv_id FROM emotes WHERE xiv_id = $1
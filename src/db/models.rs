@@ -3,7 +3,10 @@ use xiv_emote_parser::log_message::condition::Gender;
 use strum_macros::{EnumIter, FromRepr};
 use time::OffsetDateTime;
 
-use crate::handler::{ConditionTextPair, EmoteData};
+use crate::{
+    catalog::{RawEmoteData, RawEmoteTexts},
+    handler::{ConditionTextPair, EmoteData},
+};
 
 #[derive(sqlx::Type, Default, Debug, Clone, Copy, PartialEq, Eq, EnumIter, FromRepr)]
 #[repr(i32)]
@@ -11,6 +14,8 @@ pub enum DbLanguage {
     #[default]
     En = 0,
     Ja = 1,
+    De = 2,
+    Fr = 3,
 }
 
 impl DbLanguage {
@@ -18,6 +23,8 @@ impl DbLanguage {
         match self {
             DbLanguage::En => "English",
             DbLanguage::Ja => "Japanese",
+            DbLanguage::De => "German",
+            DbLanguage::Fr => "French",
         }
     }
 
@@ -25,6 +32,26 @@ impl DbLanguage {
         match self {
             DbLanguage::En => "英語",
             DbLanguage::Ja => "日本語",
+            DbLanguage::De => "ドイツ語",
+            DbLanguage::Fr => "フランス語",
+        }
+    }
+
+    pub fn to_string_de(self) -> &'static str {
+        match self {
+            DbLanguage::En => "Englisch",
+            DbLanguage::Ja => "Japanisch",
+            DbLanguage::De => "Deutsch",
+            DbLanguage::Fr => "Französisch",
+        }
+    }
+
+    pub fn to_string_fr(self) -> &'static str {
+        match self {
+            DbLanguage::En => "Anglais",
+            DbLanguage::Ja => "Japonais",
+            DbLanguage::De => "Allemand",
+            DbLanguage::Fr => "Français",
         }
     }
 
@@ -32,13 +59,35 @@ impl DbLanguage {
         match language {
             DbLanguage::En => self.to_string_en(),
             DbLanguage::Ja => self.to_string_ja(),
+            DbLanguage::De => self.to_string_de(),
+            DbLanguage::Fr => self.to_string_fr(),
         }
     }
 
-    pub fn with_emote_data<'a>(&'a self, emote_data: &'a EmoteData) -> &ConditionTextPair {
+    pub fn with_emote_data<'a>(&'a self, emote_data: &'a EmoteData) -> &'a ConditionTextPair {
         match self {
             DbLanguage::En => &emote_data.en,
             DbLanguage::Ja => &emote_data.ja,
+            DbLanguage::De => emote_data.de.as_ref().unwrap_or(&emote_data.en),
+            DbLanguage::Fr => emote_data.fr.as_ref().unwrap_or(&emote_data.en),
+        }
+    }
+
+    pub fn with_raw_emote_data<'a>(&self, raw: &'a RawEmoteData) -> &'a RawEmoteTexts {
+        match self {
+            DbLanguage::En => &raw.en,
+            DbLanguage::Ja => &raw.ja,
+            DbLanguage::De => &raw.de,
+            DbLanguage::Fr => &raw.fr,
+        }
+    }
+
+    pub fn with_raw_emote_data_mut<'a>(&self, raw: &'a mut RawEmoteData) -> &'a mut RawEmoteTexts {
+        match self {
+            DbLanguage::En => &mut raw.en,
+            DbLanguage::Ja => &mut raw.ja,
+            DbLanguage::De => &mut raw.de,
+            DbLanguage::Fr => &mut raw.fr,
         }
     }
 
@@ -70,10 +119,318 @@ impl DbGender {
         }
     }
 
+    pub fn to_string_de(self) -> &'static str {
+        match self {
+            DbGender::M => "Männlich",
+            DbGender::F => "Weiblich",
+        }
+    }
+
+    pub fn to_string_fr(self) -> &'static str {
+        match self {
+            DbGender::M => "Masculin",
+            DbGender::F => "Féminin",
+        }
+    }
+
+    pub fn to_string(self, language: DbLanguage) -> &'static str {
+        match language {
+            DbLanguage::En => self.to_string_en(),
+            DbLanguage::Ja => self.to_string_ja(),
+            DbLanguage::De => self.to_string_de(),
+            DbLanguage::Fr => self.to_string_fr(),
+        }
+    }
+
+    pub fn for_user(self, user: &DbUser) -> &'static str {
+        self.to_string(user.language)
+    }
+}
+
+/// How an emote aimed at several users is sent
+#[derive(sqlx::Type, Default, Debug, Clone, Copy, PartialEq, Eq, EnumIter, FromRepr)]
+#[repr(i32)]
+pub enum DbMultiTargetMode {
+    /// One message naming every target, such as "You hug Alice and Bob."
+    #[default]
+    Together = 0,
+    /// The same message once for each target, unless there are so many that they share one
+    Separately = 1,
+}
+
+impl DbMultiTargetMode {
+    pub fn to_string_en(self) -> &'static str {
+        match self {
+            DbMultiTargetMode::Together => "Several targets share one message",
+            DbMultiTargetMode::Separately => "Each target gets its own message",
+        }
+    }
+
+    pub fn to_string_ja(self) -> &'static str {
+        match self {
+            DbMultiTargetMode::Together => "複数のターゲットを一つのメッセージにまとめる",
+            DbMultiTargetMode::Separately => "ターゲットごとにメッセージを送信する",
+        }
+    }
+
+    pub fn to_string_de(self) -> &'static str {
+        match self {
+            DbMultiTargetMode::Together => "Mehrere Ziele teilen sich eine Nachricht",
+            DbMultiTargetMode::Separately => "Jedes Ziel erhält eine eigene Nachricht",
+        }
+    }
+
+    pub fn to_string_fr(self) -> &'static str {
+        match self {
+            DbMultiTargetMode::Together => "Plusieurs cibles partagent un message",
+            DbMultiTargetMode::Separately => "Chaque cible reçoit son propre message",
+        }
+    }
+
+    pub fn to_string(self, language: DbLanguage) -> &'static str {
+        match language {
+            DbLanguage::En => self.to_string_en(),
+            DbLanguage::Ja => self.to_string_ja(),
+            DbLanguage::De => self.to_string_de(),
+            DbLanguage::Fr => self.to_string_fr(),
+        }
+    }
+
+    pub fn for_user(self, user: &DbUser) -> &'static str {
+        self.to_string(user.language)
+    }
+}
+
+/// Who emote messages are sent as
+#[derive(sqlx::Type, Default, Debug, Clone, Copy, PartialEq, Eq, EnumIter, FromRepr)]
+#[repr(i32)]
+pub enum DbSendMode {
+    #[default]
+    Bot = 0,
+    /// Through a webhook with the sender's name and avatar, falling back to the bot where the
+    /// webhook can't be used
+    Webhook = 1,
+}
+
+impl DbSendMode {
+    pub fn to_string_en(self) -> &'static str {
+        match self {
+            DbSendMode::Bot => "Emotes are sent by the bot",
+            DbSendMode::Webhook => "Emotes are sent as their sender",
+        }
+    }
+
+    pub fn to_string_ja(self) -> &'static str {
+        match self {
+            DbSendMode::Bot => "エモートをボットとして送信する",
+            DbSendMode::Webhook => "エモートを送信者として送信する",
+        }
+    }
+
+    pub fn to_string_de(self) -> &'static str {
+        match self {
+            DbSendMode::Bot => "Emotes werden vom Bot gesendet",
+            DbSendMode::Webhook => "Emotes werden als ihr Absender gesendet",
+        }
+    }
+
+    pub fn to_string_fr(self) -> &'static str {
+        match self {
+            DbSendMode::Bot => "Les emotes sont envoyées par le bot",
+            DbSendMode::Webhook => "Les emotes sont envoyées au nom de leur auteur",
+        }
+    }
+
+    pub fn to_string(self, language: DbLanguage) -> &'static str {
+        match language {
+            DbLanguage::En => self.to_string_en(),
+            DbLanguage::Ja => self.to_string_ja(),
+            DbLanguage::De => self.to_string_de(),
+            DbLanguage::Fr => self.to_string_fr(),
+        }
+    }
+
+    pub fn for_user(self, user: &DbUser) -> &'static str {
+        self.to_string(user.language)
+    }
+}
+
+/// How emote messages are laid out
+#[derive(sqlx::Type, Default, Debug, Clone, Copy, PartialEq, Eq, EnumIter, FromRepr)]
+#[repr(i32)]
+pub enum DbOutputFormat {
+    #[default]
+    Plain = 0,
+    /// An embed titled with the emote's name, with its command in the footer
+    Embed = 1,
+    /// An embed with only the message
+    CompactEmbed = 2,
+    /// A code block colored like the game's chat log
+    GameLog = 3,
+}
+
+impl DbOutputFormat {
+    pub fn to_string_en(self) -> &'static str {
+        match self {
+            DbOutputFormat::Plain => "Plain text",
+            DbOutputFormat::Embed => "Embed",
+            DbOutputFormat::CompactEmbed => "Compact embed",
+            DbOutputFormat::GameLog => "Game log",
+        }
+    }
+
+    pub fn to_string_ja(self) -> &'static str {
+        match self {
+            DbOutputFormat::Plain => "テキスト",
+            DbOutputFormat::Embed => "埋め込み",
+            DbOutputFormat::CompactEmbed => "簡易埋め込み",
+            DbOutputFormat::GameLog => "ゲームログ",
+        }
+    }
+
+    pub fn to_string_de(self) -> &'static str {
+        match self {
+            DbOutputFormat::Plain => "Text",
+            DbOutputFormat::Embed => "Einbettung",
+            DbOutputFormat::CompactEmbed => "Kompakte Einbettung",
+            DbOutputFormat::GameLog => "Spiel-Log",
+        }
+    }
+
+    pub fn to_string_fr(self) -> &'static str {
+        match self {
+            DbOutputFormat::Plain => "Texte",
+            DbOutputFormat::Embed => "Intégration",
+            DbOutputFormat::CompactEmbed => "Intégration compacte",
+            DbOutputFormat::GameLog => "Journal du jeu",
+        }
+    }
+
+    pub fn to_string(self, language: DbLanguage) -> &'static str {
+        match language {
+            DbLanguage::En => self.to_string_en(),
+            DbLanguage::Ja => self.to_string_ja(),
+            DbLanguage::De => self.to_string_de(),
+            DbLanguage::Fr => self.to_string_fr(),
+        }
+    }
+
+    pub fn for_user(self, user: &DbUser) -> &'static str {
+        self.to_string(user.language)
+    }
+}
+
+/// Which channels emotes can be sent in, going by the channels and categories a guild lists
+#[derive(sqlx::Type, Default, Debug, Clone, Copy, PartialEq, Eq, EnumIter, FromRepr)]
+#[repr(i32)]
+pub enum DbChannelFilterMode {
+    /// Everywhere but the listed channels
+    #[default]
+    DenyList = 0,
+    /// Only the listed channels, or everywhere while none are listed, the same as an empty
+    /// [DenyList](DbChannelFilterMode::DenyList)
+    AllowList = 1,
+}
+
+impl DbChannelFilterMode {
+    pub fn to_string_en(self) -> &'static str {
+        match self {
+            DbChannelFilterMode::DenyList => "Emotes work everywhere except the selected channels",
+            DbChannelFilterMode::AllowList => "Emotes only work in the selected channels",
+        }
+    }
+
+    pub fn to_string_ja(self) -> &'static str {
+        match self {
+            DbChannelFilterMode::DenyList => "選択したチャンネル以外でエモートを使用可能",
+            DbChannelFilterMode::AllowList => "選択したチャンネルでのみエモートを使用可能",
+        }
+    }
+
+    pub fn to_string_de(self) -> &'static str {
+        match self {
+            DbChannelFilterMode::DenyList => {
+                "Emotes funktionieren überall außer in den ausgewählten Kanälen"
+            }
+            DbChannelFilterMode::AllowList => {
+                "Emotes funktionieren nur in den ausgewählten Kanälen"
+            }
+        }
+    }
+
+    pub fn to_string_fr(self) -> &'static str {
+        match self {
+            DbChannelFilterMode::DenyList => {
+                "Les emotes fonctionnent partout sauf dans les salons sélectionnés"
+            }
+            DbChannelFilterMode::AllowList => {
+                "Les emotes ne fonctionnent que dans les salons sélectionnés"
+            }
+        }
+    }
+
+    pub fn to_string(self, language: DbLanguage) -> &'static str {
+        match language {
+            DbLanguage::En => self.to_string_en(),
+            DbLanguage::Ja => self.to_string_ja(),
+            DbLanguage::De => self.to_string_de(),
+            DbLanguage::Fr => self.to_string_fr(),
+        }
+    }
+
+    pub fn for_user(self, user: &DbUser) -> &'static str {
+        self.to_string(user.language)
+    }
+}
+
+/// The rows of the game's EmoteCategory sheet
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, EnumIter, FromRepr)]
+#[repr(i32)]
+pub enum DbEmoteCategory {
+    General = 1,
+    Special = 2,
+    Expressions = 3,
+}
+
+impl DbEmoteCategory {
+    pub fn to_string_en(self) -> &'static str {
+        match self {
+            DbEmoteCategory::General => "General",
+            DbEmoteCategory::Special => "Special",
+            DbEmoteCategory::Expressions => "Expressions",
+        }
+    }
+
+    pub fn to_string_ja(self) -> &'static str {
+        match self {
+            DbEmoteCategory::General => "基本",
+            DbEmoteCategory::Special => "特殊",
+            DbEmoteCategory::Expressions => "表情",
+        }
+    }
+
+    pub fn to_string_de(self) -> &'static str {
+        match self {
+            DbEmoteCategory::General => "Allgemein",
+            DbEmoteCategory::Special => "Speziell",
+            DbEmoteCategory::Expressions => "Mimik",
+        }
+    }
+
+    pub fn to_string_fr(self) -> &'static str {
+        match self {
+            DbEmoteCategory::General => "Générales",
+            DbEmoteCategory::Special => "Spéciales",
+            DbEmoteCategory::Expressions => "Expressions",
+        }
+    }
+
     pub fn to_string(self, language: DbLanguage) -> &'static str {
         match language {
             DbLanguage::En => self.to_string_en(),
             DbLanguage::Ja => self.to_string_ja(),
+            DbLanguage::De => self.to_string_de(),
+            DbLanguage::Fr => self.to_string_fr(),
         }
     }
 
@@ -103,6 +460,10 @@ pub struct DbUser {
     pub discord_id: String,
     pub language: DbLanguage,
     pub gender: DbGender,
+    /// `None` uses the guild's
+    pub output_format: Option<DbOutputFormat>,
+    /// Minutes ahead of UTC, for the timestamps of [DbOutputFormat::GameLog]
+    pub utc_offset: i32,
     pub is_set_flg: bool,
     pub insert_tm: time::OffsetDateTime,
     pub update_tm: time::OffsetDateTime,
@@ -114,6 +475,8 @@ impl Default for DbUser {
             discord_id: String::default(),
             language: DbLanguage::default(),
             gender: DbGender::default(),
+            output_format: None,
+            utc_offset: 0,
             is_set_flg: false,
             insert_tm: OffsetDateTime::now_utc(),
             update_tm: OffsetDateTime::now_utc(),
@@ -191,6 +554,14 @@ pub struct DbGuild {
     pub language: DbLanguage,
     pub gender: DbGender,
     pub prefix: String,
+    pub multi_target_mode: DbMultiTargetMode,
+    /// Whether emote messages ping the users they mention
+    pub pings_enabled: bool,
+    pub send_mode: DbSendMode,
+    pub output_format: DbOutputFormat,
+    pub channel_filter_mode: DbChannelFilterMode,
+    /// Ids of the channels and categories the filter lists
+    pub channel_filter: Vec<String>,
     pub is_set_flg: bool,
     pub insert_tm: time::OffsetDateTime,
     pub update_tm: time::OffsetDateTime,
@@ -203,6 +574,12 @@ impl Default for DbGuild {
             language: DbLanguage::default(),
             gender: DbGender::default(),
             prefix: DEFAULT_PREFIX.to_string(),
+            multi_target_mode: DbMultiTargetMode::default(),
+            pings_enabled: true,
+            send_mode: DbSendMode::default(),
+            output_format: DbOutputFormat::default(),
+            channel_filter_mode: DbChannelFilterMode::default(),
+            channel_filter: vec![],
             is_set_flg: false,
             insert_tm: OffsetDateTime::now_utc(),
             update_tm: OffsetDateTime::now_utc(),
@@ -210,11 +587,91 @@ impl Default for DbGuild {
     }
 }
 
+/// Overrides of a guild's settings for one of its channels, where `None` uses the guild's
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(type_name = "channel")]
+pub struct DbChannel {
+    pub discord_id: String,
+    pub language: Option<DbLanguage>,
+    pub gender: Option<DbGender>,
+    pub prefix: Option<String>,
+    pub output_format: Option<DbOutputFormat>,
+    pub send_mode: Option<DbSendMode>,
+    /// Whether messages starting with the prefix are handled, which slash commands aren't affected
+    /// by
+    pub prefix_emotes_enabled: bool,
+}
+
+impl Default for DbChannel {
+    fn default() -> Self {
+        DbChannel {
+            discord_id: String::default(),
+            language: None,
+            gender: None,
+            prefix: None,
+            output_format: None,
+            send_mode: None,
+            prefix_emotes_enabled: true,
+        }
+    }
+}
+
+#[allow(dead_code)]
 #[derive(sqlx::FromRow, Debug, Clone)]
 #[sqlx(type_name = "emote")]
 pub struct DbEmote {
     pub xiv_id: i32,
+    pub name: String,
+    pub category: Option<i32>,
+    pub sort_order: i32,
+    pub insert_tm: time::OffsetDateTime,
+    pub update_tm: time::OffsetDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(type_name = "emote_message")]
+pub struct DbEmoteMessage {
+    pub xiv_id: i32,
+    pub language: DbLanguage,
+    pub name: String,
+    pub command: Option<String>,
+    pub alias: Option<String>,
+    pub targeted: String,
+    pub untargeted: String,
+    pub insert_tm: time::OffsetDateTime,
+    pub update_tm: time::OffsetDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(type_name = "guild_emote")]
+pub struct DbGuildEmote {
+    pub guild_emote_id: i64,
+    pub guild_id: i64,
+    pub command: String,
+    pub targeted: String,
+    pub untargeted: String,
+    pub insert_tm: time::OffsetDateTime,
+    pub update_tm: time::OffsetDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(type_name = "user_emote_alias")]
+pub struct DbUserEmoteAlias {
+    pub user_emote_alias_id: i64,
+    pub user_id: i64,
+    pub alias: String,
     pub command: String,
     pub insert_tm: time::OffsetDateTime,
     pub update_tm: time::OffsetDateTime,
 }
+
+/// How many of a user's recent emotes were a particular emote, which is either built-in or a guild's
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbEmoteUsage {
+    pub emote_xiv_id: Option<i32>,
+    pub guild_emote_id: Option<i64>,
+    pub count: i64,
+}
@@ -1,4 +1,4 @@
-use serenity::model::prelude::{GuildId, RoleId, UserId};
+use serenity::model::prelude::{ChannelId, GuildId, RoleId, UserId};
 
 pub trait DiscordIdExt {
     fn to_db_string(&self) -> String;
@@ -39,3 +39,15 @@ impl DiscordIdExt for RoleId {
         format!("{:0>20}", self.0)
     }
 }
+
+impl DiscordIdExt for &ChannelId {
+    fn to_db_string(&self) -> String {
+        format!("{:0>20}", self.0)
+    }
+}
+
+impl DiscordIdExt for ChannelId {
+    fn to_db_string(&self) -> String {
+        format!("{:0>20}", self.0)
+    }
+}
@@ -1,20 +1,35 @@
+pub mod autocomplete;
+pub mod channel_filter;
 pub mod commands;
 pub mod emotes;
+pub mod game_log;
+pub mod guild_emotes;
+pub mod normalize;
+pub mod send;
+pub mod suggestions;
+pub mod user_aliases;
 
-use std::{collections::HashMap, sync::Arc};
+use arc_swap::ArcSwap;
+use serenity::{
+    model::prelude::{ChannelId, GuildId, UserId},
+    prelude::Context,
+};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 use thiserror::Error;
+use tokio::sync::{Mutex, OnceCell};
 use tracing::*;
 
 use xiv_emote_parser::{
-    log_message::{
-        condition::LogMessageAnswersError,
-        parser::{extract_condition_texts, ConditionTexts},
-        EmoteTextError,
-    },
-    repository::{xivapi, LogMessageRepository, LogMessageRepositoryError},
+    log_message::{condition::LogMessageAnswersError, parser::ConditionTexts, EmoteTextError},
+    repository::LogMessageRepositoryError,
+};
+
+use crate::{
+    catalog::{fallback::FallbackTemplates, CatalogDiff, EmoteCatalog, EmoteSource},
+    db::{models::DbEmoteCategory, Db},
 };
 
-use crate::db::Db;
+use self::{autocomplete::RecentUsage, guild_emotes::GuildEmotes, send::ChannelWebhook};
 
 #[derive(Debug, Clone)]
 pub struct ConditionTextPair {
@@ -26,109 +41,103 @@ pub struct ConditionTextPair {
 pub struct EmoteData {
     pub id: u32,
     pub name: String,
+    pub category: Option<DbEmoteCategory>,
+    /// Where the emote is placed within its category in the game's emote list
+    pub order: u32,
+    /// Whether the messages were generated from the fallback templates because the emote has
+    /// no log messages of its own
+    pub synthetic: bool,
+    /// Set for emotes defined by a guild, which have an id of 0 as they aren't in the game
+    pub guild_emote_id: Option<i64>,
     pub en: ConditionTextPair,
     pub ja: ConditionTextPair,
+    /// German and French messages fall back to English when a source doesn't have them
+    pub de: Option<ConditionTextPair>,
+    pub fr: Option<ConditionTextPair>,
 }
 
 pub struct Handler {
-    pub emotes: HashMap<String, Arc<EmoteData>>,
+    catalog: ArcSwap<EmoteCatalog>,
+    source: Box<dyn EmoteSource>,
+    fallback: FallbackTemplates,
+    // serializes reloads so that each reports its diff against the catalog it replaced
+    reload_lock: Mutex<()>,
+    owners: OnceCell<Vec<UserId>>,
+    /// The webhook emotes are sent through in each channel that they were sent through one in
+    webhooks: Mutex<HashMap<ChannelId, ChannelWebhook>>,
+    /// Each guild's parsed emotes, dropped whenever the guild changes them
+    guild_emotes: Mutex<HashMap<GuildId, Arc<GuildEmotes>>>,
+    /// When each user was last told that prefix emotes are blocked in a channel, along with the
+    /// channel's guild
+    blocked_notices: Mutex<HashMap<(ChannelId, UserId), (GuildId, Instant)>>,
+    /// Each user's recent usage for autocomplete, along with when it was loaded
+    recent_usage: Mutex<HashMap<UserId, (Instant, Arc<RecentUsage>)>>,
     pub db: Db,
 }
 
 impl Handler {
-    #[instrument(level = "trace")]
-    pub fn new(db: Db, api_key: Option<String>) -> Result<Handler, HandlerError> {
-        let query = LogMessageRepository::prep_xivapi_query(api_key);
-        let emotes = LogMessageRepository::load_xivapi(&query)?
-            .into_iter()
-            .try_fold(
-                HashMap::new(),
-                |mut map, result| -> Result<_, HandlerError> {
-                    trace!("processing from xivapi: {:?}", result);
-                    if let xivapi::EmoteData {
-                        log_message_targeted: Some(targeted),
-                        log_message_untargeted: Some(untargeted),
-                        text_command: Some(text_command),
-                        name: Some(name),
-                        id: Some(id),
-                    } = result
-                    {
-                        if [
-                            &targeted.text_en,
-                            &untargeted.text_en,
-                            &targeted.text_ja,
-                            &untargeted.text_ja,
-                        ]
-                        .into_iter()
-                        .any(String::is_empty)
-                        {
-                            trace!(name, id, "skipping due to no messages");
-                            return Ok(map);
-                        }
-                        let en_targeted =
-                            extract_condition_texts(&targeted.text_en).map_err(|e| {
-                                error!(
-                                    name,
-                                    id, "could not extract condition texts for en->targeted"
-                                );
-                                e
-                            })?;
-                        let en_untargeted =
-                            extract_condition_texts(&untargeted.text_en).map_err(|e| {
-                                error!(
-                                    name,
-                                    id, "could not extract condition texts for en->untargeted"
-                                );
-                                e
-                            })?;
-                        let ja_targeted =
-                            extract_condition_texts(&targeted.text_ja).map_err(|e| {
-                                error!(
-                                    name,
-                                    id, "could not extract condition texts for ja->targeted"
-                                );
-                                e
-                            })?;
-                        let ja_untargeted =
-                            extract_condition_texts(&untargeted.text_ja).map_err(|e| {
-                                error!(
-                                    name,
-                                    id, "could not extract condition texts for ja->untargeted"
-                                );
-                                e
-                            })?;
-                        let data = Arc::new(EmoteData {
-                            id,
-                            name,
-                            en: ConditionTextPair {
-                                targeted: en_targeted,
-                                untargeted: en_untargeted,
-                            },
-                            ja: ConditionTextPair {
-                                targeted: ja_targeted,
-                                untargeted: ja_untargeted,
-                            },
-                        });
-                        [
-                            text_command.alias_en,
-                            text_command.alias_ja,
-                            text_command.command_en,
-                            text_command.command_ja,
-                        ]
-                        .into_iter()
-                        .flatten()
-                        .filter(|cmd| !cmd.is_empty())
-                        .for_each(|cmd| {
-                            trace!("{} => {}", cmd, data.name);
-                            map.insert(cmd, data.clone());
-                        })
-                    } else {
-                        trace!("ignoring invalid emote data ({:?})", result);
-                    }
-                    Ok(map)
-                },
-            )?;
-        Ok(Handler { db, emotes })
+    pub fn new(
+        db: Db,
+        source: Box<dyn EmoteSource>,
+        fallback: FallbackTemplates,
+        catalog: EmoteCatalog,
+    ) -> Handler {
+        Handler {
+            catalog: ArcSwap::from_pointee(catalog),
+            source,
+            fallback,
+            reload_lock: Mutex::new(()),
+            owners: OnceCell::new(),
+            webhooks: Mutex::new(HashMap::new()),
+            guild_emotes: Mutex::new(HashMap::new()),
+            blocked_notices: Mutex::new(HashMap::new()),
+            recent_usage: Mutex::new(HashMap::new()),
+            db,
+        }
+    }
+
+    pub async fn from_source(
+        db: Db,
+        source: Box<dyn EmoteSource>,
+        fallback: FallbackTemplates,
+    ) -> Result<Handler, HandlerError> {
+        let catalog = EmoteCatalog::new(source.load().await?, &fallback)?;
+        Ok(Handler::new(db, source, fallback, catalog))
+    }
+
+    /// The current catalog, which stays valid for the caller even if it is reloaded meanwhile
+    pub fn catalog(&self) -> Arc<EmoteCatalog> {
+        self.catalog.load_full()
+    }
+
+    /// Loads the catalog from the source again and swaps it in, leaving the current catalog in
+    /// place if anything fails
+    #[instrument(skip(self))]
+    pub async fn reload_emotes(&self) -> Result<CatalogDiff, HandlerError> {
+        let _guard = self.reload_lock.lock().await;
+        let catalog = EmoteCatalog::new(self.source.load().await?, &self.fallback)?;
+        self.db.upsert_emotes(&catalog).await?;
+        let diff = self.catalog.load().diff(&catalog);
+        self.catalog.store(Arc::new(catalog));
+        info!(?diff, "reloaded emotes");
+        Ok(diff)
+    }
+
+    /// Whether the user owns the bot's application, or is a member of the team that does
+    pub async fn is_owner(&self, context: &Context, user_id: UserId) -> Result<bool, HandlerError> {
+        let owners = self
+            .owners
+            .get_or_try_init(|| async {
+                let info = context.http.get_current_application_info().await?;
+                let mut owners = vec![info.owner.id];
+                if let Some(team) = info.team {
+                    owners.extend(team.members.into_iter().map(|member| member.user.id));
+                }
+                debug!(?owners, "fetched application owners");
+                Ok::<_, HandlerError>(owners)
+            })
+            .await?;
+        Ok(owners.contains(&user_id))
     }
 }
 
@@ -141,17 +150,19 @@ pub enum HandlerError {
     #[error("Command was empty")]
     EmptyCommand,
     #[error("Internal error, could not retrieve emote data")]
-    EmoteData(#[from] LogMessageRepositoryError),
+    EmoteData(#[from] Box<LogMessageRepositoryError>),
     #[error("Internal error, could not build response")]
-    Answers(#[from] LogMessageAnswersError),
+    Answers(#[from] Box<LogMessageAnswersError>),
     #[error("Internal error, could not build response")]
-    Extract(#[from] EmoteTextError),
+    Extract(#[from] Box<EmoteTextError>),
     #[error("Internal error, could not build response")]
     TargetNone,
     #[error("Internal error, could not build response")]
     Db(#[from] sqlx::Error),
     #[error("Failed to send message")]
-    Send(#[from] serenity::Error),
+    Send(#[from] Box<serenity::Error>),
+    #[error("Failed to send message")]
+    SendThroughWebhook(#[from] Box<ureq::Error>),
     #[error("Command can only be used in a server")]
     NotGuild,
     #[error("Timed out or had too many inputs")]
@@ -172,6 +183,46 @@ pub enum HandlerError {
     TypeMapNotFound,
     #[error("Could not set up application commands")]
     CommandSetup,
+    #[error("Internal error, could not load emote data")]
+    Io(#[from] std::io::Error),
+    #[error("Internal error, could not load emote data")]
+    Json(#[from] serde_json::Error),
+    #[error("Internal error, could not load emote data")]
+    Csv(#[from] csv::Error),
+    #[error("Internal error, could not load emote data")]
+    LoadTask(#[from] tokio::task::JoinError),
+    #[error("Internal error, invalid emote sheet ({0})")]
+    InvalidSheet(String),
+    #[error("Internal error, unsupported emote snapshot version ({0})")]
+    SnapshotVersion(u32),
+    #[error("Couldn't send through a webhook in this channel")]
+    WebhookUnavailable,
+}
+
+// the larger errors are boxed so that results of HandlerError stay small, while `?` still converts
+// them directly
+impl From<LogMessageRepositoryError> for HandlerError {
+    fn from(err: LogMessageRepositoryError) -> Self {
+        HandlerError::EmoteData(Box::new(err))
+    }
+}
+
+impl From<LogMessageAnswersError> for HandlerError {
+    fn from(err: LogMessageAnswersError) -> Self {
+        HandlerError::Answers(Box::new(err))
+    }
+}
+
+impl From<EmoteTextError> for HandlerError {
+    fn from(err: EmoteTextError) -> Self {
+        HandlerError::Extract(Box::new(err))
+    }
+}
+
+impl From<serenity::Error> for HandlerError {
+    fn from(err: serenity::Error) -> Self {
+        HandlerError::Send(Box::new(err))
+    }
 }
 
 impl HandlerError {
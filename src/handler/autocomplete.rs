@@ -0,0 +1,201 @@
+//! Suggestions for options that take an emote's command
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serenity::{
+    model::prelude::{
+        interaction::{
+            application_command::CommandDataOption, autocomplete::AutocompleteInteraction,
+        },
+        UserId,
+    },
+    prelude::Context,
+};
+use tracing::*;
+
+use super::{Handler, HandlerError};
+
+/// Most choices Discord accepts in an autocomplete response
+const MAX_CHOICES: usize = 25;
+/// How many of a user's latest emotes decide which emotes they use the most
+const RECENT_EMOTE_LIMIT: i64 = 100;
+/// How long a user's usage is reused for, which only needs to last while they're typing
+const RECENT_USAGE_TTL: Duration = Duration::from_secs(30);
+
+fn focused_option(options: &[CommandDataOption]) -> Option<&CommandDataOption> {
+    options.iter().find_map(|opt| {
+        if opt.focused {
+            Some(opt)
+        } else {
+            focused_option(&opt.options)
+        }
+    })
+}
+
+/// Orders commands that start with the input before ones that only contain it, then by how often
+/// they were used, dropping the ones that don't match at all
+pub fn rank_commands<'a>(
+    input: &str,
+    commands: impl Iterator<Item = (&'a str, i64)>,
+) -> Vec<&'a str> {
+    let input = input.trim();
+    let input = input.strip_prefix('/').unwrap_or(input).to_lowercase();
+    let mut ranked: Vec<_> = commands
+        .filter_map(|(command, usage)| {
+            let lower = command.strip_prefix('/').unwrap_or(command).to_lowercase();
+            let starts = if lower.starts_with(&input) {
+                true
+            } else if lower.contains(&input) {
+                false
+            } else {
+                return None;
+            };
+            Some((Reverse(starts), Reverse(usage), command))
+        })
+        .collect();
+    ranked.sort_unstable();
+    // a command listed more than once keeps its best rank
+    let mut seen = HashSet::new();
+    ranked
+        .into_iter()
+        .map(|(_, _, command)| command)
+        .filter(|command| seen.insert(*command))
+        .take(MAX_CHOICES)
+        .collect()
+}
+
+/// How many times a user used each emote recently, by emote id
+#[derive(Debug, Default)]
+pub struct RecentUsage {
+    xiv: HashMap<u32, i64>,
+    guild: HashMap<i64, i64>,
+}
+
+impl Handler {
+    /// A user's recent usage, which is only looked up again once it's [RECENT_USAGE_TTL] old since
+    /// every key typed into an option asks for choices again
+    async fn recent_usage(&self, user_id: UserId) -> Result<Arc<RecentUsage>, HandlerError> {
+        if let Some((loaded_at, usage)) = self.recent_usage.lock().await.get(&user_id) {
+            if loaded_at.elapsed() < RECENT_USAGE_TTL {
+                return Ok(usage.clone());
+            }
+        }
+        let mut usage = RecentUsage::default();
+        for row in self
+            .db
+            .find_recent_emote_usage(&user_id, RECENT_EMOTE_LIMIT)
+            .await?
+        {
+            match (row.emote_xiv_id, row.guild_emote_id) {
+                (Some(xiv_id), _) => {
+                    usage.xiv.insert(xiv_id as u32, row.count);
+                }
+                (None, Some(guild_emote_id)) => {
+                    usage.guild.insert(guild_emote_id, row.count);
+                }
+                // logs of deleted guild emotes
+                (None, None) => {}
+            }
+        }
+        let usage = Arc::new(usage);
+        let mut cache = self.recent_usage.lock().await;
+        cache.retain(|_, (loaded_at, _)| loaded_at.elapsed() < RECENT_USAGE_TTL);
+        cache.insert(user_id, (Instant::now(), usage.clone()));
+        Ok(usage)
+    }
+
+    #[instrument(skip(self, context, interaction))]
+    pub async fn handle_autocomplete(
+        &self,
+        context: &Context,
+        interaction: &AutocompleteInteraction,
+    ) -> Result<(), HandlerError> {
+        // only emote options have autocomplete enabled
+        let input = focused_option(&interaction.data.options)
+            .and_then(|opt| opt.value.as_ref())
+            .and_then(|value| value.as_str())
+            .unwrap_or_default();
+        debug!(input, "autocompleting emote");
+
+        let usage = self.recent_usage(interaction.user.id).await?;
+
+        let catalog = self.catalog();
+        let guild_emotes = self.guild_emotes(interaction.guild_id).await?;
+        let commands = catalog
+            .commands()
+            .map(|(command, emote)| (command, usage.xiv.get(&emote.id)))
+            .chain(guild_emotes.iter().map(|(command, emote)| {
+                (
+                    command,
+                    emote.guild_emote_id.and_then(|id| usage.guild.get(&id)),
+                )
+            }))
+            .map(|(command, usage)| (command.as_str(), usage.copied().unwrap_or(0)));
+        let choices = rank_commands(input, commands);
+        trace!(?choices, "autocomplete choices");
+
+        interaction
+            .create_autocomplete_response(context, |res| {
+                for choice in choices {
+                    res.add_string_choice(choice, choice);
+                }
+                res
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_prefixes_before_substrings() {
+        let commands = [("/beckon", 0), ("/hug", 0), ("/laugh", 9), ("/huh", 0)];
+        assert_eq!(rank_commands("hu", commands.into_iter()), ["/hug", "/huh"]);
+        assert_eq!(
+            rank_commands("/ug", commands.into_iter()),
+            ["/laugh", "/hug"]
+        );
+        assert!(rank_commands("/x", commands.into_iter()).is_empty());
+        assert_eq!(rank_commands("gh", commands.into_iter()), ["/laugh"]);
+        assert_eq!(
+            rank_commands("/H", [("/cheer", 0), ("/hug", 0), ("/ハグ", 0)].into_iter()),
+            ["/hug", "/cheer"]
+        );
+    }
+
+    #[test]
+    fn breaks_ties_by_usage() {
+        let commands = [("/wave", 1), ("/welcome", 5), ("/wow", 0), ("/bow", 7)];
+        assert_eq!(
+            rank_commands("w", commands.into_iter()),
+            ["/welcome", "/wave", "/wow", "/bow"]
+        );
+        // then alphabetically
+        assert_eq!(
+            rank_commands("", [("/b", 0), ("/a", 0)].into_iter()),
+            ["/a", "/b"]
+        );
+    }
+
+    #[test]
+    fn lists_each_command_once_at_its_best_rank() {
+        let commands = [("/hug", 0), ("/cheer", 3), ("/hug", 5)];
+        assert_eq!(rank_commands("", commands.into_iter()), ["/hug", "/cheer"]);
+    }
+
+    #[test]
+    fn limits_choices() {
+        let commands: Vec<_> = (0..40).map(|i| format!("/emote{:02}", i)).collect();
+        let ranked = rank_commands("emote", commands.iter().map(|c| (c.as_str(), 0)));
+        assert_eq!(ranked.len(), MAX_CHOICES);
+        assert_eq!(ranked[0], "/emote00");
+    }
+}
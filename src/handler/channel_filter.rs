@@ -0,0 +1,224 @@
+//! Keeping emotes to the channels a guild allows them in
+
+use serenity::{
+    model::prelude::{
+        interaction::{
+            application_command::ApplicationCommandInteraction, InteractionResponseType,
+        },
+        ChannelId, GuildId, Message, UserId,
+    },
+    prelude::Context,
+};
+use std::time::{Duration, Instant};
+use tracing::*;
+
+use crate::{
+    db::{
+        models::{DbChannelFilterMode, DbGuild},
+        util::DiscordIdExt,
+    },
+    util::LocalizedString,
+    MessageDbData,
+};
+
+use super::{send::reply_to_author, Handler, HandlerError};
+
+pub const EMOTES_BLOCKED: LocalizedString = LocalizedString {
+    en: "Emotes can't be used in this channel.",
+    ja: "このチャンネルではエモートを使用できません。",
+    de: "In diesem Kanal können keine Emotes verwendet werden.",
+    fr: "Les emotes ne peuvent pas être utilisées dans ce salon.",
+};
+
+/// How long before a user is told again that prefix emotes are blocked in a channel
+const BLOCKED_NOTICE_AGAIN_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A thread, its channel and that channel's category
+const MAX_CHANNEL_DEPTH: usize = 3;
+
+/// The channel along with the channel and category it's in, any of which the filter can list
+async fn channel_and_parents(
+    context: &Context,
+    channel_id: ChannelId,
+) -> Result<Vec<ChannelId>, HandlerError> {
+    let mut channels = vec![channel_id];
+    while channels.len() < MAX_CHANNEL_DEPTH {
+        let current = channels[channels.len() - 1];
+        let parent_id = match context.cache.guild_channel(current) {
+            Some(channel) => channel.parent_id,
+            None => current
+                .to_channel(context)
+                .await?
+                .guild()
+                .and_then(|channel| channel.parent_id),
+        };
+        match parent_id {
+            Some(parent_id) => channels.push(parent_id),
+            None => break,
+        }
+    }
+    Ok(channels)
+}
+
+/// Whether the guild's filter allows emotes in a channel, given along with the channels it's in.
+/// Nothing is filtered while no channels are listed, whichever the mode.
+fn filter_allows(guild: &DbGuild, channels: &[ChannelId]) -> bool {
+    if guild.channel_filter.is_empty() {
+        return true;
+    }
+    let listed = channels
+        .iter()
+        .any(|channel_id| guild.channel_filter.contains(&channel_id.to_db_string()));
+    debug!(?guild.channel_filter_mode, listed, "checked channel filter");
+    match guild.channel_filter_mode {
+        DbChannelFilterMode::DenyList => !listed,
+        DbChannelFilterMode::AllowList => listed,
+    }
+}
+
+impl Handler {
+    /// Whether emotes can be used in the channel, which they always can outside of guilds
+    #[instrument(skip(self, context, message_db_data))]
+    pub async fn emotes_allowed(
+        &self,
+        context: &Context,
+        message_db_data: &MessageDbData<'_>,
+    ) -> Result<bool, HandlerError> {
+        let Some(guild) = message_db_data.guild().await? else {
+            return Ok(true);
+        };
+        // saves looking up the channel's parents when there's nothing to filter
+        if guild.channel_filter.is_empty() {
+            return Ok(true);
+        }
+        let channels = channel_and_parents(context, message_db_data.channel_discord_id()).await?;
+        Ok(filter_allows(&guild, &channels))
+    }
+
+    /// Tells whoever used a prefix emote in a channel emotes are blocked in why nothing happened.
+    /// Other bots may use the same prefix there, so each user is only told once in a while in each
+    /// channel.
+    pub async fn notify_prefix_emote_blocked(
+        &self,
+        context: &Context,
+        msg: &Message,
+        message_db_data: &MessageDbData<'_>,
+    ) -> Result<(), HandlerError> {
+        // only guilds filter channels
+        let Some(guild_id) = message_db_data.guild_discord_id() else {
+            return Ok(());
+        };
+        if !self
+            .take_blocked_notice(guild_id, msg.channel_id, msg.author.id)
+            .await
+        {
+            return Ok(());
+        }
+        let user = message_db_data.determine_user_settings().await?;
+        reply_to_author(context, msg, EMOTES_BLOCKED.for_user(&user)).await?;
+        Ok(())
+    }
+
+    /// Whether a user is due to be told that prefix emotes are blocked in a channel, which they
+    /// then aren't again until [BLOCKED_NOTICE_AGAIN_AFTER] has passed
+    async fn take_blocked_notice(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user_id: UserId,
+    ) -> bool {
+        let mut notices = self.blocked_notices.lock().await;
+        notices.retain(|_, (_, at)| at.elapsed() < BLOCKED_NOTICE_AGAIN_AFTER);
+        notices
+            .insert((channel_id, user_id), (guild_id, Instant::now()))
+            .is_none()
+    }
+
+    /// Forgets who was told that prefix emotes are blocked in a guild's channels, which must be
+    /// done whenever its channel filter changes
+    pub async fn forget_blocked_notices(&self, guild_id: GuildId) {
+        self.blocked_notices
+            .lock()
+            .await
+            .retain(|_, (notice_guild_id, _)| *notice_guild_id != guild_id);
+    }
+
+    /// Checks that an emote command can be used in its channel, and if not tells whoever used it
+    /// why nothing happened
+    pub async fn check_emote_command_allowed(
+        &self,
+        cmd: &ApplicationCommandInteraction,
+        context: &Context,
+        message_db_data: &MessageDbData<'_>,
+    ) -> Result<bool, HandlerError> {
+        if self.emotes_allowed(context, message_db_data).await? {
+            return Ok(true);
+        }
+        info!("emote command used in blocked channel");
+        let user = message_db_data.determine_user_settings().await?;
+        cmd.create_interaction_response(context, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.ephemeral(true).content(EMOTES_BLOCKED.for_user(&user))
+                })
+        })
+        .await?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::fixture;
+
+    use super::*;
+
+    fn guild(channel_filter_mode: DbChannelFilterMode, channel_filter: &[u64]) -> DbGuild {
+        DbGuild {
+            channel_filter_mode,
+            channel_filter: channel_filter
+                .iter()
+                .map(|id| ChannelId(*id).to_db_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn filters_channels() {
+        // a thread in channel 2, which is in category 3
+        let thread = [ChannelId(1), ChannelId(2), ChannelId(3)];
+        let elsewhere = [ChannelId(4), ChannelId(5)];
+        for mode in [
+            DbChannelFilterMode::DenyList,
+            DbChannelFilterMode::AllowList,
+        ] {
+            assert!(filter_allows(&guild(mode, &[]), &thread), "{:?}", mode);
+        }
+        for listed in [1, 2, 3] {
+            let deny = guild(DbChannelFilterMode::DenyList, &[listed]);
+            assert!(!filter_allows(&deny, &thread), "{}", listed);
+            assert!(filter_allows(&deny, &elsewhere), "{}", listed);
+            let allow = guild(DbChannelFilterMode::AllowList, &[listed]);
+            assert!(filter_allows(&allow, &thread), "{}", listed);
+            assert!(!filter_allows(&allow, &elsewhere), "{}", listed);
+        }
+    }
+
+    #[tokio::test]
+    async fn notifies_each_user_once_until_the_filter_changes() {
+        let handler = fixture::handler().await;
+        let notify = |guild, channel, user| {
+            handler.take_blocked_notice(GuildId(guild), ChannelId(channel), UserId(user))
+        };
+        assert!(notify(1, 2, 3).await);
+        assert!(!notify(1, 2, 3).await);
+        assert!(notify(1, 2, 4).await);
+        assert!(notify(1, 5, 3).await);
+
+        handler.forget_blocked_notices(GuildId(6)).await;
+        assert!(!notify(1, 2, 3).await);
+        handler.forget_blocked_notices(GuildId(1)).await;
+        assert!(notify(1, 2, 3).await);
+    }
+}
@@ -22,8 +22,8 @@ impl Handler {
     {
         let mut cmd_map = HashMap::new();
         for cmd in commands {
-            let cmd_enum =
-                T::from_str(&cmd.name).map_err(|_| HandlerError::CommandRegisterUnknown)?;
+            let cmd_enum = T::from_registered(&cmd.name, cmd.kind)
+                .ok_or(HandlerError::CommandRegisterUnknown)?;
             if let Some(prev) = cmd_map.insert(cmd.id, cmd_enum) {
                 warn!(?prev, "overwrote previous command with same id");
             }
@@ -92,7 +92,7 @@ impl Handler {
                 error!("guilds list is not empty, but no guild commands were registered");
                 return Err(HandlerError::CommandSetup);
             }
-            if let Err(err) = stream::iter(guild_commands.into_iter())
+            if let Err(err) = stream::iter(guild_commands)
                 .map(Ok)
                 .try_for_each(|cmds| async {
                     self.save_command_ids::<GuildCommands>(context, cmds.into_iter())
@@ -1,7 +1,8 @@
 use serenity::{
+    builder::CreateAllowedMentions,
     model::prelude::{GuildId, Mention, Message, UserId},
     prelude::{Context, Mentionable},
-    utils::MessageBuilder,
+    utils::{parse_username, MessageBuilder},
 };
 use std::{borrow::Cow, fmt::Debug, sync::Arc};
 use tracing::*;
@@ -11,38 +12,248 @@ use xiv_emote_parser::log_message::{
     LogMessageAnswers,
 };
 
-use crate::{db::models::DbUser, MessageDbData};
+use crate::{
+    db::models::{DbEmoteCategory, DbMultiTargetMode, DbUser},
+    util::LocalizedString,
+    MessageDbData,
+};
 
-use super::{EmoteData, Handler, HandlerError};
+use super::{
+    normalize::normalize_emote_input,
+    send::{reply_to_author, OutgoingEmote},
+    suggestions::{did_you_mean, UNRECOGNIZED_EMOTE},
+    EmoteData, Handler, HandlerError,
+};
 
 // untargeted messages shouldn't reference target character at all, but just in case
 pub const UNTARGETED_TARGET: Character =
     Character::new("Godbert Manderville", Gender::Male, false, false);
 
+/// Most targets sent a message each, past which they share one message so a single emote can't
+/// flood the channel
+const MAX_SEPARATE_TARGETS: usize = 5;
+
+/// Marks emotes with [synthetic](EmoteData::synthetic) messages wherever emotes are listed
+pub const SYNTHETIC_MARKER: &str = "*";
+
+#[derive(Debug, Clone)]
+pub struct EmoteListItem {
+    pub command: String,
+    pub synthetic: bool,
+}
+
+impl EmoteListItem {
+    pub fn label(&self) -> String {
+        if self.synthetic {
+            [&self.command, SYNTHETIC_MARKER].concat()
+        } else {
+            self.command.clone()
+        }
+    }
+}
+
+/// How a target given as text rather than a user is referred to, since it has no settings to use
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainTarget {
+    pub is_npc: bool,
+}
+
+/// The one target an emote message is built for, as text along with the user it names, if any
+#[derive(Debug, Clone, Copy, Default)]
+struct SingleTarget<'t> {
+    text: Option<&'t str>,
+    user: Option<&'t UserId>,
+    plain: PlainTarget,
+}
+
+pub const TARGET_LIST_SEPARATOR: LocalizedString = LocalizedString {
+    en: ", ",
+    ja: "、",
+    de: ", ",
+    fr: ", ",
+};
+pub const TARGET_LIST_LAST_SEPARATOR: LocalizedString = LocalizedString {
+    en: " and ",
+    ja: "と",
+    de: " und ",
+    fr: " et ",
+};
+
+/// Who an emote message may ping: only its author and the users it targets, or no one at all if
+/// the guild turned pings off. Nothing else that ends up in the message, such as `@everyone` or a
+/// role mention typed as a target, ever pings.
+#[derive(Debug, Clone)]
+pub struct EmoteMentions {
+    users: Vec<UserId>,
+    pings_enabled: bool,
+}
+
+impl EmoteMentions {
+    pub async fn new(
+        message_db_data: &MessageDbData<'_>,
+        author: UserId,
+        target_users: &[UserId],
+    ) -> Result<EmoteMentions, HandlerError> {
+        let pings_enabled = message_db_data
+            .guild()
+            .await?
+            .map(|guild| guild.pings_enabled)
+            .unwrap_or(true);
+        let users = if pings_enabled {
+            std::iter::once(author)
+                .chain(target_users.iter().copied())
+                .collect()
+        } else {
+            vec![]
+        };
+        Ok(EmoteMentions {
+            users,
+            pings_enabled,
+        })
+    }
+
+    /// Pings only the author, for replies that may repeat whatever they typed
+    pub fn author_only(author: UserId) -> EmoteMentions {
+        EmoteMentions {
+            users: vec![author],
+            pings_enabled: true,
+        }
+    }
+
+    pub fn apply<'a>(&self, am: &'a mut CreateAllowedMentions) -> &'a mut CreateAllowedMentions {
+        am.empty_parse()
+            .empty_roles()
+            .users(self.users.iter().copied())
+            .replied_user(self.pings_enabled)
+    }
+}
+
+/// Breaks up `@everyone` and `@here` typed as part of a target, which would otherwise look like they
+/// pinged everyone even though they can't
+fn neutralize_mass_mentions(text: &str) -> Cow<'_, str> {
+    if text.contains("@everyone") || text.contains("@here") {
+        Cow::Owned(
+            text.replace("@everyone", "@\u{200B}everyone")
+                .replace("@here", "@\u{200B}here"),
+        )
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// The only user mentioned as a target, who may be the author targeting themselves
+fn single_user<'a>(mut users: impl Iterator<Item = &'a UserId>) -> Option<&'a UserId> {
+    match (users.next(), users.next()) {
+        (Some(user), None) => Some(user),
+        _ => None,
+    }
+}
+
+/// Splits a target into the words that may each be a mention. Japanese lists aren't spaced, so
+/// their separators split words too.
+fn target_words(target: &str) -> impl Iterator<Item = &str> {
+    target
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '、' | 'と' | '&'))
+        .filter(|word| !word.is_empty())
+}
+
+/// Every user mentioned in a target typed as text, in the order they're mentioned. These come from
+/// the text itself rather than the message's mentions, which also include the author of a message
+/// that's replied to.
+pub fn users_in_target(target: &str) -> Vec<UserId> {
+    let mut users = Vec::new();
+    for user in target_words(target).filter_map(|word| parse_username(word).map(UserId)) {
+        if !users.contains(&user) {
+            users.push(user);
+        }
+    }
+    users
+}
+
+/// The users named by a target that is nothing but their mentions, such as `@Alice and @Bob`, in
+/// the order they're named. Anything else in the target, such as a mention of someone who isn't in
+/// `users` or other text, means it's a single target.
+pub fn listed_users(target: &str, users: &[UserId]) -> Option<Vec<UserId>> {
+    let is_conjunction = |word: &str| {
+        let word = word.to_lowercase();
+        let LocalizedString { en, ja, de, fr } = TARGET_LIST_LAST_SEPARATOR;
+        [en, ja, de, fr].iter().any(|and| and.trim() == word)
+    };
+    let mut listed = Vec::new();
+    for word in target_words(target) {
+        match parse_username(word).map(UserId) {
+            Some(user) if users.contains(&user) => {
+                if !listed.contains(&user) {
+                    listed.push(user);
+                }
+            }
+            None if is_conjunction(word) => {}
+            _ => return None,
+        }
+    }
+    Some(listed)
+}
+
+/// Names every target in one phrase, such as "@Alice, @Bob and @Carol"
+fn join_targets(targets: &[String], user: &DbUser) -> String {
+    match targets {
+        [] => String::new(),
+        [target] => target.clone(),
+        [rest @ .., last] => [
+            rest.join(TARGET_LIST_SEPARATOR.for_user(user)).as_str(),
+            TARGET_LIST_LAST_SEPARATOR.for_user(user),
+            last,
+        ]
+        .concat(),
+    }
+}
+
+fn capitalize_first(s: Cow<'_, str>) -> Cow<'_, str> {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) if first.is_lowercase() => {
+            Cow::Owned(first.to_uppercase().chain(chars).collect())
+        }
+        _ => s,
+    }
+}
+
 impl Handler {
-    pub fn emote_list_by_id(&self) -> impl Iterator<Item = &String> {
-        let mut values: Vec<_> = self.emotes.iter().collect();
-        values.sort_unstable_by(|(_, v1), (_, v2)| v1.id.cmp(&v2.id));
-        values.into_iter().map(|(k, _)| k)
+    pub fn emote_list_by_id(&self) -> Vec<String> {
+        self.catalog()
+            .commands_by_id()
+            .into_iter()
+            .map(|(command, _)| command.clone())
+            .collect()
+    }
+
+    /// Every command and alias, either ordered by id or in the order the game lists a category
+    pub fn emote_list_in_category(&self, category: Option<DbEmoteCategory>) -> Vec<EmoteListItem> {
+        let catalog = self.catalog();
+        let commands = match category {
+            Some(category) => catalog.commands_in_category(category),
+            None => catalog.commands_by_id(),
+        };
+        commands
+            .into_iter()
+            .map(|(command, emote)| EmoteListItem {
+                command: command.clone(),
+                synthetic: emote.synthetic,
+            })
+            .collect()
     }
 
     pub async fn upsert_emotes(&self) -> Result<(), HandlerError> {
-        self.db
-            .upsert_emotes(
-                self.emotes
-                    .values()
-                    .map(|data| (data.id as i32, data.name.clone())),
-            )
-            .await?;
+        self.db.upsert_emotes(&self.catalog()).await?;
         Ok(())
     }
 
     pub fn contains_emote(&self, emote: &str) -> bool {
-        self.emotes.contains_key(emote)
+        self.catalog().contains(emote)
     }
 
-    pub fn get_emote_data(&self, emote: &str) -> Option<&Arc<EmoteData>> {
-        self.emotes.get(emote)
+    pub fn get_emote_data(&self, emote: &str) -> Option<Arc<EmoteData>> {
+        self.catalog().get(emote).cloned()
     }
 
     #[instrument(skip(self, context, msg))]
@@ -54,53 +265,183 @@ impl Handler {
         message_db_data: &MessageDbData<'a>,
     ) -> Result<(), HandlerError> {
         let (original_emote, mention) = mparts.split_first().ok_or(HandlerError::EmptyCommand)?;
-        let emote = ["/", original_emote].concat();
-        let mention = if mention.is_empty() {
-            None
-        } else {
-            Some(mention.join(" "))
+        // the guild prefix was already stripped from the message
+        let emote = normalize_emote_input(original_emote, "").ok_or(HandlerError::EmptyCommand)?;
+        // replying to a message without naming a target targets whoever sent that message
+        let (mention, target_users): (_, Vec<_>) = match &msg.referenced_message {
+            _ if !mention.is_empty() => {
+                let mention = mention.join(" ");
+                let target_users = users_in_target(&mention);
+                (Some(mention), target_users)
+            }
+            Some(referenced) => (
+                Some(referenced.author.mention().to_string()),
+                vec![referenced.author.id],
+            ),
+            None => (None, vec![]),
         };
 
-        debug!(emote, ?mention, "parsed message");
+        debug!(emote, ?mention, ?target_users, "parsed message");
 
-        let emote = self.get_emote_data(&emote);
+        let emote = self
+            .find_emote_for_user(&emote, &msg.author.id, msg.guild_id)
+            .await?;
 
         match (emote, mention) {
             (Some(emote), mention_opt) => {
-                let body = self
-                    .build_emote_message(
-                        emote,
+                let bodies = self
+                    .build_emote_messages(
+                        &emote,
                         message_db_data,
                         &msg.author,
                         mention_opt.as_ref().map(AsRef::as_ref),
+                        &target_users,
+                        PlainTarget::default(),
                     )
                     .await?;
-                debug!(body, "emote result");
-                msg.reply(context, body).await?;
+                debug!(?bodies, "emote result");
+                let mentions =
+                    EmoteMentions::new(message_db_data, msg.author.id, &target_users).await?;
+                self.send_emote(
+                    context,
+                    message_db_data,
+                    OutgoingEmote {
+                        channel_id: msg.channel_id,
+                        author: &msg.author,
+                        emote: &emote,
+                        bodies,
+                        mentions,
+                        reply_to: Some(msg),
+                    },
+                )
+                .await?;
                 self.log_emote(
                     &msg.author.id,
                     msg.guild_id.as_ref(),
-                    msg.mentions.iter().map(|u| &u.id),
-                    emote,
+                    target_users.iter(),
+                    &emote,
                 )
                 .await?;
                 Ok(())
             }
-            (_, _) => {
+            (None, _) => {
                 warn!("could not find matching emote");
-                Err(HandlerError::UnrecognizedEmote(original_emote.to_string()))
+                let user = message_db_data.determine_user_settings().await?;
+                let suggestions = self.suggest_emotes(original_emote, msg.guild_id).await?;
+                let mut body = format!("{} {}", UNRECOGNIZED_EMOTE.for_user(&user), original_emote);
+                if let Some(did_you_mean) = did_you_mean(&suggestions, &user) {
+                    body.push('\n');
+                    body.push_str(&did_you_mean);
+                }
+                reply_to_author(context, msg, body).await?;
+                Ok(())
             }
         }
     }
 
+    /// Builds the messages for an emote, where `target_users` are the users mentioned in `target`.
+    /// A target listing several users is sent according to the guild's [DbMultiTargetMode], which
+    /// is the only way there's more than one message.
     #[instrument(skip(self))]
-    pub async fn build_emote_message<'a, T: Mentionable + Debug>(
+    pub async fn build_emote_messages<'a, T: Mentionable + Debug>(
         &self,
         emote: &Arc<EmoteData>,
         message_db_data: &MessageDbData<'a>,
         author_mentionable: &T,
         target: Option<&str>,
+        target_users: &[UserId],
+        plain_target: PlainTarget,
+    ) -> Result<Vec<String>, HandlerError> {
+        let target = target.map(neutralize_mass_mentions);
+        let target = target.as_deref();
+        let listed = target
+            .and_then(|target| listed_users(target, target_users))
+            .filter(|listed| listed.len() > 1);
+        let Some(listed) = listed else {
+            let body = self
+                .build_single_target_message(
+                    emote,
+                    message_db_data,
+                    author_mentionable,
+                    SingleTarget {
+                        text: target,
+                        user: single_user(target_users.iter()),
+                        plain: plain_target,
+                    },
+                )
+                .await?;
+            return Ok(vec![body]);
+        };
+
+        let mode = message_db_data
+            .guild()
+            .await?
+            .map(|guild| guild.multi_target_mode)
+            .unwrap_or_default();
+        let mode = match mode {
+            DbMultiTargetMode::Separately if listed.len() > MAX_SEPARATE_TARGETS => {
+                DbMultiTargetMode::Together
+            }
+            mode => mode,
+        };
+        debug!(?listed, ?mode, "emote has several targets");
+        let mentions: Vec<_> = listed
+            .iter()
+            .map(|user| user.mention().to_string())
+            .collect();
+        match mode {
+            DbMultiTargetMode::Together => {
+                let user = message_db_data.determine_user_settings().await?;
+                let body = self
+                    .build_single_target_message(
+                        emote,
+                        message_db_data,
+                        author_mentionable,
+                        SingleTarget {
+                            text: Some(&join_targets(&mentions, &user)),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                Ok(vec![body])
+            }
+            // one message each, since a line per target could easily add up to more than fits in
+            // a single message
+            DbMultiTargetMode::Separately => {
+                let mut lines = Vec::with_capacity(listed.len());
+                for (user, mention) in listed.iter().zip(&mentions) {
+                    lines.push(
+                        self.build_single_target_message(
+                            emote,
+                            message_db_data,
+                            author_mentionable,
+                            SingleTarget {
+                                text: Some(mention),
+                                user: Some(user),
+                                ..Default::default()
+                            },
+                        )
+                        .await?,
+                    );
+                }
+                Ok(lines)
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn build_single_target_message<'a, T: Mentionable + Debug>(
+        &self,
+        emote: &Arc<EmoteData>,
+        message_db_data: &MessageDbData<'a>,
+        author_mentionable: &T,
+        target: SingleTarget<'_>,
     ) -> Result<String, HandlerError> {
+        let SingleTarget {
+            text: target,
+            user: target_user,
+            plain: plain_target,
+        } = target;
         enum BuilderAction<'a> {
             Mention(Mention),
             Text(Cow<'a, str>),
@@ -129,20 +470,31 @@ impl Handler {
             localized_messages.untargeted.clone()
         };
 
+        // an author who targets themselves is both sides of the message, which the game words with
+        // its own self conditions rather than naming them twice
+        let is_self_target = target_user == Some(&message_db_data.user_discord_id());
         let origin_char = Character::new_from_string(
             author_mention.mention().to_string(),
             gender.into(),
             true,
-            false,
+            is_self_target,
         );
-        let target_char = target
-            .as_ref()
-            .map(|t| Character::new_from_string(t.to_string(), Gender::Male, true, false))
-            .unwrap_or(UNTARGETED_TARGET);
+        let target_char = if is_self_target {
+            origin_char.clone()
+        } else {
+            let target_is_pc = target_user.is_some() || !plain_target.is_npc;
+            target
+                .as_ref()
+                .map(|t| {
+                    // log messages only ever depend on the origin's gender, so the target's doesn't matter
+                    Character::new_from_string(t.to_string(), Gender::Male, target_is_pc, false)
+                })
+                .unwrap_or(UNTARGETED_TARGET)
+        };
         debug!(emote.name, ?origin_char, ?target_char, "building emote");
         let answers = LogMessageAnswers::new(origin_char, target_char)?;
 
-        Ok(condition_texts
+        let message = condition_texts
             .into_map_texts(&answers, move |text| match text {
                 Text::Dynamic(d) => match d {
                     DynamicText::NpcOriginName
@@ -157,17 +509,19 @@ impl Handler {
                 },
                 Text::Static(s) => Ok(BuilderAction::Text(Cow::Owned(s))),
             })
-            .fold(Ok(MessageBuilder::new()), |builder_res, action_res| match (
-                builder_res,
-                action_res,
-            ) {
-                (Err(e), _) | (_, Err(e)) => Err(e),
-                (Ok(mut builder), Ok(action)) => {
-                    action.do_action(&mut builder);
-                    Ok(builder)
+            .enumerate()
+            .try_fold(MessageBuilder::new(), |mut builder, (i, action_res)| {
+                match action_res? {
+                    // "you" starts a self-targeted message, which the game capitalizes
+                    BuilderAction::Text(s) if i == 0 && is_self_target => {
+                        BuilderAction::Text(capitalize_first(s)).do_action(&mut builder)
+                    }
+                    action => action.do_action(&mut builder),
                 }
+                Ok::<_, HandlerError>(builder)
             })?
-            .build())
+            .build();
+        Ok(message)
     }
 
     #[instrument(skip(self))]
@@ -178,13 +532,364 @@ impl Handler {
         target_discord_ids: impl Iterator<Item = &UserId> + Debug,
         messages: &Arc<EmoteData>,
     ) -> Result<(), HandlerError> {
-        if let Ok(id) = messages.id.try_into() {
-            self.db
-                .insert_emote_log(user_discord_id, guild_discord_id, target_discord_ids, id)
-                .await?;
-        } else {
-            error!(messages.id, "could not convert emote id to i32");
-        };
+        self.db
+            .insert_emote_log(
+                user_discord_id,
+                guild_discord_id,
+                target_discord_ids,
+                messages,
+            )
+            .await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        catalog::fixture,
+        db::models::{DbGuild, DbLanguage},
+        handler::guild_emotes::parse_guild_emote,
+    };
+
+    use super::*;
+
+    const AUTHOR: UserId = UserId(1);
+    const OTHER: UserId = UserId(2);
+
+    fn user(language: DbLanguage) -> DbUser {
+        DbUser {
+            language,
+            ..Default::default()
+        }
+    }
+
+    /// The emote as sent for a prefix command such as `!hug @someone`, whose target users are
+    /// taken from what's typed after the command
+    async fn render(language: DbLanguage, command: &str, target: Option<UserId>) -> String {
+        let handler = fixture::handler().await;
+        let mdd = MessageDbData::with_settings(&handler.db, AUTHOR, Some(user(language)), None);
+        let emote = handler.get_emote_data(command).unwrap();
+        let target = target.map(|user| user.mention().to_string());
+        let target_users = target.as_deref().map(users_in_target).unwrap_or_default();
+        let messages = handler
+            .build_emote_messages(
+                &emote,
+                &mdd,
+                &AUTHOR,
+                target.as_deref(),
+                &target_users,
+                PlainTarget::default(),
+            )
+            .await
+            .unwrap();
+        let [message]: [String; 1] = messages.try_into().unwrap();
+        message
+    }
+
+    #[test]
+    fn finds_users_in_targets() {
+        assert_eq!(users_in_target("<@2>"), [UserId(2)]);
+        assert_eq!(
+            users_in_target("<@!3>, <@2> and <@3>"),
+            [UserId(3), UserId(2)]
+        );
+        assert_eq!(users_in_target("<@2>&<@4>"), [UserId(2), UserId(4)]);
+        assert!(users_in_target("Bob").is_empty());
+        // neither role nor channel mentions are users
+        assert!(users_in_target("<@&5> <#6>").is_empty());
+    }
+
+    #[tokio::test]
+    async fn renders_english() {
+        // a player target is named even when it's the author, who the game calls "you" otherwise
+        let cases = [
+            ("/hug", None, "<@1> hugs the air."),
+            ("/hug", Some(OTHER), "<@1> gives <@2> a big hug."),
+            ("/hug", Some(AUTHOR), "You give <@1> a big hug."),
+            ("/surprised", None, "<@1> looks surprised!"),
+            ("/surprised", Some(OTHER), "<@1> looks at <@2> in surprise."),
+            ("/surprised", Some(AUTHOR), "You look at <@1> in surprise."),
+        ];
+        for (command, target, expected) in cases {
+            assert_eq!(
+                render(DbLanguage::En, command, target).await,
+                expected,
+                "{} {:?}",
+                command,
+                target
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn renders_japanese() {
+        // Japanese messages have no self conditions, so they read the same either way
+        let cases = [
+            ("/hug", None, "<@1>はハグした。"),
+            ("/hug", Some(OTHER), "<@1>は<@2>をハグした。"),
+            ("/hug", Some(AUTHOR), "<@1>は<@1>をハグした。"),
+            ("/surprised", None, "<@1>はおどろいた。"),
+            ("/surprised", Some(OTHER), "<@1>は<@2>におどろいた。"),
+            ("/surprised", Some(AUTHOR), "<@1>は<@1>におどろいた。"),
+        ];
+        for (command, target, expected) in cases {
+            assert_eq!(
+                render(DbLanguage::Ja, command, target).await,
+                expected,
+                "{} {:?}",
+                command,
+                target
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn only_capitalizes_self_targets() {
+        let handler = fixture::handler().await;
+        let mdd =
+            MessageDbData::with_settings(&handler.db, AUTHOR, Some(user(DbLanguage::En)), None);
+        let origin =
+            "<If(PlayerParameter(7))><Sheet(ObjStr,PlayerParameter(7),0)/><Else/>ObjectParameter(2)</If>";
+        let target =
+            "<If(PlayerParameter(8))><Sheet(ObjStr,PlayerParameter(8),0)/><Else/>ObjectParameter(3)</If>";
+        let emote = Arc::new(
+            parse_guild_emote(
+                1,
+                "/oops",
+                &format!("oh no, {} tripped over {}.", origin, target),
+                &format!("oh no, {} tripped.", origin),
+            )
+            .unwrap(),
+        );
+        for (target, expected) in [
+            (None, "oh no, <@1> tripped."),
+            (Some(OTHER), "oh no, <@1> tripped over <@2>."),
+            (Some(AUTHOR), "Oh no, <@1> tripped over <@1>."),
+        ] {
+            let target_text = target.map(|user| user.mention().to_string());
+            let target_users: Vec<_> = target.into_iter().collect();
+            let messages = handler
+                .build_emote_messages(
+                    &emote,
+                    &mdd,
+                    &AUTHOR,
+                    target_text.as_deref(),
+                    &target_users,
+                    PlainTarget::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(messages, [expected], "{:?}", target);
+        }
+    }
+
+    #[test]
+    fn lists_users() {
+        let users = [UserId(2), UserId(3), UserId(4)];
+        assert_eq!(listed_users("<@2>", &users), Some(vec![UserId(2)]));
+        assert_eq!(
+            listed_users("<@2>, <@!3> and <@4>", &users),
+            Some(vec![UserId(2), UserId(3), UserId(4)])
+        );
+        assert_eq!(
+            listed_users("<@3>、<@4>と<@2>", &users),
+            Some(vec![UserId(3), UserId(4), UserId(2)])
+        );
+        assert_eq!(
+            listed_users("<@3> UND <@2> & <@3>", &users),
+            Some(vec![UserId(3), UserId(2)])
+        );
+        assert_eq!(
+            listed_users("<@2> et <@4>", &users),
+            Some(vec![UserId(2), UserId(4)])
+        );
+        // anything other than a mention of a target makes it a single target
+        assert_eq!(listed_users("<@2> and Bob", &users), None);
+        assert_eq!(listed_users("<@2> and <@5>", &users), None);
+        assert_eq!(listed_users("<@2> and <@&3>", &users), None);
+        assert_eq!(listed_users("", &users), Some(vec![]));
+    }
+
+    #[test]
+    fn joins_targets() {
+        let targets = |n: usize| -> Vec<_> {
+            ["A", "B", "C"][..n]
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+        let en = user(DbLanguage::En);
+        assert_eq!(join_targets(&targets(0), &en), "");
+        assert_eq!(join_targets(&targets(1), &en), "A");
+        assert_eq!(join_targets(&targets(2), &en), "A and B");
+        assert_eq!(join_targets(&targets(3), &en), "A, B and C");
+        assert_eq!(join_targets(&targets(3), &user(DbLanguage::Ja)), "A、BとC");
+        assert_eq!(
+            join_targets(&targets(3), &user(DbLanguage::De)),
+            "A, B und C"
+        );
+        assert_eq!(join_targets(&targets(2), &user(DbLanguage::Fr)), "A et B");
+    }
+
+    #[tokio::test]
+    async fn sends_several_targets_by_guild_mode() {
+        let handler = fixture::handler().await;
+        let emote = handler.get_emote_data("/hug").unwrap();
+        let target_users = [UserId(2), UserId(3), UserId(4)];
+        let messages = |multi_target_mode| {
+            let handler = &handler;
+            let emote = &emote;
+            async move {
+                let guild = DbGuild {
+                    multi_target_mode,
+                    ..Default::default()
+                };
+                let mdd = MessageDbData::with_settings(
+                    &handler.db,
+                    AUTHOR,
+                    Some(user(DbLanguage::En)),
+                    Some((GuildId(1), guild)),
+                );
+                handler
+                    .build_emote_messages(
+                        emote,
+                        &mdd,
+                        &AUTHOR,
+                        Some("<@2>, <@3> and <@4>"),
+                        &target_users,
+                        PlainTarget::default(),
+                    )
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(
+            messages(DbMultiTargetMode::Together).await,
+            ["<@1> gives <@2>, <@3> and <@4> a big hug."]
+        );
+        assert_eq!(
+            messages(DbMultiTargetMode::Separately).await,
+            [
+                "<@1> gives <@2> a big hug.",
+                "<@1> gives <@3> a big hug.",
+                "<@1> gives <@4> a big hug."
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn shares_one_message_past_the_separate_limit() {
+        let handler = fixture::handler().await;
+        let emote = handler.get_emote_data("/hug").unwrap();
+        let guild = DbGuild {
+            multi_target_mode: DbMultiTargetMode::Separately,
+            ..Default::default()
+        };
+        let mdd = MessageDbData::with_settings(
+            &handler.db,
+            AUTHOR,
+            Some(user(DbLanguage::En)),
+            Some((GuildId(1), guild)),
+        );
+        let target_users: Vec<_> = (2..=MAX_SEPARATE_TARGETS as u64 + 2).map(UserId).collect();
+        let target = target_users
+            .iter()
+            .map(|user| user.mention().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let messages = handler
+            .build_emote_messages(
+                &emote,
+                &mdd,
+                &AUTHOR,
+                Some(&target),
+                &target_users,
+                PlainTarget::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            messages,
+            ["<@1> gives <@2>, <@3>, <@4>, <@5>, <@6> and <@7> a big hug."]
+        );
+    }
+
+    /// What the mentions allow, as sent to Discord
+    fn allowed(mentions: &EmoteMentions) -> serde_json::Value {
+        let mut am = CreateAllowedMentions::default();
+        mentions.apply(&mut am);
+        serde_json::to_value(am.0).unwrap()
+    }
+
+    #[test]
+    fn neutralizes_mass_mentions() {
+        assert_eq!(
+            neutralize_mass_mentions("@everyone and @here"),
+            "@\u{200B}everyone and @\u{200B}here"
+        );
+        assert!(matches!(
+            neutralize_mass_mentions("<@2> and <@&3>"),
+            Cow::Borrowed("<@2> and <@&3>")
+        ));
+    }
+
+    #[tokio::test]
+    async fn targets_are_only_text() {
+        let handler = fixture::handler().await;
+        let mdd =
+            MessageDbData::with_settings(&handler.db, AUTHOR, Some(user(DbLanguage::En)), None);
+        let emote = handler.get_emote_data("/hug").unwrap();
+        for (target, expected) in [
+            ("@everyone", "<@1> gives @\u{200B}everyone a big hug."),
+            ("@here", "<@1> gives @\u{200B}here a big hug."),
+            // left as is, as the message's allowed mentions keep it from pinging
+            ("<@&3>", "<@1> gives <@&3> a big hug."),
+        ] {
+            let messages = handler
+                .build_emote_messages(
+                    &emote,
+                    &mdd,
+                    &AUTHOR,
+                    Some(target),
+                    &[],
+                    PlainTarget::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(messages, [expected], "{}", target);
+        }
+    }
+
+    #[tokio::test]
+    async fn pings_only_the_author_and_targets() {
+        let handler = fixture::handler().await;
+        let mentions = |pings_enabled| {
+            let db = &handler.db;
+            async move {
+                let guild = DbGuild {
+                    pings_enabled,
+                    ..Default::default()
+                };
+                let mdd = MessageDbData::with_settings(db, AUTHOR, None, Some((GuildId(1), guild)));
+                EmoteMentions::new(&mdd, AUTHOR, &[OTHER]).await.unwrap()
+            }
+        };
+        assert_eq!(
+            allowed(&mentions(true).await),
+            serde_json::json!({"parse": [], "roles": [], "users": ["1", "2"], "replied_user": true})
+        );
+        assert_eq!(
+            allowed(&mentions(false).await),
+            serde_json::json!({"parse": [], "roles": [], "users": [], "replied_user": false})
+        );
+        // pings are on outside of guilds
+        let mdd = MessageDbData::with_settings(&handler.db, AUTHOR, None, None);
+        let dm = EmoteMentions::new(&mdd, AUTHOR, &[OTHER]).await.unwrap();
+        assert_eq!(allowed(&dm), allowed(&mentions(true).await));
+        assert_eq!(
+            allowed(&EmoteMentions::author_only(AUTHOR)),
+            serde_json::json!({"parse": [], "roles": [], "users": ["1"], "replied_user": true})
+        );
+    }
+}
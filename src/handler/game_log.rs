@@ -0,0 +1,221 @@
+//! The [game log](crate::db::models::DbOutputFormat::GameLog) output format, which mimics the
+//! game's chat log with an `ansi` code block
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serenity::model::prelude::UserId;
+use std::{borrow::Cow, collections::HashMap};
+use time::{OffsetDateTime, UtcOffset};
+
+const RESET: &str = "\u{1b}[0m";
+const TIMESTAMP_COLOR: &str = "\u{1b}[2;30m";
+const ORIGIN_NAME_COLOR: &str = "\u{1b}[2;36m";
+/// The closest of the colors Discord renders to the game's emote text
+const EMOTE_COLOR: &str = "\u{1b}[2;33m";
+
+/// Offsets beyond these aren't used anywhere
+const MIN_UTC_OFFSET: i32 = -12 * 60;
+const MAX_UTC_OFFSET: i32 = 14 * 60;
+
+static USER_MENTION: Lazy<Regex> = Lazy::new(|| Regex::new(r"<@!?(\d+)>").unwrap());
+
+/// Parses an offset from UTC in minutes, such as `+9`, `-05:30` or `UTC+1`
+pub fn parse_utc_offset(input: &str) -> Option<i32> {
+    let input = input.trim();
+    let input = ["UTC", "utc", "GMT", "gmt"]
+        .iter()
+        .find_map(|tz| input.strip_prefix(tz))
+        .unwrap_or(input)
+        .trim();
+    if input.is_empty() {
+        return Some(0);
+    }
+    let (sign, input) = match input.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, input.strip_prefix('+').unwrap_or(input)),
+    };
+    let (hours, minutes) = input.split_once(':').unwrap_or((input, "0"));
+    // the sign was already taken off
+    if !(hours.chars().all(|c| c.is_ascii_digit()) && minutes.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if !(0..60).contains(&minutes) {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+        .filter(|offset| (MIN_UTC_OFFSET..=MAX_UTC_OFFSET).contains(offset))
+}
+
+/// Formats an offset from UTC in minutes, such as `UTC+09:00`
+pub fn format_utc_offset(offset: i32) -> String {
+    if offset == 0 {
+        return "UTC".to_string();
+    }
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("UTC{}{:02}:{:02}", sign, offset / 60, offset % 60)
+}
+
+/// Renders an emote message as a chat log, with each line timestamped at `now` in the sender's
+/// timezone. User mentions are replaced with `names`, since they wouldn't be rendered inside the
+/// code block, and the sender's name is colored like the game colors player names.
+pub fn game_log_block(
+    body: &str,
+    author: UserId,
+    names: &HashMap<UserId, String>,
+    utc_offset: i32,
+    now: OffsetDateTime,
+) -> String {
+    let offset = UtcOffset::from_whole_seconds(utc_offset * 60).unwrap_or(UtcOffset::UTC);
+    let now = now.to_offset(offset);
+    let timestamp = format!(
+        "{}[{:02}:{:02}]{}",
+        TIMESTAMP_COLOR,
+        now.hour(),
+        now.minute(),
+        RESET
+    );
+    let body = USER_MENTION.replace_all(body, |caps: &Captures| {
+        let name = caps[1]
+            .parse()
+            .ok()
+            .map(UserId)
+            .and_then(|id| names.get(&id).map(|name| (id, name)));
+        match name {
+            Some((id, name)) if id == author => [ORIGIN_NAME_COLOR, name, EMOTE_COLOR].concat(),
+            Some((_, name)) => name.clone(),
+            None => caps[0].to_string(),
+        }
+    });
+    // names are checked too, since they come from users as much as the message does
+    let body = break_code_fences(&body);
+    let lines: Vec<_> = body
+        .lines()
+        .map(|line| format!("{} {}{}{}", timestamp, EMOTE_COLOR, line, RESET))
+        .collect();
+    format!("```ansi\n{}\n```", lines.join("\n"))
+}
+
+/// Breaks up anything that would close the code block early. Every backtick is split from the next
+/// one, since a run of more than three would still leave three together if only those were.
+fn break_code_fences(text: &str) -> Cow<'_, str> {
+    if text.contains("```") {
+        Cow::Owned(text.replace('`', "`\u{200B}"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// The users mentioned in an emote message, whose names [game_log_block] needs
+pub fn mentioned_users(body: &str) -> Vec<UserId> {
+    let mut users: Vec<_> = USER_MENTION
+        .captures_iter(body)
+        .filter_map(|caps| caps[1].parse().ok().map(UserId))
+        .collect();
+    users.sort_unstable();
+    users.dedup();
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHOR: UserId = UserId(1);
+
+    #[test]
+    fn parses_utc_offsets() {
+        let cases = [
+            ("0", Some(0)),
+            ("UTC", Some(0)),
+            ("+9", Some(9 * 60)),
+            ("9", Some(9 * 60)),
+            ("-05:30", Some(-(5 * 60 + 30))),
+            ("UTC+14", Some(14 * 60)),
+            ("utc-12", Some(-12 * 60)),
+            (" GMT +5:45 ", Some(5 * 60 + 45)),
+            ("UTC+14:01", None),
+            ("UTC+15", None),
+            ("-13", None),
+            ("+5:60", None),
+            ("+-5", None),
+            ("5h", None),
+            ("", Some(0)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_utc_offset(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn formats_utc_offsets() {
+        assert_eq!(format_utc_offset(0), "UTC");
+        assert_eq!(format_utc_offset(9 * 60), "UTC+09:00");
+        assert_eq!(format_utc_offset(-(5 * 60 + 30)), "UTC-05:30");
+        assert_eq!(format_utc_offset(14 * 60), "UTC+14:00");
+        for offset in [-12 * 60, -(9 * 60 + 30), 0, 5 * 60 + 45, 14 * 60] {
+            assert_eq!(
+                parse_utc_offset(&format_utc_offset(offset)),
+                Some(offset),
+                "{}",
+                offset
+            );
+        }
+    }
+
+    fn log(body: &str, names: &[(u64, &str)], utc_offset: i32) -> String {
+        let names = names
+            .iter()
+            .map(|(id, name)| (UserId(*id), name.to_string()))
+            .collect();
+        game_log_block(
+            body,
+            AUTHOR,
+            &names,
+            utc_offset,
+            // 2024-01-01 23:45 UTC
+            OffsetDateTime::from_unix_timestamp(1_704_152_700).unwrap(),
+        )
+    }
+
+    #[test]
+    fn renders_game_log() {
+        assert_eq!(
+            log("<@1> hugs <@!2>.\n<@1> hugs <@3>.", &[(1, "Alice"), (2, "Bob")], 9 * 60),
+            [
+                "```ansi",
+                "\u{1b}[2;30m[08:45]\u{1b}[0m \u{1b}[2;33m\u{1b}[2;36mAlice\u{1b}[2;33m hugs Bob.\u{1b}[0m",
+                "\u{1b}[2;30m[08:45]\u{1b}[0m \u{1b}[2;33m\u{1b}[2;36mAlice\u{1b}[2;33m hugs <@3>.\u{1b}[0m",
+                "```",
+            ]
+            .join("\n")
+        );
+        assert!(log("<@1> waves.", &[], -(5 * 60 + 30)).contains("[18:15]"));
+    }
+
+    #[test]
+    fn breaks_up_code_fences() {
+        for (body, names) in [
+            ("<@1> hugs ```<@2>.", vec![]),
+            ("<@1> hugs `````.", vec![]),
+            ("<@1> hugs <@2>.", vec![(2, "``")]),
+            ("<@1> hugs <@2>`.", vec![(2, "Bob``")]),
+            ("<@1> hugs <@2>.", vec![(1, "```Alice"), (2, "Bob")]),
+        ] {
+            let block = log(body, &names, 0);
+            let inner = &block["```ansi\n".len()..block.len() - "\n```".len()];
+            assert!(!inner.contains("```"), "{:?} {:?}", body, names);
+        }
+    }
+
+    #[test]
+    fn finds_mentioned_users() {
+        assert_eq!(
+            mentioned_users("<@3> hugs <@!2> and <@3>, not <@&4> or <#5>."),
+            [UserId(2), UserId(3)]
+        );
+        assert!(mentioned_users("@everyone").is_empty());
+    }
+}
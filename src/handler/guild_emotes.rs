@@ -0,0 +1,211 @@
+//! Emotes that guilds define for themselves, using the same markup as the game's log messages
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use serenity::model::prelude::GuildId;
+use thiserror::Error;
+use tracing::*;
+use xiv_emote_parser::log_message::{
+    condition::DynamicText,
+    parser::{extract_condition_texts, ConditionTexts, Text},
+    EmoteTextError,
+};
+
+use crate::db::models::DbGuildEmote;
+
+use super::{
+    emotes::EmoteListItem, normalize::normalize_emote_input, ConditionTextPair, EmoteData, Handler,
+    HandlerError,
+};
+
+/// Length of the command column, including the leading slash
+pub const MAX_COMMAND_LEN: usize = 30;
+
+#[derive(Debug, Error)]
+pub enum GuildEmoteError {
+    #[error("Invalid command")]
+    InvalidCommand,
+    #[error("Command is already used by a built-in emote")]
+    BuiltIn,
+    #[error("Invalid message ({0})")]
+    InvalidMessage(#[from] Box<EmoteTextError>),
+    #[error("Message without a target refers to the target")]
+    UntargetedTarget,
+}
+
+impl From<EmoteTextError> for GuildEmoteError {
+    fn from(err: EmoteTextError) -> Self {
+        GuildEmoteError::InvalidMessage(Box::new(err))
+    }
+}
+
+/// A guild's emotes keyed by command
+#[derive(Debug, Default)]
+pub struct GuildEmotes {
+    emotes: BTreeMap<String, Arc<EmoteData>>,
+}
+
+impl GuildEmotes {
+    pub fn get(&self, command: &str) -> Option<&Arc<EmoteData>> {
+        self.emotes.get(command)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Arc<EmoteData>)> {
+        self.emotes.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emotes.is_empty()
+    }
+
+    /// Every command, ordered alphabetically
+    pub fn list(&self) -> Vec<EmoteListItem> {
+        self.emotes
+            .keys()
+            .map(|command| EmoteListItem {
+                command: command.clone(),
+                synthetic: false,
+            })
+            .collect()
+    }
+}
+
+/// Normalizes a command the same way as emotes that are typed, or returns `None` if the command
+/// can't be typed as one
+pub fn normalize_command(command: &str) -> Option<String> {
+    let command = normalize_emote_input(command, "")?;
+    if command.chars().any(char::is_whitespace) || command.chars().count() > MAX_COMMAND_LEN {
+        return None;
+    }
+    Some(command)
+}
+
+/// Parses the messages of a guild emote, which is also how they're validated before saving
+pub fn parse_guild_emote(
+    guild_emote_id: i64,
+    command: &str,
+    targeted: &str,
+    untargeted: &str,
+) -> Result<EmoteData, GuildEmoteError> {
+    let targeted = extract_condition_texts(targeted)?;
+    let untargeted = extract_condition_texts(untargeted)?;
+    if refers_to_target(&untargeted) {
+        return Err(GuildEmoteError::UntargetedTarget);
+    }
+    let messages = ConditionTextPair {
+        targeted,
+        untargeted,
+    };
+    Ok(EmoteData {
+        id: 0,
+        name: command.to_string(),
+        category: None,
+        order: 0,
+        synthetic: false,
+        guild_emote_id: Some(guild_emote_id),
+        en: messages.clone(),
+        ja: messages,
+        de: None,
+        fr: None,
+    })
+}
+
+/// Whether any branch of the texts names the target
+fn refers_to_target(texts: &ConditionTexts) -> bool {
+    texts.clone().into_inner().into_iter().any(|text| {
+        matches!(
+            text.text,
+            Text::Dynamic(
+                DynamicText::NpcTargetName
+                    | DynamicText::PlayerTargetNameEn
+                    | DynamicText::PlayerTargetNameJp
+            )
+        )
+    })
+}
+
+impl Handler {
+    /// The emotes a guild has defined, or none outside of guilds. They're only loaded from the
+    /// database the first time, and again after [Handler::forget_guild_emotes].
+    #[instrument(skip(self))]
+    pub async fn guild_emotes(
+        &self,
+        guild_id: Option<GuildId>,
+    ) -> Result<Arc<GuildEmotes>, HandlerError> {
+        let Some(guild_id) = guild_id else {
+            return Ok(Arc::default());
+        };
+        if let Some(emotes) = self.guild_emotes.lock().await.get(&guild_id) {
+            return Ok(emotes.clone());
+        }
+        let emotes = Arc::new(self.load_guild_emotes(guild_id).await?);
+        self.guild_emotes
+            .lock()
+            .await
+            .insert(guild_id, emotes.clone());
+        Ok(emotes)
+    }
+
+    /// Drops the cached emotes of a guild, which must be done whenever they're changed
+    pub async fn forget_guild_emotes(&self, guild_id: GuildId) {
+        self.guild_emotes.lock().await.remove(&guild_id);
+    }
+
+    async fn load_guild_emotes(&self, guild_id: GuildId) -> Result<GuildEmotes, HandlerError> {
+        debug!("loading guild emotes");
+        let emotes = self
+            .db
+            .find_guild_emotes(&guild_id)
+            .await?
+            .into_iter()
+            .filter_map(|emote: DbGuildEmote| {
+                match parse_guild_emote(
+                    emote.guild_emote_id,
+                    &emote.command,
+                    &emote.targeted,
+                    &emote.untargeted,
+                ) {
+                    Ok(data) => Some((emote.command, Arc::new(data))),
+                    Err(err) => {
+                        // only validated emotes are saved, so the parser must have changed since
+                        error!(?err, emote.command, "could not parse saved guild emote");
+                        None
+                    }
+                }
+            })
+            .collect();
+        Ok(GuildEmotes { emotes })
+    }
+
+    /// Looks up a built-in emote, then one defined by the guild
+    pub async fn find_emote(
+        &self,
+        command: &str,
+        guild_id: Option<GuildId>,
+    ) -> Result<Option<Arc<EmoteData>>, HandlerError> {
+        if let Some(emote) = self.get_emote_data(command) {
+            return Ok(Some(emote));
+        }
+        Ok(self.guild_emotes(guild_id).await?.get(command).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_commands_by_characters() {
+        let japanese = format!("/{}", "あ".repeat(MAX_COMMAND_LEN - 1));
+        assert_eq!(normalize_command(&japanese), Some(japanese.clone()));
+
+        let too_long = format!("/{}", "あ".repeat(MAX_COMMAND_LEN));
+        assert_eq!(normalize_command(&too_long), None);
+    }
+
+    #[test]
+    fn rejects_commands_with_whitespace() {
+        assert_eq!(normalize_command("/hug me"), None);
+        assert_eq!(normalize_command("hug"), Some("/hug".to_string()));
+    }
+}
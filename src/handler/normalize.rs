@@ -0,0 +1,127 @@
+//! Turns however an emote was typed into the command it's looked up by, such as `！ＨＵＧ` into
+//! `/hug`
+
+/// Folds full-width ASCII, as typed with a Japanese IME, into plain ASCII
+fn fold_full_width(c: char) -> char {
+    match c {
+        '！'..='～' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Strips `prefix` from the start of `s`, also when either was typed in full-width
+pub fn strip_prefix_folded<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut prefix_chars = prefix.chars().map(fold_full_width);
+    let mut end = 0;
+    for (i, c) in s.char_indices() {
+        match prefix_chars.next() {
+            Some(p) if p == fold_full_width(c) => end = i + c.len_utf8(),
+            Some(_) => return None,
+            None => return Some(&s[end..]),
+        }
+    }
+    match prefix_chars.next() {
+        Some(_) => None,
+        None => Some(&s[end..]),
+    }
+}
+
+/// Normalizes an emote's command as typed in a message with the guild `prefix` or given as an
+/// option, returning `None` if nothing is left of it.
+///
+/// Surrounding whitespace, the prefix and the slash are stripped, in either half or full width,
+/// and the rest is lowercased with full-width letters folded, so that `!hug`, `/Hug`, `hug` and
+/// `／ｈｕｇ` are all `/hug`, and `ハグ` and `／ハグ` are both `/ハグ`.
+pub fn normalize_emote_input(input: &str, prefix: &str) -> Option<String> {
+    let input = input.trim();
+    let input = match prefix {
+        "" => input,
+        prefix => strip_prefix_folded(input, prefix)
+            .unwrap_or(input)
+            .trim_start(),
+    };
+    let input = strip_prefix_folded(input, "/")
+        .unwrap_or(input)
+        .trim_start();
+    if input.is_empty() {
+        return None;
+    }
+    let command: String = input.chars().map(fold_full_width).collect();
+    Some(["/", &command.to_lowercase()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_prefixes_in_either_width() {
+        let cases = [
+            ("!hug", "!", Some("hug")),
+            ("！hug", "!", Some("hug")),
+            ("!hug", "！", Some("hug")),
+            ("／ハグ", "/", Some("ハグ")),
+            ("xiv!hug", "xiv!", Some("hug")),
+            ("ｘｉｖ！hug", "xiv!", Some("hug")),
+            ("xi!hug", "xiv!", None),
+            ("xi", "xiv!", None),
+            ("!", "!", Some("")),
+            ("", "!", None),
+            ("hug", "", Some("hug")),
+            ("", "", Some("")),
+        ];
+        for (s, prefix, expected) in cases {
+            assert_eq!(
+                strip_prefix_folded(s, prefix),
+                expected,
+                "stripping {:?} from {:?}",
+                prefix,
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn normalizes_emote_input() {
+        let cases = [
+            ("hug", "!", Some("/hug")),
+            ("!hug", "!", Some("/hug")),
+            ("/hug", "!", Some("/hug")),
+            ("!/hug", "!", Some("/hug")),
+            // full-width prefixes and slashes
+            ("！hug", "!", Some("/hug")),
+            ("／hug", "", Some("/hug")),
+            ("！／ＨＵＧ", "!", Some("/hug")),
+            // multi-character prefixes
+            ("xiv!hug", "xiv!", Some("/hug")),
+            ("ｘｉｖ！hug", "xiv!", Some("/hug")),
+            // surrounding whitespace
+            ("  !hug  ", "!", Some("/hug")),
+            ("/ hug", "", Some("/hug")),
+            // mixed case and full-width letters
+            ("/HuG", "", Some("/hug")),
+            ("ｈｕｇ", "", Some("/hug")),
+            // Japanese commands with and without the slash
+            ("ハグ", "", Some("/ハグ")),
+            ("/ハグ", "", Some("/ハグ")),
+            ("！ハグ", "!", Some("/ハグ")),
+            // nothing left of the command
+            ("!", "!", None),
+            ("！", "!", None),
+            ("/", "!", None),
+            ("! /", "!", None),
+            ("! /hug", "!", Some("/hug")),
+            ("", "!", None),
+            ("   ", "", None),
+        ];
+        for (input, prefix, expected) in cases {
+            assert_eq!(
+                normalize_emote_input(input, prefix).as_deref(),
+                expected,
+                "normalizing {:?} with prefix {:?}",
+                input,
+                prefix
+            );
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Sending emote messages, either as the bot or through a webhook that looks like their sender
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use serenity::{
+    builder::{CreateEmbed, ExecuteWebhook},
+    json::{hashmap_to_json_map, JsonMap},
+    model::prelude::{
+        Channel, ChannelId, Embed, GuildId, Message, User, UserId, Webhook, WebhookId,
+    },
+    prelude::Context,
+    utils::Colour,
+};
+use time::OffsetDateTime;
+use tracing::*;
+
+use crate::{
+    db::models::{DbOutputFormat, DbSendMode},
+    MessageDbData,
+};
+
+use super::{
+    emotes::EmoteMentions,
+    game_log::{game_log_block, mentioned_users},
+    EmoteData, Handler, HandlerError,
+};
+
+/// Name of the webhooks the bot creates, which messages sent through them replace
+const WEBHOOK_NAME: &str = "XIV Emotes";
+/// Where webhooks are executed, for the requests serenity can't make itself
+const WEBHOOK_URL: &str = "https://discord.com/api/v10/webhooks";
+/// Sidebar color of emote embeds
+const EMOTE_COLOR: Colour = Colour::new(0xC8A165);
+/// How long to send as the bot in a channel whose webhook couldn't be found or created before
+/// trying again, such as after the bot is given permission to manage webhooks
+const WEBHOOK_RETRY_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// The webhook emotes are sent through in a channel
+#[derive(Debug, Clone)]
+pub enum ChannelWebhook {
+    /// Threads can't have webhooks, so emotes in them are sent through their parent's webhook
+    Ready {
+        webhook: Box<Webhook>,
+        thread_id: Option<ChannelId>,
+    },
+    Failed {
+        at: Instant,
+    },
+}
+
+/// An emote message ready to be sent
+#[derive(Debug)]
+pub struct OutgoingEmote<'a> {
+    pub channel_id: ChannelId,
+    pub author: &'a User,
+    pub emote: &'a EmoteData,
+    /// The messages to send, in order, such as one per target when a guild sends them separately
+    pub bodies: Vec<String>,
+    pub mentions: EmoteMentions,
+    /// The message the emote responds to, which the first message replies to when sent by the bot
+    pub reply_to: Option<&'a Message>,
+}
+
+/// The parts of an emote embed besides its message, which only the full [DbOutputFormat::Embed]
+/// has
+#[derive(Debug, Clone, Default)]
+struct EmoteEmbed {
+    title: Option<String>,
+    footer: Option<String>,
+}
+
+impl EmoteEmbed {
+    fn fill<'a>(&self, e: &'a mut CreateEmbed, body: &str) -> &'a mut CreateEmbed {
+        e.description(body).colour(EMOTE_COLOR);
+        if let Some(title) = &self.title {
+            e.title(title);
+        }
+        if let Some(footer) = &self.footer {
+            e.footer(|f| f.text(footer));
+        }
+        e
+    }
+}
+
+/// Replies to a message without pinging anyone but its author, since the reply may repeat things
+/// such as `@everyone` from it
+pub async fn reply_to_author(
+    context: &Context,
+    msg: &Message,
+    content: impl Display,
+) -> serenity::Result<Message> {
+    let mentions = EmoteMentions::author_only(msg.author.id);
+    msg.channel_id
+        .send_message(context, |m| {
+            m.content(content)
+                .reference_message(msg)
+                .allowed_mentions(|am| mentions.apply(am))
+        })
+        .await
+}
+
+impl Handler {
+    /// Sends an emote message the way its channel is set to, falling back to sending it as the bot
+    /// when the webhook can't be used, such as when the bot can't manage the channel's webhooks
+    #[instrument(skip(self, context, message_db_data))]
+    pub async fn send_emote(
+        &self,
+        context: &Context,
+        message_db_data: &MessageDbData<'_>,
+        emote: OutgoingEmote<'_>,
+    ) -> Result<(), HandlerError> {
+        let output_format = message_db_data.output_format().await?;
+        let embed = self
+            .emote_embed(message_db_data, output_format, emote.emote)
+            .await?;
+        let webhook_guild = match message_db_data.send_mode().await? {
+            DbSendMode::Webhook => message_db_data.guild_discord_id(),
+            DbSendMode::Bot => None,
+        };
+        for (i, body) in emote.bodies.iter().enumerate() {
+            let body = match output_format {
+                DbOutputFormat::GameLog => {
+                    self.game_log_body(context, message_db_data, emote.author.id, body)
+                        .await?
+                }
+                _ => body.clone(),
+            };
+            if let Some(guild_id) = webhook_guild {
+                match self
+                    .send_through_webhook(context, guild_id, &emote, &body, embed.as_ref())
+                    .await
+                {
+                    Ok(()) => continue,
+                    Err(err) => warn!(?err, "could not send through webhook, sending as the bot"),
+                }
+            }
+
+            emote
+                .channel_id
+                .send_message(context, |m| {
+                    match &embed {
+                        Some(embed) => m.embed(|e| embed.fill(e, &body)),
+                        None => m.content(&body),
+                    };
+                    m.allowed_mentions(|am| emote.mentions.apply(am));
+                    match emote.reply_to {
+                        Some(reply_to) if i == 0 => m.reference_message(reply_to),
+                        _ => m,
+                    }
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// The embed an emote is sent in, or `None` if it's sent as plain text. Its name and command
+    /// are in the language of whoever sent it.
+    async fn emote_embed(
+        &self,
+        message_db_data: &MessageDbData<'_>,
+        output_format: DbOutputFormat,
+        emote: &EmoteData,
+    ) -> Result<Option<EmoteEmbed>, HandlerError> {
+        match output_format {
+            DbOutputFormat::Plain | DbOutputFormat::GameLog => Ok(None),
+            DbOutputFormat::CompactEmbed => Ok(Some(EmoteEmbed::default())),
+            DbOutputFormat::Embed => {
+                // guild emotes are named by their command and only have the one
+                if emote.guild_emote_id.is_some() {
+                    return Ok(Some(EmoteEmbed {
+                        title: Some(emote.name.clone()),
+                        footer: Some(emote.name.clone()),
+                    }));
+                }
+                let user = message_db_data.determine_user_settings().await?;
+                let catalog = self.catalog();
+                let texts = catalog
+                    .raw_emote(emote.id)
+                    .map(|raw| user.language.with_raw_emote_data(raw));
+                Ok(Some(EmoteEmbed {
+                    title: Some(
+                        texts
+                            .map(|texts| texts.name.clone())
+                            .unwrap_or_else(|| emote.name.clone()),
+                    ),
+                    footer: texts.and_then(|texts| texts.command.clone()),
+                }))
+            }
+        }
+    }
+
+    /// The emote message as a chat log, timestamped in the sender's timezone
+    async fn game_log_body(
+        &self,
+        context: &Context,
+        message_db_data: &MessageDbData<'_>,
+        author: UserId,
+        body: &str,
+    ) -> Result<String, HandlerError> {
+        let user = message_db_data.determine_user_settings().await?;
+        let mut names = HashMap::new();
+        for user_id in mentioned_users(body) {
+            let name = match message_db_data.guild_discord_id() {
+                Some(guild_id) => guild_id
+                    .member(context, user_id)
+                    .await
+                    .map(|member| member.display_name().into_owned()),
+                None => user_id.to_user(context).await.map(|user| user.name),
+            };
+            match name {
+                Ok(name) => {
+                    names.insert(user_id, name);
+                }
+                // left as a mention, which is better than not sending the emote at all
+                Err(err) => warn!(?err, ?user_id, "could not look up name for game log"),
+            }
+        }
+        Ok(game_log_block(
+            body,
+            author,
+            &names,
+            user.utc_offset,
+            OffsetDateTime::now_utc(),
+        ))
+    }
+
+    async fn send_through_webhook(
+        &self,
+        context: &Context,
+        guild_id: GuildId,
+        emote: &OutgoingEmote<'_>,
+        body: &str,
+        embed: Option<&EmoteEmbed>,
+    ) -> Result<(), HandlerError> {
+        let (webhook, thread_id) = self.channel_webhook(context, emote.channel_id).await?;
+        let token = webhook
+            .token
+            .as_deref()
+            .ok_or(HandlerError::WebhookUnavailable)?;
+        let member = guild_id.member(context, emote.author.id).await?;
+        let mut execute = ExecuteWebhook::default();
+        match embed {
+            Some(embed) => execute.embeds(vec![Embed::fake(|e| embed.fill(e, body))]),
+            None => execute.content(body),
+        };
+        execute
+            .username(member.display_name())
+            .avatar_url(member.face())
+            .allowed_mentions(|am| emote.mentions.apply(am));
+        let map = hashmap_to_json_map(execute.0);
+        let res = match thread_id {
+            Some(thread_id) => execute_webhook_in_thread(webhook.id, token, thread_id, &map).await,
+            None => context
+                .http
+                .execute_webhook(webhook.id.0, token, false, &map)
+                .await
+                .map(|_| ())
+                .map_err(|err| Box::new(err).into()),
+        };
+        if res.is_err() {
+            // the webhook may have been deleted, so look it up again next time
+            self.webhooks.lock().await.remove(&emote.channel_id);
+        }
+        res
+    }
+
+    /// The webhook to send emotes in a channel through, along with the thread to send them to if
+    /// the channel is one. The lookup is remembered either way, so a channel where it fails is only
+    /// tried again after a while.
+    async fn channel_webhook(
+        &self,
+        context: &Context,
+        channel_id: ChannelId,
+    ) -> Result<(Webhook, Option<ChannelId>), HandlerError> {
+        match self.webhooks.lock().await.get(&channel_id) {
+            Some(ChannelWebhook::Ready { webhook, thread_id }) => {
+                return Ok((Webhook::clone(webhook), *thread_id))
+            }
+            Some(ChannelWebhook::Failed { at }) if at.elapsed() < WEBHOOK_RETRY_AFTER => {
+                return Err(HandlerError::WebhookUnavailable)
+            }
+            _ => {}
+        }
+        // the lock isn't held while talking to Discord, so another emote in the channel may look
+        // the webhook up at the same time, which at worst finds the same one
+        let res = self.find_channel_webhook(context, channel_id).await;
+        let entry = match &res {
+            Ok((webhook, thread_id)) => ChannelWebhook::Ready {
+                webhook: Box::new(webhook.clone()),
+                thread_id: *thread_id,
+            },
+            Err(err) => {
+                warn!(?err, ?channel_id, "could not get webhook for channel");
+                ChannelWebhook::Failed { at: Instant::now() }
+            }
+        };
+        self.webhooks.lock().await.insert(channel_id, entry);
+        res
+    }
+
+    /// The bot's webhook in a channel, or its parent if it's a thread, which is created the first
+    /// time it's needed
+    async fn find_channel_webhook(
+        &self,
+        context: &Context,
+        channel_id: ChannelId,
+    ) -> Result<(Webhook, Option<ChannelId>), HandlerError> {
+        let (webhook_channel_id, thread_id) = match channel_id.to_channel(context).await? {
+            Channel::Guild(channel) if channel.thread_metadata.is_some() => (
+                channel.parent_id.ok_or(HandlerError::UnexpectedData)?,
+                Some(channel_id),
+            ),
+            _ => (channel_id, None),
+        };
+        let bot_id = context.cache.current_user_id();
+        let existing = webhook_channel_id
+            .webhooks(context)
+            .await?
+            .into_iter()
+            .find(|webhook| {
+                webhook.token.is_some() && webhook.user.as_ref().map(|user| user.id) == Some(bot_id)
+            });
+        let webhook = match existing {
+            Some(webhook) => webhook,
+            None => {
+                debug!(?webhook_channel_id, "creating webhook");
+                webhook_channel_id
+                    .create_webhook(context, WEBHOOK_NAME)
+                    .await?
+            }
+        };
+        Ok((webhook, thread_id))
+    }
+}
+
+/// Sends a webhook message to a thread, which serenity's webhook requests have no way to address
+async fn execute_webhook_in_thread(
+    webhook_id: WebhookId,
+    token: &str,
+    thread_id: ChannelId,
+    map: &JsonMap,
+) -> Result<(), HandlerError> {
+    let url = format!("{}/{}/{}", WEBHOOK_URL, webhook_id, token);
+    let body = serde_json::to_string(map)?;
+    tokio::task::spawn_blocking(move || {
+        ureq::post(&url)
+            .query("thread_id", &thread_id.to_string())
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .map_err(Box::new)
+    })
+    .await??;
+    Ok(())
+}
@@ -0,0 +1,302 @@
+//! "Did you mean" suggestions for commands that aren't emotes.
+//!
+//! Commands are compared by edit distance after converting kana to romaji, so that `/hagu` and
+//! `/はぐ` both find `/ハグ`.
+
+use serenity::model::prelude::GuildId;
+use strsim::damerau_levenshtein;
+use tracing::*;
+
+use crate::{db::models::DbUser, util::LocalizedString};
+
+use super::{Handler, HandlerError};
+
+pub const DID_YOU_MEAN: LocalizedString = LocalizedString {
+    en: "Did you mean:",
+    ja: "もしかして：",
+    de: "Meintest du:",
+    fr: "Vouliez-vous dire :",
+};
+pub const UNRECOGNIZED_EMOTE: LocalizedString = LocalizedString {
+    en: "That's not an emote I know:",
+    ja: "存在しないエモートです：",
+    de: "Dieses Emote kenne ich nicht:",
+    fr: "Je ne connais pas cette emote :",
+};
+
+const MAX_SUGGESTIONS: usize = 3;
+/// Edits allowed per this many characters of input, so short inputs only get close matches
+const CHARS_PER_EDIT: usize = 3;
+
+/// Hepburn romanization of each hiragana, with small kana handled by [to_romaji]
+const KANA_ROMAJI: &[(char, &str)] = &[
+    ('あ', "a"),
+    ('い', "i"),
+    ('う', "u"),
+    ('え', "e"),
+    ('お', "o"),
+    ('か', "ka"),
+    ('き', "ki"),
+    ('く', "ku"),
+    ('け', "ke"),
+    ('こ', "ko"),
+    ('が', "ga"),
+    ('ぎ', "gi"),
+    ('ぐ', "gu"),
+    ('げ', "ge"),
+    ('ご', "go"),
+    ('さ', "sa"),
+    ('し', "shi"),
+    ('す', "su"),
+    ('せ', "se"),
+    ('そ', "so"),
+    ('ざ', "za"),
+    ('じ', "ji"),
+    ('ず', "zu"),
+    ('ぜ', "ze"),
+    ('ぞ', "zo"),
+    ('た', "ta"),
+    ('ち', "chi"),
+    ('つ', "tsu"),
+    ('て', "te"),
+    ('と', "to"),
+    ('だ', "da"),
+    ('ぢ', "ji"),
+    ('づ', "zu"),
+    ('で', "de"),
+    ('ど', "do"),
+    ('な', "na"),
+    ('に', "ni"),
+    ('ぬ', "nu"),
+    ('ね', "ne"),
+    ('の', "no"),
+    ('は', "ha"),
+    ('ひ', "hi"),
+    ('ふ', "fu"),
+    ('へ', "he"),
+    ('ほ', "ho"),
+    ('ば', "ba"),
+    ('び', "bi"),
+    ('ぶ', "bu"),
+    ('べ', "be"),
+    ('ぼ', "bo"),
+    ('ぱ', "pa"),
+    ('ぴ', "pi"),
+    ('ぷ', "pu"),
+    ('ぺ', "pe"),
+    ('ぽ', "po"),
+    ('ま', "ma"),
+    ('み', "mi"),
+    ('む', "mu"),
+    ('め', "me"),
+    ('も', "mo"),
+    ('や', "ya"),
+    ('ゆ', "yu"),
+    ('よ', "yo"),
+    ('ら', "ra"),
+    ('り', "ri"),
+    ('る', "ru"),
+    ('れ', "re"),
+    ('ろ', "ro"),
+    ('わ', "wa"),
+    ('を', "wo"),
+    ('ん', "n"),
+    ('ゔ', "vu"),
+];
+
+fn katakana_to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Converts any kana to romaji, leaving everything else lowercased
+pub fn to_romaji(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut double_next = false;
+    for c in s.chars().map(katakana_to_hiragana) {
+        let romaji = KANA_ROMAJI
+            .iter()
+            .find(|(kana, _)| *kana == c)
+            .map(|(_, romaji)| *romaji);
+        match (c, romaji) {
+            ('っ', _) => double_next = true,
+            // repeats the previous vowel
+            ('ー', _) => {
+                if let Some(vowel) = res.chars().last().filter(|c| "aeiou".contains(*c)) {
+                    res.push(vowel);
+                }
+            }
+            // combines with the previous kana, such as しゃ to sha
+            ('ゃ' | 'ゅ' | 'ょ', _) => {
+                let vowel = match c {
+                    'ゃ' => 'a',
+                    'ゅ' => 'u',
+                    _ => 'o',
+                };
+                if res.ends_with('i') {
+                    res.pop();
+                    if !(res.ends_with("sh") || res.ends_with("ch") || res.ends_with('j')) {
+                        res.push('y');
+                    }
+                } else {
+                    res.push('y');
+                }
+                res.push(vowel);
+            }
+            // replaces the vowel of the previous kana, such as ティ to ti
+            ('ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ', _) => {
+                let vowel = match c {
+                    'ぁ' => 'a',
+                    'ぃ' => 'i',
+                    'ぅ' => 'u',
+                    'ぇ' => 'e',
+                    _ => 'o',
+                };
+                if res.ends_with(|c| "aeiou".contains(c)) && res.len() > 1 {
+                    res.pop();
+                }
+                res.push(vowel);
+            }
+            (_, Some(romaji)) => {
+                if std::mem::take(&mut double_next) {
+                    res.extend(romaji.chars().next());
+                }
+                res.push_str(romaji);
+            }
+            (c, None) => res.extend(c.to_lowercase()),
+        }
+    }
+    res
+}
+
+/// The commands closest to the input, closest first
+pub fn closest_commands<'a>(input: &str, commands: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let normalize = |s: &str| to_romaji(s.trim().trim_start_matches('/'));
+    let input = normalize(input);
+    let max_distance = (input.chars().count() / CHARS_PER_EDIT).max(1);
+    let mut closest: Vec<_> = commands
+        .map(|command| (damerau_levenshtein(&input, &normalize(command)), command))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    closest.sort_unstable();
+    closest.dedup_by_key(|(_, command)| *command);
+    closest
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, command)| command)
+        .collect()
+}
+
+/// A line listing the suggestions, or nothing if there aren't any
+pub fn did_you_mean(suggestions: &[String], user: &DbUser) -> Option<String> {
+    if suggestions.is_empty() {
+        return None;
+    }
+    let suggestions: Vec<_> = suggestions.iter().map(|s| format!("`{}`", s)).collect();
+    Some(format!(
+        "{} {}",
+        DID_YOU_MEAN.for_user(user),
+        suggestions.join(", ")
+    ))
+}
+
+impl Handler {
+    /// Suggests built-in and guild emotes for a command that isn't one
+    #[instrument(skip(self))]
+    pub async fn suggest_emotes(
+        &self,
+        command: &str,
+        guild_id: Option<GuildId>,
+    ) -> Result<Vec<String>, HandlerError> {
+        let catalog = self.catalog();
+        let guild_emotes = self.guild_emotes(guild_id).await?;
+        let suggestions: Vec<_> = closest_commands(
+            command,
+            catalog
+                .commands()
+                .map(|(command, _)| command.as_str())
+                .chain(guild_emotes.iter().map(|(command, _)| command.as_str())),
+        )
+        .into_iter()
+        .map(ToString::to_string)
+        .collect();
+        debug!(?suggestions, "suggested emotes");
+        Ok(suggestions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_kana_to_romaji() {
+        let cases = [
+            ("はぐ", "hagu"),
+            ("おどろく", "odoroku"),
+            // っ doubles the next consonant
+            ("がっかり", "gakkari"),
+            ("ちょっと", "chotto"),
+            // ー repeats the previous vowel
+            ("ケーキ", "keeki"),
+            ("ラッキー", "rakkii"),
+            // ゃゅょ combine with the previous kana, without a y after し, ち and じ
+            ("きゃ", "kya"),
+            ("にょ", "nyo"),
+            ("しゃ", "sha"),
+            ("ちゅ", "chu"),
+            ("じょ", "jo"),
+            ("ぢゃ", "ja"),
+            // small vowels replace the previous vowel
+            ("ティ", "ti"),
+            ("ファイト", "faito"),
+            ("ヴァ", "va"),
+            // or stand on their own
+            ("ぁ", "a"),
+            ("ー", ""),
+            ("っ", ""),
+            // katakana is folded into hiragana
+            ("ハグ", "hagu"),
+            ("ダンス", "dansu"),
+            ("Hug", "hug"),
+        ];
+        for (kana, romaji) in cases {
+            assert_eq!(to_romaji(kana), romaji, "converting {}", kana);
+        }
+    }
+
+    const COMMANDS: &[&str] = &[
+        "/ハグ",
+        "/hug",
+        "/huh",
+        "/dance",
+        "/ダンス",
+        "/surprised",
+        "/bow",
+    ];
+
+    #[test]
+    fn finds_commands_across_scripts() {
+        let closest = |input| closest_commands(input, COMMANDS.iter().copied());
+        assert_eq!(closest("/hagu"), ["/ハグ"]);
+        assert_eq!(closest("/はぐ"), ["/ハグ"]);
+        assert_eq!(closest("/dansu"), ["/ダンス"]);
+        assert_eq!(closest("/dnace"), ["/dance"]);
+        // closest first, then alphabetically
+        assert_eq!(closest("hug"), ["/hug", "/huh"]);
+        assert_eq!(closest("/hgu"), ["/hug", "/ハグ"]);
+    }
+
+    #[test]
+    fn allows_more_edits_for_longer_input() {
+        let closest = |input| closest_commands(input, COMMANDS.iter().copied());
+        // one edit for every three characters, and at least one
+        assert_eq!(closest("/surprsd"), ["/surprised"]);
+        assert!(closest("/surpd").is_empty());
+        assert_eq!(closest("/bo"), ["/bow"]);
+        assert!(closest("/b").is_empty());
+        assert!(closest("/xyzzy").is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+//! Personal shortcuts for emotes, such as `/h` for `/hug`, which only work for the user who set them
+
+use std::sync::Arc;
+
+use serenity::model::prelude::{GuildId, UserId};
+use tracing::*;
+
+use super::{EmoteData, Handler, HandlerError};
+
+impl Handler {
+    /// Looks up the emote a user's alias stands for, or else the emote with that command. An alias
+    /// can't be set for a command that's already an emote, but it does hide one added later, by the
+    /// guild or by reloading the catalog, from that user until they remove it.
+    #[instrument(skip(self))]
+    pub async fn find_emote_for_user(
+        &self,
+        command: &str,
+        user_id: &UserId,
+        guild_id: Option<GuildId>,
+    ) -> Result<Option<Arc<EmoteData>>, HandlerError> {
+        if let Some(aliased) = self.db.find_user_emote_alias(user_id, command).await? {
+            debug!(aliased, "using user alias");
+            // the aliased emote may have been removed since, which leaves the alias doing nothing
+            if let Some(emote) = self.find_emote(&aliased, guild_id).await? {
+                return Ok(Some(emote));
+            }
+        }
+        self.find_emote(command, guild_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::{catalog::fixture, db::Db};
+
+    use super::*;
+
+    #[ignore = "needs a database at DATABASE_URL"]
+    #[sqlx::test]
+    async fn aliases_are_found_before_emotes(pool: PgPool) {
+        let handler = fixture::handler_with_db(Db(pool)).await;
+        let find = |command: &'static str, user_id: u64| {
+            let handler = &handler;
+            async move {
+                handler
+                    .find_emote_for_user(command, &UserId(user_id), None)
+                    .await
+                    .unwrap()
+                    .map(|emote| emote.id)
+            }
+        };
+        let db = &handler.db;
+        db.upsert_user_emote_alias(&UserId(1), "/h", "/hug")
+            .await
+            .unwrap();
+        // as if /surprised was added to the catalog after the alias was set
+        db.upsert_user_emote_alias(&UserId(1), "/surprised", "/hug")
+            .await
+            .unwrap();
+        db.upsert_user_emote_alias(&UserId(1), "/gone", "/removed")
+            .await
+            .unwrap();
+
+        assert_eq!(find("/h", 1).await, Some(112));
+        assert_eq!(find("/surprised", 1).await, Some(112));
+        assert_eq!(find("/hug", 1).await, Some(112));
+        assert_eq!(find("/gone", 1).await, None);
+        // aliases are only for the user who set them
+        assert_eq!(find("/h", 2).await, None);
+        assert_eq!(find("/surprised", 2).await, Some(1));
+    }
+}
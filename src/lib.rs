@@ -1,16 +1,18 @@
+pub mod catalog;
 mod commands;
 mod db;
 pub mod handler;
 pub mod util;
 
+use catalog::CatalogConfig;
 use commands::CommandsEnum;
 use db::{
-    models::{DbGuild, DbUser},
+    models::{DbChannel, DbGuild, DbOutputFormat, DbSendMode, DbUser},
     util::DiscordIdExt,
     Db,
 };
 use futures::try_join;
-use handler::{Handler, HandlerError};
+use handler::{normalize::strip_prefix_folded, send::reply_to_author, Handler, HandlerError};
 use sqlx::PgPool;
 use std::{borrow::Cow, fmt::Debug, time::Duration};
 use tokio::sync::OnceCell;
@@ -20,7 +22,7 @@ use serenity::{
     async_trait,
     model::prelude::{
         interaction::{application_command::ApplicationCommandInteraction, Interaction},
-        GuildId, Message, Ready, UserId,
+        ChannelId, GuildId, Message, Ready, UserId,
     },
     prelude::{Context, EventHandler, GatewayIntents},
     Client,
@@ -33,8 +35,10 @@ pub struct MessageDbData<'a> {
     db: &'a Db,
     user_discord_id: UserId,
     guild_discord_id: Option<GuildId>,
+    channel_discord_id: ChannelId,
     user_cell: OnceCell<Option<DbUser>>,
     guild_cell: OnceCell<Option<DbGuild>>,
+    channel_cell: OnceCell<Option<DbChannel>>,
 }
 
 impl<'a> MessageDbData<'a> {
@@ -43,17 +47,52 @@ impl<'a> MessageDbData<'a> {
         db: &Db,
         user_discord_id: UserId,
         guild_discord_id: Option<GuildId>,
-    ) -> MessageDbData {
+        channel_discord_id: ChannelId,
+    ) -> MessageDbData<'_> {
         MessageDbData {
             db,
             user_discord_id,
             guild_discord_id,
+            channel_discord_id,
             user_cell: OnceCell::new(),
             guild_cell: OnceCell::new(),
+            channel_cell: OnceCell::new(),
         }
     }
 
-    pub async fn user(&self) -> Result<Option<Cow<DbUser>>, HandlerError> {
+    /// Data whose user and guild settings are already known, so that tests never look them up
+    #[cfg(test)]
+    pub(crate) fn with_settings(
+        db: &Db,
+        user_discord_id: UserId,
+        user: Option<DbUser>,
+        guild: Option<(GuildId, DbGuild)>,
+    ) -> MessageDbData<'_> {
+        let (guild_discord_id, guild) = guild.unzip();
+        MessageDbData {
+            db,
+            user_discord_id,
+            guild_discord_id,
+            channel_discord_id: ChannelId(1),
+            user_cell: OnceCell::new_with(Some(user)),
+            guild_cell: OnceCell::new_with(Some(guild)),
+            channel_cell: OnceCell::new_with(Some(None)),
+        }
+    }
+
+    pub fn user_discord_id(&self) -> UserId {
+        self.user_discord_id
+    }
+
+    pub fn guild_discord_id(&self) -> Option<GuildId> {
+        self.guild_discord_id
+    }
+
+    pub fn channel_discord_id(&self) -> ChannelId {
+        self.channel_discord_id
+    }
+
+    pub async fn user(&self) -> Result<Option<Cow<'_, DbUser>>, HandlerError> {
         Ok(self
             .user_cell
             .get_or_try_init(|| async { self.db.find_user(&self.user_discord_id).await })
@@ -62,7 +101,7 @@ impl<'a> MessageDbData<'a> {
             .map(Cow::Borrowed))
     }
 
-    pub async fn guild(&self) -> Result<Option<Cow<DbGuild>>, HandlerError> {
+    pub async fn guild(&self) -> Result<Option<Cow<'_, DbGuild>>, HandlerError> {
         if let Some(discord_id) = &self.guild_discord_id {
             Ok(self
                 .guild_cell
@@ -75,17 +114,85 @@ impl<'a> MessageDbData<'a> {
         }
     }
 
-    pub async fn determine_user_settings(&self) -> Result<Cow<DbUser>, HandlerError> {
+    /// Overrides of the guild's settings for the channel, which only guild channels can have
+    pub async fn channel(&self) -> Result<Option<Cow<'_, DbChannel>>, HandlerError> {
+        if self.guild_discord_id.is_none() {
+            return Ok(None);
+        }
+        Ok(self
+            .channel_cell
+            .get_or_try_init(|| async { self.db.find_channel(&self.channel_discord_id).await })
+            .await?
+            .as_ref()
+            .map(Cow::Borrowed))
+    }
+
+    pub async fn send_mode(&self) -> Result<DbSendMode, HandlerError> {
+        if let Some(send_mode) = self.channel().await?.and_then(|channel| channel.send_mode) {
+            return Ok(send_mode);
+        }
+        Ok(self
+            .guild()
+            .await?
+            .map(|guild| guild.send_mode)
+            .unwrap_or_default())
+    }
+
+    /// The user's output format, or else the channel's or the guild's
+    pub async fn output_format(&self) -> Result<DbOutputFormat, HandlerError> {
+        if let Some(output_format) = self.user().await?.and_then(|user| user.output_format) {
+            return Ok(output_format);
+        }
+        if let Some(output_format) = self
+            .channel()
+            .await?
+            .and_then(|channel| channel.output_format)
+        {
+            return Ok(output_format);
+        }
+        Ok(self
+            .guild()
+            .await?
+            .map(|guild| guild.output_format)
+            .unwrap_or_default())
+    }
+
+    /// The prefix of emote messages in the channel
+    pub async fn prefix(&self) -> Result<String, HandlerError> {
+        if let Some(prefix) = self
+            .channel()
+            .await?
+            .and_then(|channel| channel.prefix.clone())
+        {
+            return Ok(prefix);
+        }
+        Ok(self.guild().await?.unwrap_or_default().prefix.clone())
+    }
+
+    pub async fn prefix_emotes_enabled(&self) -> Result<bool, HandlerError> {
+        Ok(self
+            .channel()
+            .await?
+            .map(|channel| channel.prefix_emotes_enabled)
+            .unwrap_or(true))
+    }
+
+    pub async fn determine_user_settings(&self) -> Result<Cow<'_, DbUser>, HandlerError> {
         if let Some(user) = self.user().await? {
             return Ok(user);
         }
-        if let Some(guild) = self.guild().await? {
-            return Ok(Cow::Owned(DbUser {
-                discord_id: self.user_discord_id.to_db_string(),
-                ..DbUser::from(guild.as_ref())
-            }));
+        let Some(guild) = self.guild().await? else {
+            return Ok(Cow::Owned(DbUser::default()));
+        };
+        let mut user = DbUser {
+            discord_id: self.user_discord_id.to_db_string(),
+            ..DbUser::from(guild.as_ref())
+        };
+        if let Some(channel) = self.channel().await? {
+            user.language = channel.language.unwrap_or(user.language);
+            user.gender = channel.gender.unwrap_or(user.gender);
         }
-        Ok(Cow::Owned(DbUser::default()))
+        Ok(Cow::Owned(user))
     }
 }
 
@@ -98,7 +205,7 @@ impl EventHandler for Handler {
         async fn handle_error(err: HandlerError, msg: Message, context: &Context) {
             error!(?err, "error during message processing");
             if err.should_followup() {
-                if let Err(e) = msg.reply(context, err.to_string()).await {
+                if let Err(e) = reply_to_author(context, &msg, err).await {
                     error!(
                         err = ?e,
                         "could not send follow-up message",
@@ -113,23 +220,44 @@ impl EventHandler for Handler {
 
         info!("handling message");
 
-        let message_db_data = MessageDbData::new(&self.db, msg.author.id, msg.guild_id);
+        let message_db_data =
+            MessageDbData::new(&self.db, msg.author.id, msg.guild_id, msg.channel_id);
 
-        let guild = match message_db_data.guild().await {
-            Ok(guild) => guild.unwrap_or_default(),
-            Err(HandlerError::NotGuild) => Cow::Owned(DbGuild::default()),
+        let prefix = match try_join!(
+            message_db_data.prefix(),
+            message_db_data.prefix_emotes_enabled()
+        ) {
+            Ok((prefix, true)) => prefix,
+            Ok((_, false)) => {
+                debug!("prefix emotes are disabled in channel");
+                return;
+            }
             Err(err) => {
                 error!(?err, "error communicating with db");
                 handle_error(err, msg, &context).await;
                 return;
             }
         };
-        debug!(guild.prefix, "using guild prefix");
-        if msg.content.starts_with(&guild.prefix) {
-            let mut mparts: Vec<_> = msg.content.split_whitespace().collect();
-            if let Some(first) = mparts.get_mut(0) {
-                *first = first.strip_prefix(&guild.prefix).unwrap_or(first);
+        debug!(prefix, "using prefix");
+        if let Some(content) = strip_prefix_folded(&msg.content, &prefix) {
+            match self.emotes_allowed(&context, &message_db_data).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    debug!("emotes are not allowed in channel");
+                    if let Err(err) = self
+                        .notify_prefix_emote_blocked(&context, &msg, &message_db_data)
+                        .await
+                    {
+                        handle_error(err, msg, &context).await;
+                    }
+                    return;
+                }
+                Err(err) => {
+                    handle_error(err, msg, &context).await;
+                    return;
+                }
             }
+            let mparts: Vec<_> = content.split_whitespace().collect();
             debug!(?mparts);
             match self
                 .process_message_input(&context, &mparts, &msg, &message_db_data)
@@ -145,39 +273,48 @@ impl EventHandler for Handler {
 
     #[instrument(skip(self, context))]
     async fn interaction_create(&self, context: Context, interaction: Interaction) {
-        if let Interaction::ApplicationCommand(cmd) = interaction {
-            let message_db_data = MessageDbData::new(&self.db, cmd.user.id, cmd.guild_id);
+        match interaction {
+            Interaction::ApplicationCommand(cmd) => {
+                let message_db_data =
+                    MessageDbData::new(&self.db, cmd.user.id, cmd.guild_id, cmd.channel_id);
 
-            let handle_res = match self
-                .try_handle_commands::<GlobalCommands>(&context, &cmd, &message_db_data)
-                .await
-            {
-                Some(res) => res,
-                None => match self
-                    .try_handle_commands::<GuildCommands>(&context, &cmd, &message_db_data)
+                let handle_res = match self
+                    .try_handle_commands::<GlobalCommands>(&context, &cmd, &message_db_data)
                     .await
                 {
-                    Some(r) => r,
-                    None => Err(HandlerError::UnrecognizedCommand(cmd.data.name.to_string())),
-                },
-            };
-
-            if let Err(err) = handle_res {
-                error!(?err, "error during interaction processing");
-                if err.should_followup() {
-                    if let Err(e) = cmd
-                        .create_followup_message(&context, |msg| {
-                            msg.ephemeral(true).content(err.to_string())
-                        })
+                    Some(res) => res,
+                    None => match self
+                        .try_handle_commands::<GuildCommands>(&context, &cmd, &message_db_data)
                         .await
                     {
-                        error!(
-                            err = ?e,
-                            "could not send follow-up message",
-                        );
+                        Some(r) => r,
+                        None => Err(HandlerError::UnrecognizedCommand(cmd.data.name.to_string())),
+                    },
+                };
+
+                if let Err(err) = handle_res {
+                    error!(?err, "error during interaction processing");
+                    if err.should_followup() {
+                        if let Err(e) = cmd
+                            .create_followup_message(&context, |msg| {
+                                msg.ephemeral(true).content(err.to_string())
+                            })
+                            .await
+                        {
+                            error!(
+                                err = ?e,
+                                "could not send follow-up message",
+                            );
+                        }
                     }
+                };
+            }
+            Interaction::Autocomplete(autocomplete) => {
+                if let Err(err) = self.handle_autocomplete(&context, &autocomplete).await {
+                    error!(?err, "error during autocomplete");
                 }
-            };
+            }
+            _ => {}
         }
     }
 
@@ -225,7 +362,7 @@ impl Handler {
     }
 }
 
-pub async fn setup_client(token: String, pool: PgPool) -> Client {
+pub async fn setup_client(token: String, pool: PgPool, catalog_config: CatalogConfig) -> Client {
     let intents = GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::DIRECT_MESSAGES
         | GatewayIntents::MESSAGE_CONTENT
@@ -236,9 +373,12 @@ pub async fn setup_client(token: String, pool: PgPool) -> Client {
 
     let db = Db(pool);
 
-    let handler = Handler::new(db, None).expect("couldn't load log message data from xivapi");
+    let source = catalog_config.build_source(&db);
+    let handler = Handler::from_source(db, source, catalog_config.fallback.clone())
+        .await
+        .expect("couldn't load emote data from source or snapshot");
     info!(
-        emotes = ?handler.emote_list_by_id().collect::<Vec<_>>(),
+        emotes = ?handler.emote_list_by_id(),
         "repo initialized with emotes"
     );
 
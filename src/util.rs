@@ -5,6 +5,8 @@ use crate::db::models::{DbLanguage, DbUser};
 pub struct LocalizedString {
     pub en: &'static str,
     pub ja: &'static str,
+    pub de: &'static str,
+    pub fr: &'static str,
 }
 
 pub trait CreateApplicationCommandExt {
@@ -17,12 +19,16 @@ impl CreateApplicationCommandExt for CreateApplicationCommand {
         self.name(str.en)
             .name_localized("en-US", str.en)
             .name_localized("ja", str.ja)
+            .name_localized("de", str.de)
+            .name_localized("fr", str.fr)
     }
 
     fn localized_desc(&mut self, str: LocalizedString) -> &mut Self {
         self.description(str.en)
             .description_localized("en-US", str.en)
             .description_localized("ja", str.ja)
+            .description_localized("de", str.de)
+            .description_localized("fr", str.fr)
     }
 }
 
@@ -36,24 +42,34 @@ impl CreateApplicationCommandOptionExt for CreateApplicationCommandOption {
         self.name(str.en)
             .name_localized("en-US", str.en)
             .name_localized("ja", str.ja)
+            .name_localized("de", str.de)
+            .name_localized("fr", str.fr)
     }
 
     fn localized_desc(&mut self, str: LocalizedString) -> &mut Self {
         self.description(str.en)
             .description_localized("en-US", str.en)
             .description_localized("ja", str.ja)
+            .description_localized("de", str.de)
+            .description_localized("fr", str.fr)
     }
 }
 
 impl LocalizedString {
     pub fn for_user(&self, user: &DbUser) -> &'static str {
-        match user.language {
+        self.for_language(user.language)
+    }
+
+    pub fn for_language(&self, language: DbLanguage) -> &'static str {
+        match language {
             DbLanguage::En => self.en,
             DbLanguage::Ja => self.ja,
+            DbLanguage::De => self.de,
+            DbLanguage::Fr => self.fr,
         }
     }
 
     pub fn any_eq(&self, str: impl AsRef<str>) -> bool {
-        self.en == str.as_ref() || self.ja == str.as_ref()
+        [self.en, self.ja, self.de, self.fr].contains(&str.as_ref())
     }
 }